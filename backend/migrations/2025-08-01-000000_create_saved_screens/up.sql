@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE saved_screens (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(50) NOT NULL,
+    expression TEXT NOT NULL,
+    last_updated DATE NOT NULL,
+    UNIQUE(name)
+);
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE refresh_jobs (
+    id SERIAL PRIMARY KEY,
+    company_id INTEGER REFERENCES companies(id) ON DELETE CASCADE NOT NULL,
+    kind VARCHAR(20) NOT NULL,
+    state VARCHAR(20) NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    last_error TEXT,
+    next_run_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX refresh_jobs_claim_idx ON refresh_jobs (state, next_run_at);
+
+-- at most one waiting or running job per company
+CREATE UNIQUE INDEX refresh_jobs_active_idx ON refresh_jobs (company_id)
+    WHERE state IN ('queued', 'running');
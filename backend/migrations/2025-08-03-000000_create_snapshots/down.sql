@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE forecast_snapshots;
+DROP TABLE metrics_snapshots;
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE metrics_snapshots (
+    id SERIAL PRIMARY KEY,
+    company_id INTEGER REFERENCES companies(id) ON DELETE CASCADE NOT NULL,
+    reason VARCHAR(10) NOT NULL,
+    taken_at TIMESTAMP NOT NULL,
+    metrics JSONB NOT NULL
+);
+
+CREATE INDEX metrics_snapshots_company_idx ON metrics_snapshots (company_id, taken_at);
+
+CREATE TABLE forecast_snapshots (
+    id SERIAL PRIMARY KEY,
+    company_id INTEGER REFERENCES companies(id) ON DELETE CASCADE NOT NULL,
+    metrics_snapshot_id INTEGER REFERENCES metrics_snapshots(id) ON DELETE CASCADE NOT NULL,
+    taken_at TIMESTAMP NOT NULL,
+    forecast JSONB NOT NULL,
+    UNIQUE(metrics_snapshot_id)
+);
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE price_history (
+    id SERIAL PRIMARY KEY,
+    company_id INTEGER REFERENCES companies(id) ON DELETE CASCADE NOT NULL,
+    date DATE NOT NULL,
+    open DOUBLE PRECISION,
+    high DOUBLE PRECISION,
+    low DOUBLE PRECISION,
+    close DOUBLE PRECISION NOT NULL,
+    volume BIGINT,
+    UNIQUE(company_id, date)
+);
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE forecasts ADD COLUMN price_dcf DOUBLE PRECISION;
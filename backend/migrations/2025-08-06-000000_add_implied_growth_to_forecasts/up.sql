@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE forecasts ADD COLUMN implied_growth DOUBLE PRECISION;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE current_metrics
+    DROP COLUMN piotroski_f_score,
+    DROP COLUMN altman_z_score,
+    DROP COLUMN beneish_m_score;
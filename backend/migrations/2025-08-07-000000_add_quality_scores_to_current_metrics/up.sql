@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE current_metrics
+    ADD COLUMN piotroski_f_score DOUBLE PRECISION,
+    ADD COLUMN altman_z_score DOUBLE PRECISION,
+    ADD COLUMN beneish_m_score DOUBLE PRECISION;
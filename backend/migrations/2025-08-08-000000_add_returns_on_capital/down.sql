@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE current_metrics
+    DROP COLUMN return_on_equity_ttm,
+    DROP COLUMN return_on_equity_short_term_trend,
+    DROP COLUMN return_on_equity_long_term_trend,
+    DROP COLUMN return_on_assets_ttm,
+    DROP COLUMN return_on_assets_short_term_trend,
+    DROP COLUMN return_on_assets_long_term_trend,
+    DROP COLUMN return_on_invested_capital_ttm,
+    DROP COLUMN return_on_invested_capital_short_term_trend,
+    DROP COLUMN return_on_invested_capital_long_term_trend,
+    DROP COLUMN asset_turnover_ttm,
+    DROP COLUMN equity_multiplier_ttm;
+
+ALTER TABLE earnings_report
+    DROP COLUMN return_on_equity,
+    DROP COLUMN return_on_assets,
+    DROP COLUMN return_on_invested_capital,
+    DROP COLUMN effective_tax_rate,
+    DROP COLUMN asset_turnover,
+    DROP COLUMN equity_multiplier;
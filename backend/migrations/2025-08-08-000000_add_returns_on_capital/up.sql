@@ -0,0 +1,24 @@
+-- Your SQL goes here
+ALTER TABLE earnings_report
+    ADD COLUMN return_on_equity DOUBLE PRECISION,
+    ADD COLUMN return_on_assets DOUBLE PRECISION,
+    ADD COLUMN return_on_invested_capital DOUBLE PRECISION,
+    ADD COLUMN effective_tax_rate DOUBLE PRECISION,
+    ADD COLUMN asset_turnover DOUBLE PRECISION,
+    ADD COLUMN equity_multiplier DOUBLE PRECISION;
+
+-- recalculates the ratios of the stored reports on the next update
+UPDATE earnings_report SET ratio_calculated = false;
+
+ALTER TABLE current_metrics
+    ADD COLUMN return_on_equity_ttm DOUBLE PRECISION,
+    ADD COLUMN return_on_equity_short_term_trend TEXT,
+    ADD COLUMN return_on_equity_long_term_trend TEXT,
+    ADD COLUMN return_on_assets_ttm DOUBLE PRECISION,
+    ADD COLUMN return_on_assets_short_term_trend TEXT,
+    ADD COLUMN return_on_assets_long_term_trend TEXT,
+    ADD COLUMN return_on_invested_capital_ttm DOUBLE PRECISION,
+    ADD COLUMN return_on_invested_capital_short_term_trend TEXT,
+    ADD COLUMN return_on_invested_capital_long_term_trend TEXT,
+    ADD COLUMN asset_turnover_ttm DOUBLE PRECISION,
+    ADD COLUMN equity_multiplier_ttm DOUBLE PRECISION;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE TABLE industry_stats (
+    id SERIAL PRIMARY KEY,
+    industry VARCHAR(50) NOT NULL,
+    exchange VARCHAR(9) NOT NULL,
+    metric VARCHAR(50) NOT NULL,
+    count BIGINT NOT NULL,
+    mean DOUBLE PRECISION NOT NULL,
+    median DOUBLE PRECISION NOT NULL,
+    first_quartile DOUBLE PRECISION NOT NULL,
+    third_quartile DOUBLE PRECISION NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    UNIQUE(industry, exchange, metric)
+);
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE industry_margin_factors (
+    id SERIAL PRIMARY KEY,
+    industry VARCHAR(50) NOT NULL UNIQUE,
+    factor DOUBLE PRECISION NOT NULL,
+    source VARCHAR(10) NOT NULL,
+    sample_count INTEGER,
+    updated_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE trend_settings (
+    id SERIAL PRIMARY KEY,
+    metric VARCHAR(50) NOT NULL,
+    term VARCHAR(5) NOT NULL,
+    industry VARCHAR(50) NOT NULL DEFAULT 'All',
+    length INTEGER,
+    flat_threshold DOUBLE PRECISION,
+    count_threshold INTEGER,
+    updated_at TIMESTAMP NOT NULL,
+    UNIQUE(metric, term, industry)
+);
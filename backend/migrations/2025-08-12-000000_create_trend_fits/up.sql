@@ -0,0 +1,77 @@
+-- Your SQL goes here
+CREATE TABLE trend_fits (
+    id SERIAL PRIMARY KEY,
+    company_id INTEGER REFERENCES companies(id) NOT NULL,
+    net_interest_margin_short_term_slope DOUBLE PRECISION,
+    net_interest_margin_short_term_r_squared DOUBLE PRECISION,
+    net_interest_margin_short_term_acceleration TEXT,
+    cost_of_risk_short_term_slope DOUBLE PRECISION,
+    cost_of_risk_short_term_r_squared DOUBLE PRECISION,
+    cost_of_risk_short_term_acceleration TEXT,
+    gross_margin_short_term_slope DOUBLE PRECISION,
+    gross_margin_short_term_r_squared DOUBLE PRECISION,
+    gross_margin_short_term_acceleration TEXT,
+    sga_short_term_slope DOUBLE PRECISION,
+    sga_short_term_r_squared DOUBLE PRECISION,
+    sga_short_term_acceleration TEXT,
+    rnd_short_term_slope DOUBLE PRECISION,
+    rnd_short_term_r_squared DOUBLE PRECISION,
+    rnd_short_term_acceleration TEXT,
+    operating_margin_short_term_slope DOUBLE PRECISION,
+    operating_margin_short_term_r_squared DOUBLE PRECISION,
+    operating_margin_short_term_acceleration TEXT,
+    return_on_equity_short_term_slope DOUBLE PRECISION,
+    return_on_equity_short_term_r_squared DOUBLE PRECISION,
+    return_on_equity_short_term_acceleration TEXT,
+    return_on_assets_short_term_slope DOUBLE PRECISION,
+    return_on_assets_short_term_r_squared DOUBLE PRECISION,
+    return_on_assets_short_term_acceleration TEXT,
+    return_on_invested_capital_short_term_slope DOUBLE PRECISION,
+    return_on_invested_capital_short_term_r_squared DOUBLE PRECISION,
+    return_on_invested_capital_short_term_acceleration TEXT,
+    net_interest_margin_long_term_slope DOUBLE PRECISION,
+    net_interest_margin_long_term_r_squared DOUBLE PRECISION,
+    net_interest_margin_long_term_acceleration TEXT,
+    cost_of_risk_long_term_slope DOUBLE PRECISION,
+    cost_of_risk_long_term_r_squared DOUBLE PRECISION,
+    cost_of_risk_long_term_acceleration TEXT,
+    gross_margin_long_term_slope DOUBLE PRECISION,
+    gross_margin_long_term_r_squared DOUBLE PRECISION,
+    gross_margin_long_term_acceleration TEXT,
+    sga_long_term_slope DOUBLE PRECISION,
+    sga_long_term_r_squared DOUBLE PRECISION,
+    sga_long_term_acceleration TEXT,
+    rnd_long_term_slope DOUBLE PRECISION,
+    rnd_long_term_r_squared DOUBLE PRECISION,
+    rnd_long_term_acceleration TEXT,
+    operating_margin_long_term_slope DOUBLE PRECISION,
+    operating_margin_long_term_r_squared DOUBLE PRECISION,
+    operating_margin_long_term_acceleration TEXT,
+    shares_change_slope DOUBLE PRECISION,
+    shares_change_r_squared DOUBLE PRECISION,
+    shares_change_acceleration TEXT,
+    retained_earnings_slope DOUBLE PRECISION,
+    retained_earnings_r_squared DOUBLE PRECISION,
+    retained_earnings_acceleration TEXT,
+    net_cash_slope DOUBLE PRECISION,
+    net_cash_r_squared DOUBLE PRECISION,
+    net_cash_acceleration TEXT,
+    operating_cash_flow_margin_slope DOUBLE PRECISION,
+    operating_cash_flow_margin_r_squared DOUBLE PRECISION,
+    operating_cash_flow_margin_acceleration TEXT,
+    ffo_margin_slope DOUBLE PRECISION,
+    ffo_margin_r_squared DOUBLE PRECISION,
+    ffo_margin_acceleration TEXT,
+    return_on_equity_long_term_slope DOUBLE PRECISION,
+    return_on_equity_long_term_r_squared DOUBLE PRECISION,
+    return_on_equity_long_term_acceleration TEXT,
+    return_on_assets_long_term_slope DOUBLE PRECISION,
+    return_on_assets_long_term_r_squared DOUBLE PRECISION,
+    return_on_assets_long_term_acceleration TEXT,
+    return_on_invested_capital_long_term_slope DOUBLE PRECISION,
+    return_on_invested_capital_long_term_r_squared DOUBLE PRECISION,
+    return_on_invested_capital_long_term_acceleration TEXT,
+    UNIQUE(company_id)
+);
+
+INSERT INTO trend_fits (company_id) SELECT id FROM companies;
@@ -0,0 +1,253 @@
+use crate::calculate;
+use crate::metrics;
+use crate::metrics::PriceTargets;
+use crate::models::earnings_model::EarningsReport;
+use crate::models::margin_factors_model::MarginFactor;
+use crate::models::prices_model::PriceBar;
+use crate::query;
+use crate::schema::companies;
+use chrono::{Duration, Months, NaiveDate};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// prices further than this from the wanted date are treated as missing
+const MAX_PRICE_GAP_DAYS: i64 = 7;
+/// number of annual reports averaged into the multi-year growth, same as update_multi_yr_growth
+const MULTI_YEAR_REPORTS: usize = 4;
+/// upper bound of periods loaded per company and duration
+const MAX_REPLAYED_PERIODS: i64 = 200;
+const ALL_INDUSTRIES: &str = "All";
+
+/// price target columns of the forecasts table that can be replayed from earnings.
+/// the next-year target is left out because past revenue estimates aren't stored
+const TARGET_TYPES: [&str; 4] = [
+    "priceCurrentRevenueGrowth",
+    "priceCurrentGpGrowth",
+    "priceMultiYearRevenueGrowth",
+    "priceMultiYearGpGrowth",
+];
+
+/// one replayed target compared with realized prices
+struct Sample {
+    target: f64,
+    start_price: f64,
+    realized_price: f64,
+}
+
+/// accuracy of one target type within one industry
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BacktestResult {
+    pub target_type: &'static str,
+    pub industry: String,
+    pub samples: usize,
+    /// share of targets whose direction from the period-end price matched the realized move
+    pub hit_rate: Option<f64>,
+    /// mean of |target - realized| / realized, in percentage
+    pub mean_absolute_error_pct: Option<f64>,
+    /// spearman correlation between implied upside and realized return
+    pub rank_correlation: Option<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BacktestReport {
+    pub horizon_months: u32,
+    pub results: Vec<BacktestResult>,
+}
+
+/// returns the close of the first trading day on or after the date
+fn price_on_or_after(prices: &[PriceBar], date: NaiveDate) -> Option<f64> {
+    let index = prices.partition_point(|bar| bar.date < date);
+    prices
+        .get(index)
+        .filter(|bar| bar.date - date <= Duration::days(MAX_PRICE_GAP_DAYS))
+        .map(|bar| bar.close)
+}
+
+/// replays update_price_target with the data known at the end of the TTM period
+fn replay_targets(
+    report: &EarningsReport,
+    annual: &[EarningsReport],
+    net_margin_factor: f64,
+) -> PriceTargets {
+    let known_annual: Vec<&EarningsReport> = annual
+        .iter()
+        .filter(|data| data.period_ending <= report.period_ending)
+        .take(MULTI_YEAR_REPORTS)
+        .collect();
+    let rev_growth: Vec<Option<f64>> = known_annual
+        .iter()
+        .map(|data| data.revenue_growth_yoy)
+        .collect();
+    let gp_growth: Vec<Option<f64>> = known_annual
+        .iter()
+        .map(|data| data.gross_profit_growth_yoy)
+        .collect();
+    let (theoretical_net_margin, is_optimized) =
+        metrics::is_net_margin_optimized(report, net_margin_factor);
+    let eps = metrics::get_theoretical_eps(
+        Some(report.revenue),
+        Some(report.net_margin),
+        theoretical_net_margin,
+        is_optimized,
+        Some(report.shares_outstanding_diluted),
+    );
+    metrics::get_price_targets(
+        eps,
+        report.revenue_growth_yoy,
+        report.gross_profit_growth_yoy,
+        calculate::average_options(&rev_growth, true),
+        calculate::average_options(&gp_growth, true),
+        Some(report.shares_change_yoy),
+    )
+}
+
+/// ranks values from 1, giving ties their average rank
+fn rank(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let mut ranks = vec![0.; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let average_rank = (start + end + 1) as f64 / 2.;
+        order[start..end]
+            .iter()
+            .for_each(|&index| ranks[index] = average_rank);
+        start = end;
+    }
+    ranks
+}
+
+/// spearman rank correlation. None with fewer than 3 pairs or a constant side
+fn rank_correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    if xs.len() < 3 {
+        return None;
+    }
+    let (x_ranks, y_ranks) = (rank(xs), rank(ys));
+    let mean = (xs.len() + 1) as f64 / 2.;
+    let (mut covariance, mut x_variance, mut y_variance) = (0., 0., 0.);
+    for (x, y) in x_ranks.iter().zip(&y_ranks) {
+        covariance += (x - mean) * (y - mean);
+        x_variance += (x - mean).powi(2);
+        y_variance += (y - mean).powi(2);
+    }
+    if x_variance == 0. || y_variance == 0. {
+        return None;
+    }
+    Some((covariance / (x_variance * y_variance).sqrt() * 100.).round() / 100.)
+}
+
+fn summarize(target_type: &'static str, industry: String, samples: &[Sample]) -> BacktestResult {
+    let count = samples.len() as f64;
+    let hits = samples
+        .iter()
+        .filter(|sample| {
+            (sample.target - sample.start_price) * (sample.realized_price - sample.start_price) > 0.
+        })
+        .count();
+    let absolute_error = samples
+        .iter()
+        .map(|sample| (sample.target - sample.realized_price).abs() / sample.realized_price)
+        .sum::<f64>();
+    let upside: Vec<f64> = samples
+        .iter()
+        .map(|sample| sample.target / sample.start_price - 1.)
+        .collect();
+    let realized_return: Vec<f64> = samples
+        .iter()
+        .map(|sample| sample.realized_price / sample.start_price - 1.)
+        .collect();
+    let has_samples = !samples.is_empty();
+    BacktestResult {
+        target_type,
+        industry,
+        samples: samples.len(),
+        hit_rate: has_samples.then(|| (hits as f64 / count * 10000.).round() / 100.),
+        mean_absolute_error_pct: has_samples
+            .then(|| (absolute_error / count * 10000.).round() / 100.),
+        rank_correlation: rank_correlation(&upside, &realized_return),
+    }
+}
+
+/// replays every stored TTM period through the price target calculation and compares
+/// each target with the price `horizon_months` after the period end.
+/// results are grouped per target type and industry, plus an "All" industry row
+pub fn run_backtest(
+    horizon_months: u32,
+    industry_filter: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<BacktestReport, DieselError> {
+    let mut company_query = companies::table
+        .select((companies::id, companies::industry))
+        .order(companies::id)
+        .into_boxed();
+    if let Some(industry) = industry_filter {
+        company_query = company_query.filter(companies::industry.eq(industry));
+    }
+    let targeted_companies: Vec<(i32, String)> = company_query.load(conn)?;
+
+    // samples per target type and industry
+    let mut groups: BTreeMap<(usize, String), Vec<Sample>> = BTreeMap::new();
+    for (comp_id, industry) in targeted_companies {
+        let prices = PriceBar::load_range(comp_id, None, None, conn)?;
+        if prices.is_empty() {
+            continue;
+        }
+        let ttm = query::load_multiple_earnings_ttm(comp_id, MAX_REPLAYED_PERIODS, conn)?;
+        let annual = query::load_multiple_earnings_annual(comp_id, MAX_REPLAYED_PERIODS, conn)?;
+        let net_margin_factor = MarginFactor::effective(&industry, conn)?.factor;
+        for report in &ttm {
+            let start_price = price_on_or_after(&prices, report.period_ending);
+            let realized_price = report
+                .period_ending
+                .checked_add_months(Months::new(horizon_months))
+                .and_then(|date| price_on_or_after(&prices, date));
+            let (Some(start_price), Some(realized_price)) = (start_price, realized_price) else {
+                continue;
+            };
+            if start_price <= 0. || realized_price <= 0. {
+                continue;
+            }
+            let targets = replay_targets(report, &annual, net_margin_factor);
+            let replayed = [
+                targets.current_revenue_growth,
+                targets.current_gp_growth,
+                targets.multi_year_revenue_growth,
+                targets.multi_year_gp_growth,
+            ];
+            for (target_index, target) in replayed.into_iter().enumerate() {
+                let Some(target) = target else {
+                    continue;
+                };
+                for group_industry in [industry.as_str(), ALL_INDUSTRIES] {
+                    groups
+                        .entry((target_index, group_industry.to_string()))
+                        .or_default()
+                        .push(Sample {
+                            target,
+                            start_price,
+                            realized_price,
+                        });
+                }
+            }
+        }
+    }
+    let results = groups
+        .into_iter()
+        .map(|((target_index, industry), samples)| {
+            summarize(TARGET_TYPES[target_index], industry, &samples)
+        })
+        .collect();
+    Ok(BacktestReport {
+        horizon_months,
+        results,
+    })
+}
@@ -1,4 +1,12 @@
 use crate::models::metrics_model::Trend;
+use crate::models::trend_fits_model::{Acceleration, TrendFit};
+
+/// tax rate assumed when the pretax income is too small to estimate it, in percentage
+const DEFAULT_TAX_RATE: f64 = 21.;
+const MAX_TAX_RATE: f64 = 50.;
+/// a line through two points always fits perfectly, so fewer periods can't be fitted
+const MIN_LINEAR_FIT_POINTS: usize = 3;
+const MIN_QUADRATIC_FIT_POINTS: usize = 4;
 
 pub fn calculate_price_target_option(
     eps: Option<f64>,
@@ -139,6 +147,125 @@ pub fn calculate_long_term_trend_option(
     }
 }
 
+/// fits a line through the values, ordered newest first like the loaded earnings
+pub fn calculate_trend_fit(vals: &[f64], flat_threshold: f64) -> TrendFit {
+    let points: Vec<(f64, f64)> = vals
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, v)| (i as f64, *v))
+        .collect();
+    fit_points(&points, flat_threshold)
+}
+
+/// fits a line through the values, ordered newest first. missing periods are skipped
+/// when ignore_none is set and make the fit irrelevant otherwise
+pub fn calculate_trend_fit_option(
+    vals: &[Option<f64>],
+    ignore_none: bool,
+    flat_threshold: f64,
+) -> TrendFit {
+    if !ignore_none && vals.iter().any(|x| x.is_none()) {
+        return TrendFit::irrelevant();
+    }
+    let points: Vec<(f64, f64)> = vals
+        .iter()
+        .rev()
+        .enumerate()
+        .filter_map(|(i, v)| v.map(|val| (i as f64, val)))
+        .collect();
+    fit_points(&points, flat_threshold)
+}
+
+/// least-squares line through (period, value) points, with the oldest period at 0
+fn fit_points(points: &[(f64, f64)], flat_threshold: f64) -> TrendFit {
+    if points.len() < MIN_LINEAR_FIT_POINTS {
+        return TrendFit::irrelevant();
+    }
+    let n = points.len() as f64;
+    let x_mean = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let y_mean = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx = points
+        .iter()
+        .map(|(x, _)| (x - x_mean).powi(2))
+        .sum::<f64>();
+    let syy = points
+        .iter()
+        .map(|(_, y)| (y - y_mean).powi(2))
+        .sum::<f64>();
+    let sxy = points
+        .iter()
+        .map(|(x, y)| (x - x_mean) * (y - y_mean))
+        .sum::<f64>();
+    let slope = sxy / sxx;
+    // constant values have no variation for the line to explain
+    let r_squared = (syy > 0.).then(|| sxy * sxy / (sxx * syy));
+    TrendFit {
+        slope: Some((slope * 10000.).round() / 10000.),
+        r_squared: r_squared.map(|val| (val * 10000.).round() / 10000.),
+        acceleration: calculate_acceleration(points, x_mean, slope, flat_threshold),
+    }
+}
+
+/// fits a quadratic through the points and compares its slope at the latest period with the
+/// line's. the trend is steady when the curve drifts less than the flat threshold from the line
+/// over the whole window, and accelerating when it gets steeper than the line
+fn calculate_acceleration(
+    points: &[(f64, f64)],
+    x_mean: f64,
+    slope: f64,
+    flat_threshold: f64,
+) -> Acceleration {
+    if points.len() < MIN_QUADRATIC_FIT_POINTS {
+        return Acceleration::Irrelevant;
+    }
+    // centering the periods keeps the normal equations well conditioned
+    let centered: Vec<(f64, f64)> = points.iter().map(|(x, y)| (x - x_mean, *y)).collect();
+    let sum_pow = |k: i32| centered.iter().map(|(u, _)| u.powi(k)).sum::<f64>();
+    let sum_pow_y = |k: i32| centered.iter().map(|(u, y)| u.powi(k) * y).sum::<f64>();
+    let (s0, s1, s2, s3, s4) = (sum_pow(0), sum_pow(1), sum_pow(2), sum_pow(3), sum_pow(4));
+    let coefficients = solve_linear_system(
+        [[s0, s1, s2], [s1, s2, s3], [s2, s3, s4]],
+        [sum_pow_y(0), sum_pow_y(1), sum_pow_y(2)],
+    );
+    let Some([_, linear, quadratic]) = coefficients else {
+        return Acceleration::Irrelevant;
+    };
+    let first = centered.first().map(|(u, _)| *u).unwrap_or_default();
+    let last = centered.last().map(|(u, _)| *u).unwrap_or_default();
+    let latest_slope = linear + 2. * quadratic * last;
+    // a latest slope pointing against the overall trend is a reversal, which counts as decelerating
+    if (latest_slope - slope).abs() * (last - first) < flat_threshold {
+        Acceleration::Steady
+    } else if latest_slope * slope.signum() > slope.abs() {
+        Acceleration::Accelerating
+    } else {
+        Acceleration::Decelerating
+    }
+}
+
+/// solves a 3x3 linear system with Cramer's rule, None when it has no single solution
+fn solve_linear_system(matrix: [[f64; 3]; 3], values: [f64; 3]) -> Option<[f64; 3]> {
+    let determinant = |m: [[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let det = determinant(matrix);
+    if det.abs() < f64::EPSILON {
+        return None;
+    }
+    let mut solution = [0.; 3];
+    for (col, coefficient) in solution.iter_mut().enumerate() {
+        let mut replaced = matrix;
+        for (row, value) in replaced.iter_mut().zip(values) {
+            row[col] = value;
+        }
+        *coefficient = determinant(replaced) / det;
+    }
+    Some(solution)
+}
+
 pub fn calculate_average_growth(growth_vec: Vec<f64>) -> f64 {
     growth_vec.iter().sum::<f64>() / growth_vec.len() as f64
 }
@@ -244,3 +371,18 @@ pub fn calculate_margin_portion(total: Option<f64>, margin: Option<f64>) -> Opti
 pub fn calculate_per_share(total: Option<f64>, share: Option<f64>) -> Option<f64> {
     total.zip(share).map(|(x, y)| x / y)
 }
+
+/// effective tax rate in percentage, estimated from net income over operating income after interest.
+/// interest expenses are stored as negative values
+pub fn calculate_effective_tax_rate(
+    operating_income: f64,
+    interest_expenses: Option<f64>,
+    net_income: f64,
+) -> f64 {
+    let pretax_income = operating_income + interest_expenses.unwrap_or(0.);
+    if pretax_income <= 0. || net_income <= 0. {
+        return DEFAULT_TAX_RATE;
+    }
+    let tax_rate = (1. - net_income / pretax_income) * 100.;
+    (tax_rate.clamp(0., MAX_TAX_RATE) * 100.).round() / 100.
+}
@@ -0,0 +1,92 @@
+use crate::edgar;
+use crate::errors::BullsEyeError;
+use crate::services;
+use crate::transfer::TransferFormat;
+use diesel::pg::PgConnection;
+use std::fs;
+use std::path::Path;
+
+const USAGE: &str = "usage:
+    bullseye import-edgar <companyfacts.json> <ticker> [industry]
+    bullseye export-earnings <file.csv|file.json> [ticker]
+    bullseye import-earnings <file.csv|file.json>
+    bullseye import-prices <file.csv|file.json> <ticker>
+    bullseye backtest [horizon months] [industry]
+    bullseye refresh-industry-stats
+    bullseye derive-margin-factors
+    bullseye recompute-trends";
+
+/// runs an admin command given on the command line instead of starting the server
+pub fn run_command(args: &[String], conn: &mut PgConnection) -> Result<(), BullsEyeError> {
+    match args {
+        [command, path, ticker, rest @ ..] if command == "import-edgar" && rest.len() <= 1 => {
+            let industry = rest.first().map(String::as_str).unwrap_or("Unknown");
+            let count = edgar::import_company_facts(
+                Path::new(path),
+                &ticker.to_uppercase(),
+                industry,
+                conn,
+            )?;
+            println!(
+                "imported {} earnings periods for {}",
+                count,
+                ticker.to_uppercase()
+            );
+            Ok(())
+        }
+        [command, path, rest @ ..] if command == "export-earnings" && rest.len() <= 1 => {
+            let format = TransferFormat::from_path(Path::new(path));
+            let contents =
+                services::export_earnings(rest.first().map(String::as_str), format, conn)?;
+            fs::write(path, contents)?;
+            println!("exported earnings to {}", path);
+            Ok(())
+        }
+        [command, path] if command == "import-earnings" => {
+            let format = TransferFormat::from_path(Path::new(path));
+            let contents = fs::read_to_string(path)?;
+            let count = services::import_earnings(&contents, format, conn)?;
+            println!("imported {} earnings rows from {}", count, path);
+            Ok(())
+        }
+        [command, path, ticker] if command == "import-prices" => {
+            let format = TransferFormat::from_path(Path::new(path));
+            let contents = fs::read_to_string(path)?;
+            let count = services::import_prices(&ticker.to_uppercase(), &contents, format, conn)?;
+            println!(
+                "imported {} daily prices for {}",
+                count,
+                ticker.to_uppercase()
+            );
+            Ok(())
+        }
+        [command, rest @ ..] if command == "backtest" && rest.len() <= 2 => {
+            let horizon_months = match rest.first() {
+                Some(months) => months
+                    .parse()
+                    .map_err(|_| BullsEyeError::InvalidCommandError(USAGE.to_string()))?,
+                None => 12,
+            };
+            let industry = rest.get(1).map(String::as_str);
+            let report = services::run_backtest(horizon_months, industry, conn)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
+        [command] if command == "refresh-industry-stats" => {
+            let count = services::refresh_industry_stats(conn)?;
+            println!("stored {} industry statistics", count);
+            Ok(())
+        }
+        [command] if command == "derive-margin-factors" => {
+            let count = services::derive_margin_factors(conn)?;
+            println!("derived net margin factors of {} industries", count);
+            Ok(())
+        }
+        [command] if command == "recompute-trends" => {
+            let count = services::recompute_all_trends(conn)?;
+            println!("recomputed the trends of {} companies", count);
+            Ok(())
+        }
+        _ => Err(BullsEyeError::InvalidCommandError(USAGE.to_string())),
+    }
+}
@@ -1,14 +1,25 @@
 use crate::calculate;
+use crate::dcf;
+use crate::dcf::{DcfAssumptions, DcfInputs};
 use crate::errors::BullsEyeError;
 use crate::helper;
 use crate::metrics;
 use crate::models::companies_model::Company;
 use crate::models::earnings_model::EarningsReport;
 use crate::models::forecast_models::Forecasts;
+use crate::models::margin_factors_model::MarginFactor;
 use crate::models::metrics_model::CurrentMetrics;
+use crate::models::params_model::SimulationParams;
+use crate::models::prices_model::NewPriceBar;
 use crate::query;
+use crate::scores;
+use crate::simulation;
+use crate::simulation::{
+    Distribution, MonteCarloInputs, MonteCarloResult, Scenario, SimulationResult,
+};
+use crate::trend_config::TrendSettings;
 use bullseye_api::model::Exchange;
-use chrono::{Duration, Local};
+use chrono::{Duration, Local, NaiveDate};
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
@@ -27,6 +38,28 @@ pub fn establish_connection_pool() -> Result<Pool<ConnectionManager<PgConnection
     }
 }
 
+/// namespace of the advisory locks taken per company while refreshing
+const REFRESH_LOCK_NAMESPACE: i32 = 1;
+/// annual reports the monte carlo distributions are fitted to
+const MONTE_CARLO_YEARS: i64 = 10;
+
+diesel::define_sql_function! {
+    fn pg_try_advisory_lock(namespace: diesel::sql_types::Integer, key: diesel::sql_types::Integer) -> diesel::sql_types::Bool;
+}
+diesel::define_sql_function! {
+    fn pg_advisory_unlock(namespace: diesel::sql_types::Integer, key: diesel::sql_types::Integer) -> diesel::sql_types::Bool;
+}
+
+/// takes the session level refresh lock of the company without waiting.
+/// keeps several backend instances from refreshing the same company at once
+pub fn try_lock_company(comp_id: i32, conn: &mut PgConnection) -> Result<bool, DieselError> {
+    diesel::select(pg_try_advisory_lock(REFRESH_LOCK_NAMESPACE, comp_id)).get_result(conn)
+}
+
+pub fn unlock_company(comp_id: i32, conn: &mut PgConnection) -> Result<bool, DieselError> {
+    diesel::select(pg_advisory_unlock(REFRESH_LOCK_NAMESPACE, comp_id)).get_result(conn)
+}
+
 //TODO: add error
 pub fn lookup_exchange(ticker: &str) -> Exchange {
     match &ticker[0..1].parse::<u64>() {
@@ -43,20 +76,6 @@ where
     data.iter().map(f).collect()
 }
 
-/// joins all necessary tables for the list view
-pub fn join_data(
-    conn: &mut PgConnection,
-) -> Result<Vec<(Company, CurrentMetrics, Forecasts)>, DieselError> {
-    use crate::schema::companies::dsl::*;
-    use crate::schema::current_metrics::dsl::*;
-    use crate::schema::forecasts::dsl::*;
-    let all_data = companies
-        .inner_join(current_metrics)
-        .inner_join(forecasts)
-        .load::<(Company, CurrentMetrics, Forecasts)>(conn)?;
-    Ok(all_data)
-}
-
 /// updates all missing ratios for all earnings data
 pub fn update_ratios_batch(conn: &mut PgConnection) -> Result<(), DieselError> {
     use crate::schema::earnings_report::dsl::*;
@@ -98,21 +117,35 @@ pub fn update_earnings_date(
     Ok(())
 }
 
-/// updates current stock price in the metrics table
+/// updates current stock price in the metrics table and adds it to today's price history
 pub fn update_price(
     comp_id: i32,
     price: Option<f64>,
     conn: &mut PgConnection,
 ) -> Result<(), DieselError> {
     use crate::schema::forecasts::dsl::*;
+    let today = Local::now().date_naive();
     query::update_forecasts_table(
         comp_id,
-        (
-            latest_price.eq(price),
-            last_updated.eq(Local::now().date_naive()),
-        ),
+        (latest_price.eq(price), last_updated.eq(today)),
         conn,
     )?;
+    if let Some(price) = price {
+        NewPriceBar::from_price(comp_id, today, price).record_quote(conn)?;
+    }
+    Ok(())
+}
+
+/// stores a streamed price without marking the regular update as done
+pub fn update_latest_price(
+    comp_id: i32,
+    price: f64,
+    today: NaiveDate,
+    conn: &mut PgConnection,
+) -> Result<(), DieselError> {
+    use crate::schema::forecasts::dsl::*;
+    query::update_forecasts_table(comp_id, latest_price.eq(price), conn)?;
+    NewPriceBar::from_price(comp_id, today, price).record_quote(conn)?;
     Ok(())
 }
 
@@ -153,25 +186,69 @@ pub fn update_multi_yr_growth(comp_id: i32, conn: &mut PgConnection) -> Result<(
     Ok(())
 }
 
-/// updates all short term trends columns in the metrics table
+/// updates all short term trends columns in the metrics table and their fits
 pub fn update_short_term_trends(
     comp_id: i32,
     conn: &mut PgConnection,
 ) -> Result<CurrentMetrics, DieselError> {
     use crate::schema::current_metrics::dsl::*;
-    let target = query::load_multiple_earnings_ttm(comp_id, 8, conn)?;
-    let nim_trend =
-        metrics::get_short_term_trend_option(&target, |f| f.net_interest_margin, 4, true, 0.5, 2);
-    let cor_trend =
-        metrics::get_short_term_trend_option(&target, |f| f.cost_of_risk, 4, true, 0.5, 2);
-    let gross_margin_trend =
-        metrics::get_short_term_trend_option(&target, |f| f.gross_margin, 4, true, 0.5, 2);
-    let sga_ratio_trend =
-        metrics::get_short_term_trend_option(&target, |f| f.sga_gp_ratio, 4, true, 0.01, 2);
-    let rnd_ratio_trend =
-        metrics::get_short_term_trend_option(&target, |f| f.rnd_gp_ratio, 4, true, 0.01, 2);
-    let operating_margin_trend =
-        metrics::get_short_term_trend(&target, |f| f.operating_margin, 4, 0.5, 2);
+    let company = Company::load_by_id(comp_id, conn)?;
+    let settings = TrendSettings::load(&company.industry, conn)?;
+    let target = query::load_multiple_earnings_ttm(comp_id, settings.short_term_rows(), conn)?;
+    let short_term = |metric| settings.short_term(metric);
+    let (nim_trend, nim_fit) = metrics::get_short_term_trend_option(
+        &target,
+        |f| f.net_interest_margin,
+        true,
+        short_term("net_interest_margin"),
+    );
+    let (cor_trend, cor_fit) = metrics::get_short_term_trend_option(
+        &target,
+        |f| f.cost_of_risk,
+        true,
+        short_term("cost_of_risk"),
+    );
+    let (gross_margin_trend, gross_margin_fit) = metrics::get_short_term_trend_option(
+        &target,
+        |f| f.gross_margin,
+        true,
+        short_term("gross_margin"),
+    );
+    let (sga_ratio_trend, sga_ratio_fit) = metrics::get_short_term_trend_option(
+        &target,
+        |f| f.sga_gp_ratio,
+        true,
+        short_term("sga_gp_ratio"),
+    );
+    let (rnd_ratio_trend, rnd_ratio_fit) = metrics::get_short_term_trend_option(
+        &target,
+        |f| f.rnd_gp_ratio,
+        true,
+        short_term("rnd_gp_ratio"),
+    );
+    let (operating_margin_trend, operating_margin_fit) = metrics::get_short_term_trend(
+        &target,
+        |f| f.operating_margin,
+        short_term("operating_margin"),
+    );
+    let (roe_trend, roe_fit) = metrics::get_short_term_trend_option(
+        &target,
+        |f| f.return_on_equity,
+        true,
+        short_term("return_on_equity"),
+    );
+    let (roa_trend, roa_fit) = metrics::get_short_term_trend_option(
+        &target,
+        |f| f.return_on_assets,
+        true,
+        short_term("return_on_assets"),
+    );
+    let (roic_trend, roic_fit) = metrics::get_short_term_trend_option(
+        &target,
+        |f| f.return_on_invested_capital,
+        true,
+        short_term("return_on_invested_capital"),
+    );
     // query::update_metrics_table(
     //     comp_id,
     //     (
@@ -191,36 +268,139 @@ pub fn update_short_term_trends(
             sga_short_term_trend.eq(sga_ratio_trend),
             rnd_short_term_trend.eq(rnd_ratio_trend),
             operating_margin_short_term_trend.eq(operating_margin_trend),
+            return_on_equity_short_term_trend.eq(roe_trend),
+            return_on_assets_short_term_trend.eq(roa_trend),
+            return_on_invested_capital_short_term_trend.eq(roic_trend),
         ),
         conn,
     )?;
+    {
+        use crate::schema::trend_fits::dsl::*;
+        query::update_trend_fits_table(
+            comp_id,
+            (
+                net_interest_margin_short_term_slope.eq(nim_fit.slope),
+                net_interest_margin_short_term_r_squared.eq(nim_fit.r_squared),
+                net_interest_margin_short_term_acceleration.eq(nim_fit.acceleration),
+                cost_of_risk_short_term_slope.eq(cor_fit.slope),
+                cost_of_risk_short_term_r_squared.eq(cor_fit.r_squared),
+                cost_of_risk_short_term_acceleration.eq(cor_fit.acceleration),
+                gross_margin_short_term_slope.eq(gross_margin_fit.slope),
+                gross_margin_short_term_r_squared.eq(gross_margin_fit.r_squared),
+                gross_margin_short_term_acceleration.eq(gross_margin_fit.acceleration),
+                sga_short_term_slope.eq(sga_ratio_fit.slope),
+                sga_short_term_r_squared.eq(sga_ratio_fit.r_squared),
+                sga_short_term_acceleration.eq(sga_ratio_fit.acceleration),
+                rnd_short_term_slope.eq(rnd_ratio_fit.slope),
+                rnd_short_term_r_squared.eq(rnd_ratio_fit.r_squared),
+                rnd_short_term_acceleration.eq(rnd_ratio_fit.acceleration),
+                operating_margin_short_term_slope.eq(operating_margin_fit.slope),
+                operating_margin_short_term_r_squared.eq(operating_margin_fit.r_squared),
+                operating_margin_short_term_acceleration.eq(operating_margin_fit.acceleration),
+                return_on_equity_short_term_slope.eq(roe_fit.slope),
+                return_on_equity_short_term_r_squared.eq(roe_fit.r_squared),
+                return_on_equity_short_term_acceleration.eq(roe_fit.acceleration),
+                return_on_assets_short_term_slope.eq(roa_fit.slope),
+                return_on_assets_short_term_r_squared.eq(roa_fit.r_squared),
+                return_on_assets_short_term_acceleration.eq(roa_fit.acceleration),
+                return_on_invested_capital_short_term_slope.eq(roic_fit.slope),
+                return_on_invested_capital_short_term_r_squared.eq(roic_fit.r_squared),
+                return_on_invested_capital_short_term_acceleration.eq(roic_fit.acceleration),
+            ),
+            conn,
+        )?;
+    }
     Ok(updated_row)
 }
 
-/// updates all long term trends columns in the metrics table
+/// updates all long term trends columns in the metrics table and their fits
 pub fn update_long_term_trends(
     comp_id: i32,
     conn: &mut PgConnection,
 ) -> Result<CurrentMetrics, DieselError> {
     use crate::schema::current_metrics::dsl::*;
-    let target = query::load_multiple_earnings_annual(comp_id, 6, conn)?;
-    let nim_trend =
-        metrics::get_long_term_trend_option(&target, |f| f.net_interest_margin, false, 1.);
-    let cor_trend = metrics::get_long_term_trend_option(&target, |f| f.cost_of_risk, false, 1.);
-    let gross_margin_trend =
-        metrics::get_long_term_trend_option(&target, |f| f.gross_margin, false, 1.);
-    let sga_ratio_trend =
-        metrics::get_long_term_trend_option(&target, |f| f.sga_gp_ratio, false, 0.02);
-    let rnd_ratio_trend =
-        metrics::get_long_term_trend_option(&target, |f| f.rnd_gp_ratio, false, 0.02);
-    let operating_margin_trend = metrics::get_long_term_trend(&target, |f| f.operating_margin, 1.);
-    let dilution_trend = metrics::get_long_term_trend(&target, |f| f.shares_change_yoy, 2.);
-    let retained_earnings_change_trend =
-        metrics::get_long_term_trend(&target, |f| f.retained_earnings, 100.);
-    let net_cash_change_trend = metrics::get_long_term_trend(&target, |f| f.net_cash, 100.);
-    let ocfm_trend =
-        metrics::get_long_term_trend_option(&target, |f| f.operating_cash_flow_margin, false, 1.);
-    let ffom_trend = metrics::get_long_term_trend_option(&target, |f| f.ffo_margin, false, 1.);
+    let company = Company::load_by_id(comp_id, conn)?;
+    let settings = TrendSettings::load(&company.industry, conn)?;
+    let target = query::load_multiple_earnings_annual(comp_id, settings.long_term_rows(), conn)?;
+    let long_term = |metric| settings.long_term(metric);
+    let (nim_trend, nim_fit) = metrics::get_long_term_trend_option(
+        &target,
+        |f| f.net_interest_margin,
+        false,
+        long_term("net_interest_margin"),
+    );
+    let (cor_trend, cor_fit) = metrics::get_long_term_trend_option(
+        &target,
+        |f| f.cost_of_risk,
+        false,
+        long_term("cost_of_risk"),
+    );
+    let (gross_margin_trend, gross_margin_fit) = metrics::get_long_term_trend_option(
+        &target,
+        |f| f.gross_margin,
+        false,
+        long_term("gross_margin"),
+    );
+    let (sga_ratio_trend, sga_ratio_fit) = metrics::get_long_term_trend_option(
+        &target,
+        |f| f.sga_gp_ratio,
+        false,
+        long_term("sga_gp_ratio"),
+    );
+    let (rnd_ratio_trend, rnd_ratio_fit) = metrics::get_long_term_trend_option(
+        &target,
+        |f| f.rnd_gp_ratio,
+        false,
+        long_term("rnd_gp_ratio"),
+    );
+    let (operating_margin_trend, operating_margin_fit) = metrics::get_long_term_trend(
+        &target,
+        |f| f.operating_margin,
+        long_term("operating_margin"),
+    );
+    let (dilution_trend, dilution_fit) = metrics::get_long_term_trend(
+        &target,
+        |f| f.shares_change_yoy,
+        long_term("shares_change_yoy"),
+    );
+    let (retained_earnings_change_trend, retained_earnings_change_fit) =
+        metrics::get_long_term_trend(
+            &target,
+            |f| f.retained_earnings,
+            long_term("retained_earnings"),
+        );
+    let (net_cash_change_trend, net_cash_change_fit) =
+        metrics::get_long_term_trend(&target, |f| f.net_cash, long_term("net_cash"));
+    let (ocfm_trend, ocfm_fit) = metrics::get_long_term_trend_option(
+        &target,
+        |f| f.operating_cash_flow_margin,
+        false,
+        long_term("operating_cash_flow_margin"),
+    );
+    let (ffom_trend, ffom_fit) = metrics::get_long_term_trend_option(
+        &target,
+        |f| f.ffo_margin,
+        false,
+        long_term("ffo_margin"),
+    );
+    let (roe_trend, roe_fit) = metrics::get_long_term_trend_option(
+        &target,
+        |f| f.return_on_equity,
+        false,
+        long_term("return_on_equity"),
+    );
+    let (roa_trend, roa_fit) = metrics::get_long_term_trend_option(
+        &target,
+        |f| f.return_on_assets,
+        false,
+        long_term("return_on_assets"),
+    );
+    let (roic_trend, roic_fit) = metrics::get_long_term_trend_option(
+        &target,
+        |f| f.return_on_invested_capital,
+        false,
+        long_term("return_on_invested_capital"),
+    );
 
     let updated_row = query::update_and_return_table(
         current_metrics.filter(company_id.eq(comp_id)),
@@ -236,9 +416,63 @@ pub fn update_long_term_trends(
             net_cash_trend.eq(net_cash_change_trend),
             operating_cash_flow_margin_trend.eq(ocfm_trend),
             ffo_margin_trend.eq(ffom_trend),
+            return_on_equity_long_term_trend.eq(roe_trend),
+            return_on_assets_long_term_trend.eq(roa_trend),
+            return_on_invested_capital_long_term_trend.eq(roic_trend),
         ),
         conn,
     )?;
+    {
+        use crate::schema::trend_fits::dsl::*;
+        query::update_trend_fits_table(
+            comp_id,
+            (
+                net_interest_margin_long_term_slope.eq(nim_fit.slope),
+                net_interest_margin_long_term_r_squared.eq(nim_fit.r_squared),
+                net_interest_margin_long_term_acceleration.eq(nim_fit.acceleration),
+                cost_of_risk_long_term_slope.eq(cor_fit.slope),
+                cost_of_risk_long_term_r_squared.eq(cor_fit.r_squared),
+                cost_of_risk_long_term_acceleration.eq(cor_fit.acceleration),
+                gross_margin_long_term_slope.eq(gross_margin_fit.slope),
+                gross_margin_long_term_r_squared.eq(gross_margin_fit.r_squared),
+                gross_margin_long_term_acceleration.eq(gross_margin_fit.acceleration),
+                sga_long_term_slope.eq(sga_ratio_fit.slope),
+                sga_long_term_r_squared.eq(sga_ratio_fit.r_squared),
+                sga_long_term_acceleration.eq(sga_ratio_fit.acceleration),
+                rnd_long_term_slope.eq(rnd_ratio_fit.slope),
+                rnd_long_term_r_squared.eq(rnd_ratio_fit.r_squared),
+                rnd_long_term_acceleration.eq(rnd_ratio_fit.acceleration),
+                operating_margin_long_term_slope.eq(operating_margin_fit.slope),
+                operating_margin_long_term_r_squared.eq(operating_margin_fit.r_squared),
+                operating_margin_long_term_acceleration.eq(operating_margin_fit.acceleration),
+                shares_change_slope.eq(dilution_fit.slope),
+                shares_change_r_squared.eq(dilution_fit.r_squared),
+                shares_change_acceleration.eq(dilution_fit.acceleration),
+                retained_earnings_slope.eq(retained_earnings_change_fit.slope),
+                retained_earnings_r_squared.eq(retained_earnings_change_fit.r_squared),
+                retained_earnings_acceleration.eq(retained_earnings_change_fit.acceleration),
+                net_cash_slope.eq(net_cash_change_fit.slope),
+                net_cash_r_squared.eq(net_cash_change_fit.r_squared),
+                net_cash_acceleration.eq(net_cash_change_fit.acceleration),
+                operating_cash_flow_margin_slope.eq(ocfm_fit.slope),
+                operating_cash_flow_margin_r_squared.eq(ocfm_fit.r_squared),
+                operating_cash_flow_margin_acceleration.eq(ocfm_fit.acceleration),
+                ffo_margin_slope.eq(ffom_fit.slope),
+                ffo_margin_r_squared.eq(ffom_fit.r_squared),
+                ffo_margin_acceleration.eq(ffom_fit.acceleration),
+                return_on_equity_long_term_slope.eq(roe_fit.slope),
+                return_on_equity_long_term_r_squared.eq(roe_fit.r_squared),
+                return_on_equity_long_term_acceleration.eq(roe_fit.acceleration),
+                return_on_assets_long_term_slope.eq(roa_fit.slope),
+                return_on_assets_long_term_r_squared.eq(roa_fit.r_squared),
+                return_on_assets_long_term_acceleration.eq(roa_fit.acceleration),
+                return_on_invested_capital_long_term_slope.eq(roic_fit.slope),
+                return_on_invested_capital_long_term_r_squared.eq(roic_fit.r_squared),
+                return_on_invested_capital_long_term_acceleration.eq(roic_fit.acceleration),
+            ),
+            conn,
+        )?;
+    }
     Ok(updated_row)
 }
 
@@ -280,6 +514,11 @@ pub fn copy_latest_data(comp_id: i32, conn: &mut PgConnection) -> Result<(), Die
             free_cash_flow_ttm.eq(latest_earnings_ttm.free_cash_flow),
             free_cash_flow_margin_ttm.eq(latest_earnings_ttm.free_cash_flow_margin),
             ffo_margin_ttm.eq(latest_earnings_ttm.ffo_margin),
+            return_on_equity_ttm.eq(latest_earnings_ttm.return_on_equity),
+            return_on_assets_ttm.eq(latest_earnings_ttm.return_on_assets),
+            return_on_invested_capital_ttm.eq(latest_earnings_ttm.return_on_invested_capital),
+            asset_turnover_ttm.eq(latest_earnings_ttm.asset_turnover),
+            equity_multiplier_ttm.eq(latest_earnings_ttm.equity_multiplier),
         ),
         conn,
     )?;
@@ -296,39 +535,21 @@ pub fn update_price_target(comp_id: i32, conn: &mut PgConnection) -> Result<(),
     let company: Company =
         query::load_first_row(companies.filter(companies::id.eq(comp_id)), conn)?;
     let target_metrics = CurrentMetrics::load_by_id(comp_id, conn)?;
-    let net_margin_factor = calculate::get_net_margin_factor(&company.industry);
+    let net_margin_factor = MarginFactor::effective(&company.industry, conn)?.factor;
     let (curr_theoretical_net_margin, is_optimized) =
         metrics::is_net_margin_optimized(&latest_earnings, net_margin_factor);
-    let curr_theoretical_net_income = match is_optimized {
-        true => calculate::calculate_margin_portion(
-            target_metrics.revenue_ttm,
-            target_metrics.net_margin_ttm,
-        ),
-        false => target_metrics
-            .revenue_ttm
-            .map(|val| val * curr_theoretical_net_margin / 100.),
-    };
-    let curr_theoretical_eps = calculate::calculate_per_share(
-        curr_theoretical_net_income,
+    let curr_theoretical_eps = metrics::get_theoretical_eps(
+        target_metrics.revenue_ttm,
+        target_metrics.net_margin_ttm,
+        curr_theoretical_net_margin,
+        is_optimized,
         target_metrics.shares_outstanding_diluted_ttm,
     );
-    let curr_theoretical_price_rev = calculate::calculate_price_target_option(
+    let targets = metrics::get_price_targets(
         curr_theoretical_eps,
         target_metrics.revenue_growth_yoy_ttm,
-        target_metrics.shares_change_ttm,
-    );
-    let curr_theoretical_price_gp = calculate::calculate_price_target_option(
-        curr_theoretical_eps,
         target_metrics.gross_profit_growth_yoy_ttm,
-        target_metrics.shares_change_ttm,
-    );
-    let curr_theoretical_price_multi_rev = calculate::calculate_price_target_option(
-        curr_theoretical_eps,
         target_metrics.revenue_growth_multi_year,
-        target_metrics.shares_change_ttm,
-    );
-    let curr_theoretical_price_multi_gp = calculate::calculate_price_target_option(
-        curr_theoretical_eps,
         target_metrics.gross_profit_growth_multi_year,
         target_metrics.shares_change_ttm,
     );
@@ -344,10 +565,10 @@ pub fn update_price_target(comp_id: i32, conn: &mut PgConnection) -> Result<(),
     query::update_forecasts_table(
         comp_id,
         (
-            price_current_revenue_growth.eq(curr_theoretical_price_rev),
-            price_current_gp_growth.eq(curr_theoretical_price_gp),
-            price_multi_year_revenue_growth.eq(curr_theoretical_price_multi_rev),
-            price_multi_year_gp_growth.eq(curr_theoretical_price_multi_gp),
+            price_current_revenue_growth.eq(targets.current_revenue_growth),
+            price_current_gp_growth.eq(targets.current_gp_growth),
+            price_multi_year_revenue_growth.eq(targets.multi_year_revenue_growth),
+            price_multi_year_gp_growth.eq(targets.multi_year_gp_growth),
         ),
         conn,
     )?;
@@ -392,27 +613,117 @@ pub fn update_guidance(comp_id: i32, conn: &mut PgConnection) -> Result<(), Dies
     Ok(())
 }
 
-// pub fn run_sim<'a>(
-//     symbol: &str,
-//     exc: &str,
-//     sim_net_margin: u8,
-//     sim_growth: u8,
-//     conn: &mut PgConnection,
-// ) -> f64 {
-//     use crate::schema::stock_data::dsl::*;
-//     let target = stock_data
-//         .filter(ticker.eq(symbol))
-//         .filter(exchange.eq(exc))
-//         .filter(duration.eq("T"))
-//         .order((year_str.desc(), quarter_str.desc()))
-//         .limit(1)
-//         .first::<StockData>(conn)
-//         .expect("Cannot load database. Failed to simulate");
-//     let sim_net_margin: f64 = sim_net_margin as f64;
-//     let sim_growth: f64 = sim_growth as f64;
-//     let sim_theoretical_eps =
-//         target.revenue() * sim_net_margin / 100. / target.shares_outstanding_diluted();
-//     let sim_price = sim_theoretical_eps
-//         * calculate::calculate_growth_adjustment_factor(sim_growth - target.shares_change_yoy());
-//     sim_price
-// }
+/// updates the piotroski, altman and beneish scores in the metrics table from the latest two
+/// annual reports. altman uses the latest price for the market value of equity
+pub fn update_quality_scores(comp_id: i32, conn: &mut PgConnection) -> Result<(), DieselError> {
+    use crate::schema::current_metrics::dsl::*;
+    let target = query::load_multiple_earnings_annual(comp_id, 2, conn)?;
+    let Some(curr) = target.first() else {
+        return Ok(());
+    };
+    let prev = target.get(1);
+    let target_forecast = Forecasts::load_by_id(comp_id, conn)?;
+    let market_cap = target_forecast
+        .latest_price
+        .map(|price| price * curr.shares_outstanding_diluted);
+    query::update_metrics_table(
+        comp_id,
+        (
+            piotroski_f_score
+                .eq(prev.and_then(|prev| scores::calculate_piotroski_f_score(curr, prev))),
+            altman_z_score.eq(scores::calculate_altman_z_score(curr, market_cap)),
+            beneish_m_score.eq(prev.and_then(|prev| scores::calculate_beneish_m_score(curr, prev))),
+        ),
+        conn,
+    )?;
+    Ok(())
+}
+
+/// updates the discounted cash flow value per share and the growth implied by the current price
+/// in the forecasts table
+pub fn update_dcf(comp_id: i32, conn: &mut PgConnection) -> Result<(), DieselError> {
+    use crate::schema::forecasts::dsl::*;
+    let target_metrics = CurrentMetrics::load_by_id(comp_id, conn)?;
+    let target_forecast = Forecasts::load_by_id(comp_id, conn)?;
+    let inputs = DcfInputs {
+        free_cash_flow: target_metrics.free_cash_flow_ttm,
+        revenue_growth_next_year: target_forecast.revenue_growth_next_year,
+        revenue_growth_multi_year: target_metrics.revenue_growth_multi_year,
+        net_cash: target_metrics.net_cash_ttm,
+        shares_outstanding_diluted: target_metrics.shares_outstanding_diluted_ttm,
+    };
+    let assumptions = DcfAssumptions::from_env();
+    let value = dcf::calculate_dcf(&inputs, &assumptions);
+    let growth = target_forecast
+        .latest_price
+        .and_then(|price| dcf::calculate_implied_growth(&inputs, price, &assumptions));
+    query::update_forecasts_table(
+        comp_id,
+        (price_dcf.eq(value), implied_growth.eq(growth)),
+        conn,
+    )?;
+    Ok(())
+}
+
+/// simulates price targets with the given overrides on top of the stored metrics.
+/// nothing is written to the database
+pub fn run_sim(
+    comp_id: i32,
+    params: &SimulationParams,
+    conn: &mut PgConnection,
+) -> Result<SimulationResult, DieselError> {
+    let target_metrics = CurrentMetrics::load_by_id(comp_id, conn)?;
+    let target_forecast = Forecasts::load_by_id(comp_id, conn)?;
+    let stored_net_margin = match target_metrics.is_net_margin_optimized {
+        Some(true) => target_metrics.net_margin_ttm,
+        _ => target_metrics.theoretical_net_margin,
+    };
+    let revenue_growth_next_year = match params.revenue_next_year {
+        Some(next_yr_rev) => EarningsReport::latest_annual_data(comp_id, conn)
+            .optional()?
+            .map(|earnings| calculate::calculate_yoy_growth(next_yr_rev, earnings.revenue)),
+        None => target_forecast.revenue_growth_next_year,
+    };
+    let scenario = Scenario {
+        revenue: target_metrics.revenue_ttm,
+        net_margin: params.net_margin.or(stored_net_margin),
+        shares_outstanding_diluted: target_metrics.shares_outstanding_diluted_ttm,
+        revenue_growth: params
+            .revenue_growth
+            .or(target_metrics.revenue_growth_yoy_ttm),
+        share_change: params.share_change.or(target_metrics.shares_change_ttm),
+        revenue_next_year: params
+            .revenue_next_year
+            .or(target_forecast.revenue_next_year),
+        revenue_growth_next_year,
+    };
+    Ok(simulation::simulate(scenario))
+}
+
+/// fits margin, growth and dilution distributions to the annual reports and simulates the price
+/// target. None when the company lacks the revenue, shares or history to fit them
+pub fn run_monte_carlo(
+    comp_id: i32,
+    runs: usize,
+    seed: u64,
+    conn: &mut PgConnection,
+) -> Result<Option<MonteCarloResult>, DieselError> {
+    let target_metrics = CurrentMetrics::load_by_id(comp_id, conn)?;
+    let history = query::load_multiple_earnings_annual(comp_id, MONTE_CARLO_YEARS, conn)?;
+    let net_margins: Vec<f64> = history.iter().map(|data| data.net_margin).collect();
+    let revenue_growths: Vec<f64> = history
+        .iter()
+        .filter_map(|data| data.revenue_growth_yoy)
+        .collect();
+    let share_changes: Vec<f64> = history.iter().map(|data| data.shares_change_yoy).collect();
+    let inputs = (|| {
+        Some(MonteCarloInputs {
+            revenue: target_metrics.revenue_ttm?,
+            shares_outstanding_diluted: target_metrics.shares_outstanding_diluted_ttm?,
+            net_margin: Distribution::fit(&net_margins)?,
+            revenue_growth: Distribution::fit(&revenue_growths)?,
+            share_change: Distribution::fit(&share_changes)?,
+        })
+    })();
+    Ok(inputs.map(|inputs| simulation::run_monte_carlo(&inputs, runs, seed)))
+}
@@ -0,0 +1,139 @@
+use std::env;
+
+const DEFAULT_DISCOUNT_RATE: f64 = 10.;
+const DEFAULT_TERMINAL_GROWTH: f64 = 2.5;
+const DEFAULT_PROJECTION_YEARS: usize = 5;
+/// projected growth is capped both ways so a single boom or bust year doesn't dominate
+const MAX_PROJECTED_GROWTH: f64 = 50.;
+/// range of yearly growth searched by the reverse DCF, in percentage
+const MIN_IMPLIED_GROWTH: f64 = -50.;
+const MAX_IMPLIED_GROWTH: f64 = 100.;
+const BISECTION_STEPS: usize = 50;
+
+/// discount rate and terminal growth in percentage, and the number of explicitly projected years
+#[derive(Clone, Copy)]
+pub struct DcfAssumptions {
+    pub discount_rate: f64,
+    pub terminal_growth: f64,
+    pub years: usize,
+}
+
+impl DcfAssumptions {
+    /// reads DCF_DISCOUNT_RATE (10 by default), DCF_TERMINAL_GROWTH (2.5 by default)
+    /// and DCF_PROJECTION_YEARS (5 by default)
+    pub fn from_env() -> Self {
+        DcfAssumptions {
+            discount_rate: env_or("DCF_DISCOUNT_RATE", DEFAULT_DISCOUNT_RATE),
+            terminal_growth: env_or("DCF_TERMINAL_GROWTH", DEFAULT_TERMINAL_GROWTH),
+            years: env_or("DCF_PROJECTION_YEARS", DEFAULT_PROJECTION_YEARS),
+        }
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|val| val.parse().ok())
+        .unwrap_or(default)
+}
+
+/// company figures the valuation starts from. amounts are in millions, growth in percentage
+pub struct DcfInputs {
+    pub free_cash_flow: Option<f64>,
+    /// growth of the first projected year, taken from the next year revenue estimate
+    pub revenue_growth_next_year: Option<f64>,
+    pub revenue_growth_multi_year: Option<f64>,
+    pub net_cash: Option<f64>,
+    pub shares_outstanding_diluted: Option<f64>,
+}
+
+/// growth of every projected year. the first year follows the estimate when there is one,
+/// then growth fades linearly from the multi-year rate to the terminal growth
+pub fn project_growth(inputs: &DcfInputs, assumptions: &DcfAssumptions) -> Option<Vec<f64>> {
+    let multi_year = inputs
+        .revenue_growth_multi_year
+        .or(inputs.revenue_growth_next_year)?
+        .clamp(-MAX_PROJECTED_GROWTH, MAX_PROJECTED_GROWTH);
+    let first_year = inputs
+        .revenue_growth_next_year
+        .unwrap_or(multi_year)
+        .clamp(-MAX_PROJECTED_GROWTH, MAX_PROJECTED_GROWTH);
+    let years = assumptions.years.max(1);
+    Some(
+        (0..years)
+            .map(|year| match year {
+                0 => first_year,
+                _ => {
+                    let progress = year as f64 / years as f64;
+                    multi_year + (assumptions.terminal_growth - multi_year) * progress
+                }
+            })
+            .collect(),
+    )
+}
+
+/// intrinsic value per share from projected free cash flow.
+/// the terminal value uses the gordon growth model and net cash is added to the enterprise value,
+/// so net debt lowers the equity value
+pub fn calculate_intrinsic_value(
+    inputs: &DcfInputs,
+    growth: &[f64],
+    assumptions: &DcfAssumptions,
+) -> Option<f64> {
+    intrinsic_value(inputs, growth, assumptions).map(|val| (val * 100.).round() / 100.)
+}
+
+fn intrinsic_value(
+    inputs: &DcfInputs,
+    growth: &[f64],
+    assumptions: &DcfAssumptions,
+) -> Option<f64> {
+    let free_cash_flow = inputs.free_cash_flow.filter(|val| *val > 0.)?;
+    let shares = inputs.shares_outstanding_diluted.filter(|val| *val > 0.)?;
+    let discount_rate = assumptions.discount_rate / 100.;
+    let terminal_growth = assumptions.terminal_growth / 100.;
+    if discount_rate <= terminal_growth || growth.is_empty() {
+        return None;
+    }
+    let mut cash_flow = free_cash_flow;
+    let mut present_value = 0.;
+    for (year, growth_pct) in growth.iter().enumerate() {
+        cash_flow *= 1. + growth_pct / 100.;
+        present_value += cash_flow / (1. + discount_rate).powi(year as i32 + 1);
+    }
+    let terminal_value = cash_flow * (1. + terminal_growth) / (discount_rate - terminal_growth);
+    present_value += terminal_value / (1. + discount_rate).powi(growth.len() as i32);
+    let equity_value = present_value + inputs.net_cash.unwrap_or(0.);
+    Some(equity_value.max(0.) / shares)
+}
+
+/// solves for the growth rate, constant over the projected years, at which the DCF value
+/// equals the price. None when the price is out of reach within the searched growth range
+pub fn calculate_implied_growth(
+    inputs: &DcfInputs,
+    price: f64,
+    assumptions: &DcfAssumptions,
+) -> Option<f64> {
+    let years = assumptions.years.max(1);
+    let value_at = |growth: f64| intrinsic_value(inputs, &vec![growth; years], assumptions);
+    let (mut low, mut high) = (MIN_IMPLIED_GROWTH, MAX_IMPLIED_GROWTH);
+    // the value rises with growth, so the price has to sit between both ends
+    if price <= 0. || value_at(low)? > price || value_at(high)? < price {
+        return None;
+    }
+    for _ in 0..BISECTION_STEPS {
+        let mid = (low + high) / 2.;
+        if value_at(mid)? < price {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some(((low + high) / 2. * 100.).round() / 100.)
+}
+
+/// projects growth and returns the intrinsic value per share
+pub fn calculate_dcf(inputs: &DcfInputs, assumptions: &DcfAssumptions) -> Option<f64> {
+    let growth = project_growth(inputs, assumptions)?;
+    calculate_intrinsic_value(inputs, &growth, assumptions)
+}
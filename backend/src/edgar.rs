@@ -0,0 +1,590 @@
+use crate::calculate;
+use crate::db;
+use crate::errors::BullsEyeError;
+use crate::models::companies_model::Company;
+use crate::models::earnings_model;
+use crate::models::earnings_model::NewEarningsReport;
+use crate::services;
+use crate::source::Profile;
+use bullseye_api::model::get_exchange_string;
+use chrono::{Datelike, NaiveDate};
+use diesel::pg::PgConnection;
+use diesel::Connection;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// EDGAR reports raw values while the rest of the app stores millions
+const MILLION: f64 = 1_000_000.;
+const EDGAR_CURRENCY: &str = "USD";
+/// fiscal years ending up to this day of the calendar year (Feb 7) mostly cover the previous year
+const LATE_FISCAL_YEAR_END_ORDINAL: u32 = 38;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyFacts {
+    pub cik: u64,
+    pub entity_name: String,
+    facts: Facts,
+}
+
+#[derive(Deserialize)]
+struct Facts {
+    #[serde(rename = "us-gaap", default)]
+    us_gaap: HashMap<String, Concept>,
+}
+
+#[derive(Deserialize)]
+struct Concept {
+    units: HashMap<String, Vec<Fact>>,
+}
+
+#[derive(Deserialize)]
+struct Fact {
+    start: Option<NaiveDate>,
+    end: NaiveDate,
+    val: f64,
+    filed: Option<NaiveDate>,
+}
+
+/// reporting period as (start, end). Balance sheet values have no start
+type Period = (Option<NaiveDate>, NaiveDate);
+
+/// values of one line item, merged from several XBRL tags.
+/// earlier tags take priority and restated values replace older filings
+struct ConceptValues {
+    values: HashMap<Period, f64>,
+}
+
+impl ConceptValues {
+    fn from_tags(company_facts: &CompanyFacts, tags: &[&str], unit: &str, scale: f64) -> Self {
+        let mut merged: HashMap<Period, (usize, Option<NaiveDate>, f64)> = HashMap::new();
+        for (priority, tag) in tags.iter().enumerate() {
+            let facts = company_facts
+                .facts
+                .us_gaap
+                .get(*tag)
+                .and_then(|concept| concept.units.get(unit));
+            for fact in facts.into_iter().flatten() {
+                let key = (fact.start, fact.end);
+                let is_newer = match merged.get(&key) {
+                    Some(&(prev_priority, prev_filed, _)) => {
+                        prev_priority == priority && fact.filed > prev_filed
+                    }
+                    None => true,
+                };
+                if is_newer {
+                    merged.insert(key, (priority, fact.filed, fact.val / scale));
+                }
+            }
+        }
+        ConceptValues {
+            values: merged
+                .into_iter()
+                .map(|(key, (_, _, val))| (key, val))
+                .collect(),
+        }
+    }
+
+    fn usd(company_facts: &CompanyFacts, tags: &[&str]) -> Self {
+        Self::from_tags(company_facts, tags, EDGAR_CURRENCY, MILLION)
+    }
+
+    fn duration(&self, start: NaiveDate, end: NaiveDate) -> Option<f64> {
+        self.values.get(&(Some(start), end)).copied()
+    }
+
+    fn instant(&self, end: NaiveDate) -> Option<f64> {
+        self.values.get(&(None, end)).copied()
+    }
+
+    /// value of a single quarter ending on the given date
+    fn three_month(&self, end: NaiveDate) -> Option<f64> {
+        self.values.iter().find_map(|(&(start, curr_end), &val)| {
+            let days = start.map(|start| (curr_end - start).num_days())?;
+            (curr_end == end && (80..=100).contains(&days)).then_some(val)
+        })
+    }
+
+    /// weighted average share count of the period ending on the given date.
+    /// share counts aren't additive, so quarters are never summed: the latest quarter is used,
+    /// then the year-to-date average, then the count at the period end
+    fn share_count(&self, fiscal_start: NaiveDate, end: NaiveDate) -> Option<f64> {
+        self.three_month(end)
+            .or_else(|| self.duration(fiscal_start, end))
+            .or_else(|| self.instant(end))
+    }
+
+    /// every (start, end) pair covering roughly one fiscal year
+    fn annual_periods(&self) -> Vec<(NaiveDate, NaiveDate)> {
+        let mut periods: Vec<(NaiveDate, NaiveDate)> = self
+            .values
+            .keys()
+            .filter_map(|&(start, end)| start.map(|start| (start, end)))
+            .filter(|&(start, end)| (350..=380).contains(&(end - start).num_days()))
+            .collect();
+        periods.sort();
+        periods.dedup();
+        periods
+    }
+
+    /// end dates of every period reported within the fiscal year
+    fn ends_within(&self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+        self.values
+            .keys()
+            .filter_map(|&(curr_start, curr_end)| {
+                let curr_start = curr_start?;
+                let is_ytd = curr_start == start;
+                let is_quarter = (80..=100).contains(&(curr_end - curr_start).num_days());
+                (curr_end > start && curr_end < end && (is_ytd || is_quarter)).then_some(curr_end)
+            })
+            .collect()
+    }
+}
+
+/// every line item mapped from US-GAAP concepts
+struct Concepts {
+    revenue: ConceptValues,
+    cost_of_revenue: ConceptValues,
+    gross_profit: ConceptValues,
+    sga_expenses: ConceptValues,
+    rnd_expenses: ConceptValues,
+    operating_expenses: ConceptValues,
+    operating_income: ConceptValues,
+    interest_expenses: ConceptValues,
+    goodwill_impairment: ConceptValues,
+    net_income: ConceptValues,
+    eps_basic: ConceptValues,
+    eps_diluted: ConceptValues,
+    shares_outstanding_basic: ConceptValues,
+    shares_outstanding_diluted: ConceptValues,
+    cash_and_equivalents: ConceptValues,
+    cash_and_short_term_investments: ConceptValues,
+    accounts_receivable: ConceptValues,
+    inventory: ConceptValues,
+    total_current_assets: ConceptValues,
+    goodwill: ConceptValues,
+    total_assets: ConceptValues,
+    accounts_payable: ConceptValues,
+    total_current_liabilities: ConceptValues,
+    total_liabilities: ConceptValues,
+    retained_earnings: ConceptValues,
+    shareholders_equity: ConceptValues,
+    total_debt: ConceptValues,
+    depreciation_and_amortization: ConceptValues,
+    stock_based_compensation: ConceptValues,
+    operating_cash_flow: ConceptValues,
+    capital_expenditure: ConceptValues,
+    investing_cash_flow: ConceptValues,
+    financing_cash_flow: ConceptValues,
+}
+
+impl Concepts {
+    fn new(facts: &CompanyFacts) -> Self {
+        Concepts {
+            revenue: ConceptValues::usd(
+                facts,
+                &[
+                    "Revenues",
+                    "RevenueFromContractWithCustomerExcludingAssessedTax",
+                    "RevenueFromContractWithCustomerIncludingAssessedTax",
+                    "SalesRevenueNet",
+                ],
+            ),
+            cost_of_revenue: ConceptValues::usd(
+                facts,
+                &["CostOfRevenue", "CostOfGoodsAndServicesSold"],
+            ),
+            gross_profit: ConceptValues::usd(facts, &["GrossProfit"]),
+            sga_expenses: ConceptValues::usd(facts, &["SellingGeneralAndAdministrativeExpense"]),
+            rnd_expenses: ConceptValues::usd(facts, &["ResearchAndDevelopmentExpense"]),
+            operating_expenses: ConceptValues::usd(facts, &["OperatingExpenses"]),
+            operating_income: ConceptValues::usd(facts, &["OperatingIncomeLoss"]),
+            interest_expenses: ConceptValues::usd(facts, &["InterestExpense"]),
+            goodwill_impairment: ConceptValues::usd(facts, &["GoodwillImpairmentLoss"]),
+            net_income: ConceptValues::usd(facts, &["NetIncomeLoss"]),
+            eps_basic: ConceptValues::from_tags(
+                facts,
+                &["EarningsPerShareBasic"],
+                "USD/shares",
+                1.,
+            ),
+            eps_diluted: ConceptValues::from_tags(
+                facts,
+                &["EarningsPerShareDiluted"],
+                "USD/shares",
+                1.,
+            ),
+            shares_outstanding_basic: ConceptValues::from_tags(
+                facts,
+                &["WeightedAverageNumberOfSharesOutstandingBasic"],
+                "shares",
+                MILLION,
+            ),
+            shares_outstanding_diluted: ConceptValues::from_tags(
+                facts,
+                &["WeightedAverageNumberOfDilutedSharesOutstanding"],
+                "shares",
+                MILLION,
+            ),
+            cash_and_equivalents: ConceptValues::usd(
+                facts,
+                &["CashAndCashEquivalentsAtCarryingValue"],
+            ),
+            cash_and_short_term_investments: ConceptValues::usd(
+                facts,
+                &["CashCashEquivalentsAndShortTermInvestments"],
+            ),
+            accounts_receivable: ConceptValues::usd(facts, &["AccountsReceivableNetCurrent"]),
+            inventory: ConceptValues::usd(facts, &["InventoryNet"]),
+            total_current_assets: ConceptValues::usd(facts, &["AssetsCurrent"]),
+            goodwill: ConceptValues::usd(facts, &["Goodwill"]),
+            total_assets: ConceptValues::usd(facts, &["Assets"]),
+            accounts_payable: ConceptValues::usd(facts, &["AccountsPayableCurrent"]),
+            total_current_liabilities: ConceptValues::usd(facts, &["LiabilitiesCurrent"]),
+            total_liabilities: ConceptValues::usd(facts, &["Liabilities"]),
+            retained_earnings: ConceptValues::usd(facts, &["RetainedEarningsAccumulatedDeficit"]),
+            shareholders_equity: ConceptValues::usd(facts, &["StockholdersEquity"]),
+            total_debt: ConceptValues::usd(facts, &["LongTermDebt", "LongTermDebtNoncurrent"]),
+            depreciation_and_amortization: ConceptValues::usd(
+                facts,
+                &[
+                    "DepreciationDepletionAndAmortization",
+                    "DepreciationAmortizationAndAccretionNet",
+                    "DepreciationAndAmortization",
+                ],
+            ),
+            stock_based_compensation: ConceptValues::usd(facts, &["ShareBasedCompensation"]),
+            operating_cash_flow: ConceptValues::usd(
+                facts,
+                &["NetCashProvidedByUsedInOperatingActivities"],
+            ),
+            capital_expenditure: ConceptValues::usd(
+                facts,
+                &["PaymentsToAcquirePropertyPlantAndEquipment"],
+            ),
+            investing_cash_flow: ConceptValues::usd(
+                facts,
+                &["NetCashProvidedByUsedInInvestingActivities"],
+            ),
+            financing_cash_flow: ConceptValues::usd(
+                facts,
+                &["NetCashProvidedByUsedInFinancingActivities"],
+            ),
+        }
+    }
+}
+
+/// one earnings period extracted from EDGAR, ready to be converted into an earnings entry
+pub struct EdgarStatement {
+    pub term: &'static str,
+    pub fiscal_year: i16,
+    pub fiscal_quarter: i16,
+    pub period_ending: NaiveDate,
+    pub revenue: f64,
+    pub revenue_growth_yoy: Option<f64>,
+    pub cost_of_revenue: Option<f64>,
+    pub gross_profit: Option<f64>,
+    pub sga_expenses: Option<f64>,
+    pub rnd_expenses: Option<f64>,
+    pub operating_expenses: f64,
+    pub operating_income: f64,
+    pub interest_expenses: Option<f64>,
+    pub goodwill_impairment: f64,
+    pub net_income: f64,
+    pub eps_basic: f64,
+    pub eps_diluted: f64,
+    pub shares_outstanding_basic: f64,
+    pub shares_outstanding_diluted: f64,
+    pub shares_change_yoy: f64,
+    pub cash_and_equivalents: f64,
+    pub cash_and_short_term_investments: Option<f64>,
+    pub accounts_receivable: Option<f64>,
+    pub inventory: Option<f64>,
+    pub total_current_assets: Option<f64>,
+    pub goodwill: Option<f64>,
+    pub total_assets: f64,
+    pub accounts_payable: Option<f64>,
+    pub total_current_liabilities: Option<f64>,
+    pub total_liabilities: f64,
+    pub retained_earnings: f64,
+    pub shareholders_equity: f64,
+    pub total_debt: Option<f64>,
+    pub depreciation_and_amortization: Option<f64>,
+    pub stock_based_compensation: Option<f64>,
+    pub operating_cash_flow: Option<f64>,
+    pub capital_expenditure: Option<f64>,
+    pub investing_cash_flow: Option<f64>,
+    pub financing_cash_flow: Option<f64>,
+}
+
+/// fiscal year with the end date of each quarter. Q4 always ends with the fiscal year
+struct FiscalYear {
+    year: i16,
+    start: NaiveDate,
+    end: NaiveDate,
+    quarter_ends: [Option<NaiveDate>; 4],
+}
+
+impl FiscalYear {
+    /// year-to-date value from the fiscal year start to the end of the given quarter (0-based)
+    fn ytd(&self, concept: &ConceptValues, quarter: usize) -> Option<f64> {
+        let end = self.quarter_ends[quarter]?;
+        concept.duration(self.start, end).or_else(|| match quarter {
+            0 => concept.three_month(end),
+            _ => None,
+        })
+    }
+
+    /// value of a single quarter (0-based), derived from year-to-date values when not reported
+    fn discrete(&self, concept: &ConceptValues, quarter: usize) -> Option<f64> {
+        let end = self.quarter_ends[quarter]?;
+        concept.three_month(end).or_else(|| match quarter {
+            0 => self.ytd(concept, 0),
+            _ => self
+                .ytd(concept, quarter)
+                .zip(self.ytd(concept, quarter - 1))
+                .map(|(curr, prev)| curr - prev),
+        })
+    }
+}
+
+fn fiscal_years(concepts: &Concepts) -> Vec<FiscalYear> {
+    concepts
+        .revenue
+        .annual_periods()
+        .into_iter()
+        .map(|(start, end)| {
+            let mut quarter_ends = [None, None, None, Some(end)];
+            let mut ends = concepts.revenue.ends_within(start, end);
+            ends.extend(concepts.net_income.ends_within(start, end));
+            for quarter_end in ends {
+                let quarter = ((quarter_end - start).num_days() as f64 / 91.).round() as usize;
+                if (1..=3).contains(&quarter) {
+                    quarter_ends[quarter - 1] = Some(quarter_end);
+                }
+            }
+            // e.g. a year ending in January is mostly the previous calendar year
+            let year = match end.ordinal() <= LATE_FISCAL_YEAR_END_ORDINAL {
+                true => end.year() - 1,
+                false => end.year(),
+            };
+            FiscalYear {
+                year: year as i16,
+                start,
+                end,
+                quarter_ends,
+            }
+        })
+        .collect()
+}
+
+/// builds a statement from the flow values of the period and the balance sheet at its end
+fn build_statement<F>(
+    concepts: &Concepts,
+    term: &'static str,
+    fy: &FiscalYear,
+    fiscal_quarter: i16,
+    period_ending: NaiveDate,
+    flow: F,
+) -> Option<EdgarStatement>
+where
+    F: Fn(&ConceptValues) -> Option<f64>,
+{
+    let revenue = flow(&concepts.revenue)?;
+    let operating_income = flow(&concepts.operating_income)?;
+    let net_income = flow(&concepts.net_income)?;
+    let shares_outstanding_diluted = concepts
+        .shares_outstanding_diluted
+        .share_count(fy.start, period_ending)?;
+    let shares_outstanding_basic = concepts
+        .shares_outstanding_basic
+        .share_count(fy.start, period_ending)
+        .unwrap_or(shares_outstanding_diluted);
+    let total_assets = concepts.total_assets.instant(period_ending)?;
+    let shareholders_equity = concepts.shareholders_equity.instant(period_ending)?;
+    let cost_of_revenue = flow(&concepts.cost_of_revenue);
+    let gross_profit =
+        flow(&concepts.gross_profit).or_else(|| cost_of_revenue.map(|cost| revenue - cost));
+    let eps = |concept: &ConceptValues, shares: f64| match term {
+        "Y" => flow(concept).unwrap_or(net_income / shares),
+        _ => net_income / shares,
+    };
+    Some(EdgarStatement {
+        term,
+        fiscal_year: fy.year,
+        fiscal_quarter,
+        period_ending,
+        revenue,
+        revenue_growth_yoy: None,
+        cost_of_revenue,
+        gross_profit,
+        sga_expenses: flow(&concepts.sga_expenses),
+        rnd_expenses: flow(&concepts.rnd_expenses),
+        operating_expenses: flow(&concepts.operating_expenses)
+            .unwrap_or(gross_profit.unwrap_or(revenue) - operating_income),
+        operating_income,
+        interest_expenses: flow(&concepts.interest_expenses),
+        goodwill_impairment: flow(&concepts.goodwill_impairment).unwrap_or(0.),
+        net_income,
+        eps_basic: eps(&concepts.eps_basic, shares_outstanding_basic),
+        eps_diluted: eps(&concepts.eps_diluted, shares_outstanding_diluted),
+        shares_outstanding_basic,
+        shares_outstanding_diluted,
+        shares_change_yoy: 0.,
+        cash_and_equivalents: concepts
+            .cash_and_equivalents
+            .instant(period_ending)
+            .unwrap_or(0.),
+        cash_and_short_term_investments: concepts
+            .cash_and_short_term_investments
+            .instant(period_ending),
+        accounts_receivable: concepts.accounts_receivable.instant(period_ending),
+        inventory: concepts.inventory.instant(period_ending),
+        total_current_assets: concepts.total_current_assets.instant(period_ending),
+        goodwill: concepts.goodwill.instant(period_ending),
+        total_assets,
+        accounts_payable: concepts.accounts_payable.instant(period_ending),
+        total_current_liabilities: concepts.total_current_liabilities.instant(period_ending),
+        total_liabilities: concepts
+            .total_liabilities
+            .instant(period_ending)
+            .unwrap_or(total_assets - shareholders_equity),
+        retained_earnings: concepts
+            .retained_earnings
+            .instant(period_ending)
+            .unwrap_or(0.),
+        shareholders_equity,
+        total_debt: concepts.total_debt.instant(period_ending),
+        depreciation_and_amortization: flow(&concepts.depreciation_and_amortization),
+        stock_based_compensation: flow(&concepts.stock_based_compensation),
+        operating_cash_flow: flow(&concepts.operating_cash_flow),
+        capital_expenditure: flow(&concepts.capital_expenditure).map(|capex| -capex),
+        investing_cash_flow: flow(&concepts.investing_cash_flow),
+        financing_cash_flow: flow(&concepts.financing_cash_flow),
+    })
+}
+
+/// fills revenue growth and share change by comparing with the same period of the previous year
+fn fill_yoy_changes(statements: &mut [EdgarStatement]) {
+    let previous: HashMap<(&str, i16, i16), (f64, f64)> = statements
+        .iter()
+        .map(|statement| {
+            (
+                (
+                    statement.term,
+                    statement.fiscal_year,
+                    statement.fiscal_quarter,
+                ),
+                (statement.revenue, statement.shares_outstanding_diluted),
+            )
+        })
+        .collect();
+    for statement in statements.iter_mut() {
+        let key = (
+            statement.term,
+            statement.fiscal_year - 1,
+            statement.fiscal_quarter,
+        );
+        if let Some(&(prev_revenue, prev_shares)) = previous.get(&key) {
+            statement.revenue_growth_yoy =
+                calculate::calculate_yoy_growth_option(Some(statement.revenue), Some(prev_revenue));
+            statement.shares_change_yoy = calculate::calculate_yoy_growth_option(
+                Some(statement.shares_outstanding_diluted),
+                Some(prev_shares),
+            )
+            .unwrap_or(0.);
+        }
+    }
+}
+
+/// extracts annual statements and TTM statements summed from the four latest quarters
+pub fn extract_statements(facts: &CompanyFacts) -> Vec<EdgarStatement> {
+    let concepts = Concepts::new(facts);
+    let years = fiscal_years(&concepts);
+    let mut statements: Vec<EdgarStatement> = years
+        .iter()
+        .filter_map(|fy| {
+            build_statement(&concepts, "Y", fy, 0, fy.end, |concept| {
+                concept.duration(fy.start, fy.end)
+            })
+        })
+        .collect();
+
+    let quarters: Vec<(&FiscalYear, usize, NaiveDate)> = years
+        .iter()
+        .flat_map(|fy| {
+            fy.quarter_ends
+                .iter()
+                .enumerate()
+                .filter_map(move |(quarter, end)| end.map(|end| (fy, quarter, end)))
+        })
+        .collect();
+    for window in quarters.windows(4) {
+        let (fy, quarter, end) = window[3];
+        // the four quarters must be consecutive
+        if !(250..=290).contains(&(end - window[0].2).num_days()) {
+            continue;
+        }
+        let ttm = build_statement(&concepts, "T", fy, quarter as i16 + 1, end, |concept| {
+            window
+                .iter()
+                .map(|(curr_fy, curr_quarter, _)| curr_fy.discrete(concept, *curr_quarter))
+                .sum::<Option<f64>>()
+        });
+        statements.extend(ttm);
+    }
+    fill_yoy_changes(&mut statements);
+    statements
+}
+
+/// imports a companyfacts JSON file for the given ticker, creating the company if needed.
+/// returns the number of earnings periods found in the file
+pub fn import_company_facts(
+    path: &Path,
+    ticker: &str,
+    industry: &str,
+    conn: &mut PgConnection,
+) -> Result<usize, BullsEyeError> {
+    let contents = std::fs::read_to_string(path)?;
+    let facts: CompanyFacts = serde_json::from_str(&contents)?;
+    let exchange = db::lookup_exchange(ticker);
+    let statements = extract_statements(&facts);
+    let has_ttm = statements.iter().any(|statement| statement.term == "T");
+    // a failed import leaves neither a half-created company nor partial earnings behind
+    let (entry_count, comp_id) = conn.transaction::<_, BullsEyeError, _>(|conn| {
+        let company =
+            match Company::load_by_ticker_if_existed(ticker, get_exchange_string(&exchange), conn)?
+            {
+                Some(company) => company,
+                None => {
+                    let profile = Profile {
+                        company_name: facts.entity_name.chars().take(50).collect(),
+                        industry: industry.to_string(),
+                        // companyfacts has no ISIN, so the zero-padded CIK keeps the column unique
+                        isin: format!("{:0>10}", facts.cik),
+                    };
+                    services::create_company(&profile, &exchange, ticker, conn)?
+                }
+            };
+        let entries: Vec<NewEarningsReport> = statements
+            .into_iter()
+            .map(|statement| NewEarningsReport::from_edgar(company.id, EDGAR_CURRENCY, statement))
+            .collect();
+        let entry_count = entries.len();
+        let inserted = earnings_model::insert_earnings_report_batch(entries, conn)?;
+        if inserted > 0 {
+            db::update_growths_batch(conn)?;
+            db::update_ratios_batch(conn)?;
+        }
+        if has_ttm {
+            services::recompute_metrics_annual(company.id, conn)?;
+        }
+        Ok((entry_count, company.id))
+    })?;
+    // published after the commit, so clients never see metrics that were rolled back
+    if has_ttm {
+        services::publish_metrics_recomputed(comp_id, conn)?;
+    }
+    Ok(entry_count)
+}
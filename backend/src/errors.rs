@@ -3,6 +3,7 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use bullseye_api::errors::ScraperError;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
 use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum BullsEyeError {
@@ -16,6 +17,42 @@ pub enum BullsEyeError {
     DateParseError(#[from] chrono::format::ParseError),
     #[error(transparent)]
     EnvVarError(#[from] std::env::VarError),
+    #[error("Company not found: {0}")]
+    CompanyNotFoundError(String),
+    #[error("Invalid query: {0}")]
+    InvalidQueryError(String),
+    #[error("Invalid expression: {0}")]
+    InvalidExpressionError(String),
+    #[error("Screen not found: {0}")]
+    ScreenNotFoundError(i32),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+    #[error("Invalid command: {0}")]
+    InvalidCommandError(String),
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+    #[error("Job not found: {0}")]
+    JobNotFoundError(i32),
+    #[error("Job is still queued or running: {0}")]
+    JobActiveError(i32),
+    #[error("Refresh failed: {0}")]
+    RefreshFailedError(String),
+    #[error("Snapshot not found: {0}")]
+    SnapshotNotFoundError(i32),
+    #[error("Insufficient data: {0}")]
+    InsufficientDataError(String),
+    #[error("Industry not found: {0}")]
+    IndustryNotFoundError(String),
+    #[error("Margin factor not found: {0}")]
+    MarginFactorNotFoundError(String),
+    #[error(transparent)]
+    TomlError(#[from] toml::de::Error),
+    #[error("Invalid config: {0}")]
+    InvalidConfigError(String),
+    #[error("Trend setting not found: {0}")]
+    TrendSettingNotFoundError(i32),
 }
 
 impl IntoResponse for BullsEyeError {
@@ -36,10 +73,32 @@ impl IntoResponse for BullsEyeError {
             BullsEyeError::ScraperError(ScraperError::EnvVarError(_)) => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
+            BullsEyeError::DatabaseError(DieselError::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                _,
+            )) => StatusCode::CONFLICT,
             BullsEyeError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             BullsEyeError::DbPoolError => StatusCode::INTERNAL_SERVER_ERROR,
             BullsEyeError::DateParseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             BullsEyeError::EnvVarError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            BullsEyeError::CompanyNotFoundError(_) => StatusCode::NOT_FOUND,
+            BullsEyeError::InvalidQueryError(_) => StatusCode::BAD_REQUEST,
+            BullsEyeError::InvalidExpressionError(_) => StatusCode::BAD_REQUEST,
+            BullsEyeError::ScreenNotFoundError(_) => StatusCode::NOT_FOUND,
+            BullsEyeError::IoError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            BullsEyeError::JsonError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            BullsEyeError::InvalidCommandError(_) => StatusCode::BAD_REQUEST,
+            BullsEyeError::CsvError(_) => StatusCode::BAD_REQUEST,
+            BullsEyeError::JobNotFoundError(_) => StatusCode::NOT_FOUND,
+            BullsEyeError::JobActiveError(_) => StatusCode::CONFLICT,
+            BullsEyeError::RefreshFailedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            BullsEyeError::SnapshotNotFoundError(_) => StatusCode::NOT_FOUND,
+            BullsEyeError::InsufficientDataError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            BullsEyeError::IndustryNotFoundError(_) => StatusCode::NOT_FOUND,
+            BullsEyeError::MarginFactorNotFoundError(_) => StatusCode::NOT_FOUND,
+            BullsEyeError::TomlError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            BullsEyeError::InvalidConfigError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            BullsEyeError::TrendSettingNotFoundError(_) => StatusCode::NOT_FOUND,
         };
 
         (status, self.to_string()).into_response()
@@ -0,0 +1,98 @@
+use axum::extract::ws::{Message, WebSocket};
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use std::sync::LazyLock;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// events buffered per socket before a slow client starts missing them
+const EVENT_BUFFER: usize = 256;
+
+/// process wide hub. publishing without any connected client is a no-op,
+/// so the CLI and background workers can publish unconditionally
+static EVENTS: LazyLock<broadcast::Sender<CompanyEvent>> =
+    LazyLock::new(|| broadcast::channel(EVENT_BUFFER).0);
+
+/// a step of refreshing a company, tagged by `event`
+#[derive(Serialize, Clone)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum RefreshEvent {
+    #[serde(rename_all = "camelCase")]
+    ProfileFetched {
+        company_name: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    EarningsInserted {
+        ttm_rows: usize,
+        annual_rows: usize,
+    },
+    RegularDataUpdated,
+    MetricsRecomputed,
+    Completed,
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyEvent {
+    /// None when the ticker couldn't be resolved to a company
+    pub company_id: Option<i32>,
+    pub ticker: String,
+    pub at: NaiveDateTime,
+    #[serde(flatten)]
+    pub event: RefreshEvent,
+}
+
+/// broadcasts the event to every connected client
+pub fn publish(company_id: i32, ticker: &str, event: RefreshEvent) {
+    send(Some(company_id), ticker, event);
+}
+
+/// broadcasts the event of a ticker that has no company yet, e.g. when its first lookup fails
+pub fn publish_unresolved(ticker: &str, event: RefreshEvent) {
+    send(None, ticker, event);
+}
+
+fn send(company_id: Option<i32>, ticker: &str, event: RefreshEvent) {
+    // no receivers just means nobody is listening right now
+    let _ = EVENTS.send(CompanyEvent {
+        company_id,
+        // sockets filter on the upper-cased ticker
+        ticker: ticker.to_uppercase(),
+        at: Utc::now().naive_utc(),
+        event,
+    });
+}
+
+/// streams events to one client until it disconnects. only the ticker's events are sent when given
+pub async fn handle_socket(mut socket: WebSocket, ticker: Option<String>) {
+    let mut events = EVENTS.subscribe();
+    let ticker = ticker.map(|ticker| ticker.to_uppercase());
+    loop {
+        let event = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            event = events.recv() => match event {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            },
+        };
+        if ticker
+            .as_ref()
+            .is_some_and(|ticker| *ticker != event.ticker)
+        {
+            continue;
+        }
+        let Ok(text) = serde_json::to_string(&event) else {
+            continue;
+        };
+        if socket.send(Message::Text(text.into())).await.is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,523 @@
+use crate::errors::BullsEyeError;
+use crate::models::metrics_model::Trend;
+use crate::models::returning_model::{FieldValue, ReturningModel};
+use crate::screener::Comparison;
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Text(String),
+    Compare(Comparison),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LeftParen,
+    RightParen,
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Arithmetic {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+/// parsed screening expression, e.g.
+///     grossMarginTtm > 50 and operatingMarginShortTermTrend == up
+#[derive(Debug, PartialEq)]
+pub enum Expression {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Trend(Trend),
+    Field(String),
+    Negate(Box<Expression>),
+    Not(Box<Expression>),
+    Arithmetic(Box<Expression>, Arithmetic, Box<Expression>),
+    Compare(Box<Expression>, Comparison, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+/// intermediate value while evaluating an expression. Missing data evaluates to Null
+#[derive(Debug, PartialEq)]
+enum Value<'a> {
+    Number(f64),
+    Text(&'a str),
+    Bool(bool),
+    Trend(&'a Trend),
+    Null,
+}
+
+fn invalid(message: String) -> BullsEyeError {
+    BullsEyeError::InvalidExpressionError(message)
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, BullsEyeError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => (Token::LeftParen, 1),
+            ')' => (Token::RightParen, 1),
+            '+' => (Token::Plus, 1),
+            '-' => (Token::Minus, 1),
+            '*' => (Token::Star, 1),
+            '/' => (Token::Slash, 1),
+            '>' if next == Some('=') => (Token::Compare(Comparison::GreaterOrEqual), 2),
+            '<' if next == Some('=') => (Token::Compare(Comparison::LessOrEqual), 2),
+            '!' if next == Some('=') => (Token::Compare(Comparison::NotEqual), 2),
+            '=' if next == Some('=') => (Token::Compare(Comparison::Equal), 2),
+            '&' if next == Some('&') => (Token::And, 2),
+            '|' if next == Some('|') => (Token::Or, 2),
+            '>' => (Token::Compare(Comparison::Greater), 1),
+            '<' => (Token::Compare(Comparison::Less), 1),
+            '=' => (Token::Compare(Comparison::Equal), 1),
+            '!' => (Token::Not, 1),
+            '"' | '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&x| x == c)
+                    .ok_or_else(|| invalid("unterminated string".to_string()))?;
+                let text: String = chars[i + 1..i + 1 + end].iter().collect();
+                (Token::Text(text), end + 2)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|x| x.is_ascii_digit() || **x == '.')
+                    .count();
+                let literal: String = chars[i..i + len].iter().collect();
+                let number = literal
+                    .parse::<f64>()
+                    .map_err(|_| invalid(format!("invalid number '{}'", literal)))?;
+                (Token::Number(number), len)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|x| x.is_ascii_alphanumeric() || **x == '_')
+                    .count();
+                let word: String = chars[i..i + len].iter().collect();
+                let token = match word.to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Ident(word),
+                };
+                (token, len)
+            }
+            c => return Err(invalid(format!("unexpected character '{}'", c))),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Ok(tokens)
+}
+
+/// recursive descent parser. Precedence from loosest to tightest:
+///     or, and, not, comparison, + -, * /, unary minus
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn advance(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, BullsEyeError> {
+        let mut lhs = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.advance();
+            let rhs = self.parse_and()?;
+            lhs = Expression::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, BullsEyeError> {
+        let mut lhs = self.parse_not()?;
+        while self.peek() == Some(&Token::And) {
+            self.advance();
+            let rhs = self.parse_not()?;
+            lhs = Expression::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_not(&mut self) -> Result<Expression, BullsEyeError> {
+        if self.peek() == Some(&Token::Not) {
+            self.advance();
+            let inner = self.parse_not()?;
+            return Ok(Expression::Not(Box::new(inner)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expression, BullsEyeError> {
+        let lhs = self.parse_sum()?;
+        if let Some(&Token::Compare(comparison)) = self.peek() {
+            self.advance();
+            let rhs = self.parse_sum()?;
+            return Ok(Expression::Compare(
+                Box::new(lhs),
+                comparison,
+                Box::new(rhs),
+            ));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_sum(&mut self) -> Result<Expression, BullsEyeError> {
+        let mut lhs = self.parse_product()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => Arithmetic::Add,
+                Some(Token::Minus) => Arithmetic::Subtract,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            let rhs = self.parse_product()?;
+            lhs = Expression::Arithmetic(Box::new(lhs), op, Box::new(rhs));
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Expression, BullsEyeError> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => Arithmetic::Multiply,
+                Some(Token::Slash) => Arithmetic::Divide,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            let rhs = self.parse_unary()?;
+            lhs = Expression::Arithmetic(Box::new(lhs), op, Box::new(rhs));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, BullsEyeError> {
+        if self.peek() == Some(&Token::Minus) {
+            self.advance();
+            let inner = self.parse_unary()?;
+            return Ok(Expression::Negate(Box::new(inner)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, BullsEyeError> {
+        match self.advance() {
+            Some(Token::Number(val)) => Ok(Expression::Number(*val)),
+            Some(Token::Text(val)) => Ok(Expression::Text(val.clone())),
+            Some(Token::Ident(name)) => resolve_identifier(name),
+            Some(Token::LeftParen) => {
+                let inner = self.parse_or()?;
+                match self.advance() {
+                    Some(Token::RightParen) => Ok(inner),
+                    _ => Err(invalid("missing closing parenthesis".to_string())),
+                }
+            }
+            Some(token) => Err(invalid(format!("unexpected token {:?}", token))),
+            None => Err(invalid("unexpected end of expression".to_string())),
+        }
+    }
+}
+
+/// an identifier is either a field name, a boolean or a trend literal (up, down, flat, ...)
+fn resolve_identifier(name: &str) -> Result<Expression, BullsEyeError> {
+    if ReturningModel::FIELD_NAMES.contains(&name) {
+        return Ok(Expression::Field(name.to_string()));
+    }
+    match name.to_lowercase().as_str() {
+        "true" => Ok(Expression::Bool(true)),
+        "false" => Ok(Expression::Bool(false)),
+        _ => name
+            .parse::<Trend>()
+            .map(Expression::Trend)
+            .map_err(|_| invalid(format!("unknown field '{}'", name))),
+    }
+}
+
+impl Expression {
+    /// parses the source string into an expression tree
+    pub fn parse(source: &str) -> Result<Self, BullsEyeError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let expression = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(invalid(format!("unexpected token {:?}", token)));
+        }
+        Ok(expression)
+    }
+
+    /// tells if the given company satisfies the expression. Missing data never matches
+    pub fn matches(&self, model: &ReturningModel) -> Result<bool, BullsEyeError> {
+        self.matches_fields(&|name| model.field_value(name))
+    }
+
+    /// matches against any source of field values, which keeps evaluation testable without a model
+    fn matches_fields<'a, F>(&'a self, fields: &F) -> Result<bool, BullsEyeError>
+    where
+        F: Fn(&str) -> Option<FieldValue<'a>>,
+    {
+        match self.evaluate(fields)? {
+            Value::Bool(val) => Ok(val),
+            Value::Null => Ok(false),
+            val => Err(invalid(format!(
+                "expression must evaluate to true or false, got {:?}",
+                val
+            ))),
+        }
+    }
+
+    fn evaluate<'a, F>(&'a self, fields: &F) -> Result<Value<'a>, BullsEyeError>
+    where
+        F: Fn(&str) -> Option<FieldValue<'a>>,
+    {
+        let value = match self {
+            Expression::Number(val) => Value::Number(*val),
+            Expression::Text(val) => Value::Text(val),
+            Expression::Bool(val) => Value::Bool(*val),
+            Expression::Trend(val) => Value::Trend(val),
+            Expression::Field(name) => match fields(name.as_str()) {
+                Some(FieldValue::Number(val)) => val.map_or(Value::Null, Value::Number),
+                Some(FieldValue::Trend(val)) => val.map_or(Value::Null, Value::Trend),
+                Some(FieldValue::Bool(val)) => val.map_or(Value::Null, Value::Bool),
+                Some(FieldValue::Text(val)) => Value::Text(val),
+                None => return Err(invalid(format!("unknown field '{}'", name))),
+            },
+            Expression::Negate(inner) => match inner.evaluate(fields)? {
+                Value::Number(val) => Value::Number(-val),
+                Value::Null => Value::Null,
+                val => return Err(invalid(format!("cannot negate {:?}", val))),
+            },
+            Expression::Not(inner) => match inner.evaluate(fields)? {
+                Value::Bool(val) => Value::Bool(!val),
+                Value::Null => Value::Null,
+                val => return Err(invalid(format!("cannot apply not to {:?}", val))),
+            },
+            Expression::Arithmetic(lhs, op, rhs) => {
+                match (lhs.evaluate(fields)?, rhs.evaluate(fields)?) {
+                    (Value::Number(x), Value::Number(y)) => {
+                        let result = match op {
+                            Arithmetic::Add => x + y,
+                            Arithmetic::Subtract => x - y,
+                            Arithmetic::Multiply => x * y,
+                            Arithmetic::Divide => x / y,
+                        };
+                        match result.is_finite() {
+                            true => Value::Number(result),
+                            false => Value::Null,
+                        }
+                    }
+                    (Value::Null, _) | (_, Value::Null) => Value::Null,
+                    (x, y) => {
+                        return Err(invalid(format!(
+                            "cannot apply {:?} to {:?} and {:?}",
+                            op, x, y
+                        )))
+                    }
+                }
+            }
+            Expression::Compare(lhs, comparison, rhs) => {
+                compare(lhs.evaluate(fields)?, *comparison, rhs.evaluate(fields)?)?
+            }
+            // false and true decide the result even when the other side is missing
+            Expression::And(lhs, rhs) => {
+                match (
+                    to_bool(lhs.evaluate(fields)?)?,
+                    to_bool(rhs.evaluate(fields)?)?,
+                ) {
+                    (Some(false), _) | (_, Some(false)) => Value::Bool(false),
+                    (Some(true), Some(true)) => Value::Bool(true),
+                    _ => Value::Null,
+                }
+            }
+            Expression::Or(lhs, rhs) => {
+                match (
+                    to_bool(lhs.evaluate(fields)?)?,
+                    to_bool(rhs.evaluate(fields)?)?,
+                ) {
+                    (Some(true), _) | (_, Some(true)) => Value::Bool(true),
+                    (Some(false), Some(false)) => Value::Bool(false),
+                    _ => Value::Null,
+                }
+            }
+        };
+        Ok(value)
+    }
+}
+
+/// None when the value is missing
+fn to_bool(value: Value) -> Result<Option<bool>, BullsEyeError> {
+    match value {
+        Value::Bool(val) => Ok(Some(val)),
+        Value::Null => Ok(None),
+        val => Err(invalid(format!("expected true or false, got {:?}", val))),
+    }
+}
+
+fn compare<'a>(
+    lhs: Value<'a>,
+    comparison: Comparison,
+    rhs: Value<'a>,
+) -> Result<Value<'a>, BullsEyeError> {
+    let is_equal = match (&lhs, &rhs) {
+        // stays unknown so that negating a comparison with missing data doesn't match either
+        (Value::Null, _) | (_, Value::Null) => return Ok(Value::Null),
+        (Value::Number(x), Value::Number(y)) => {
+            let result = match comparison {
+                Comparison::Greater => x > y,
+                Comparison::GreaterOrEqual => x >= y,
+                Comparison::Less => x < y,
+                Comparison::LessOrEqual => x <= y,
+                Comparison::Equal => x == y,
+                Comparison::NotEqual => x != y,
+            };
+            return Ok(Value::Bool(result));
+        }
+        (Value::Text(x), Value::Text(y)) => x.eq_ignore_ascii_case(y),
+        (Value::Bool(x), Value::Bool(y)) => x == y,
+        (Value::Trend(x), Value::Trend(y)) => x == y,
+        _ => return Err(invalid(format!("cannot compare {:?} with {:?}", lhs, rhs))),
+    };
+    match comparison {
+        Comparison::Equal => Ok(Value::Bool(is_equal)),
+        Comparison::NotEqual => Ok(Value::Bool(!is_equal)),
+        _ => Err(invalid(format!(
+            "only == and != are supported between {:?} and {:?}",
+            lhs, rhs
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str) -> Box<Expression> {
+        Box::new(Expression::Field(name.to_string()))
+    }
+
+    fn number(val: f64) -> Box<Expression> {
+        Box::new(Expression::Number(val))
+    }
+
+    /// evaluates the expression with grossMarginTtm missing and operatingMarginTtm at 20
+    fn matches(source: &str) -> bool {
+        let fields = |name: &str| match name {
+            "grossMarginTtm" => Some(FieldValue::Number(None)),
+            "operatingMarginTtm" => Some(FieldValue::Number(Some(20.))),
+            _ => None,
+        };
+        Expression::parse(source)
+            .unwrap()
+            .matches_fields(&fields)
+            .unwrap()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let expected = Expression::Or(
+            Box::new(Expression::Bool(true)),
+            Box::new(Expression::And(
+                Box::new(Expression::Bool(false)),
+                Box::new(Expression::Bool(true)),
+            )),
+        );
+        assert_eq!(
+            Expression::parse("true or false and true").unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn not_applies_to_the_whole_comparison() {
+        let expected = Expression::Not(Box::new(Expression::Compare(
+            field("grossMarginTtm"),
+            Comparison::Greater,
+            number(50.),
+        )));
+        assert_eq!(
+            Expression::parse("not grossMarginTtm > 50").unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn product_binds_tighter_than_sum_and_unary_minus_tightest() {
+        let expected = Expression::Compare(
+            Box::new(Expression::Arithmetic(
+                field("operatingMarginTtm"),
+                Arithmetic::Add,
+                Box::new(Expression::Arithmetic(
+                    Box::new(Expression::Negate(number(2.))),
+                    Arithmetic::Multiply,
+                    number(3.),
+                )),
+            )),
+            Comparison::GreaterOrEqual,
+            number(10.),
+        );
+        assert_eq!(
+            Expression::parse("operatingMarginTtm + -2 * 3 >= 10").unwrap(),
+            expected
+        );
+        assert!(matches("operatingMarginTtm + -2 * 3 >= 10"));
+        assert!(!matches("(operatingMarginTtm + -2) * 3 < 10"));
+    }
+
+    #[test]
+    fn missing_data_never_matches() {
+        assert!(!matches("grossMarginTtm > 50"));
+        assert!(!matches("grossMarginTtm <= 50"));
+        assert!(!matches("not (grossMarginTtm > 50)"));
+        assert!(!matches("not not (grossMarginTtm > 50)"));
+        assert!(!matches("-grossMarginTtm < 0"));
+    }
+
+    #[test]
+    fn missing_data_under_and() {
+        assert!(!matches("grossMarginTtm > 50 and operatingMarginTtm > 10"));
+        assert!(!matches(
+            "not (grossMarginTtm > 50 and operatingMarginTtm > 10)"
+        ));
+        // false decides the result whatever the missing side would be
+        assert!(matches(
+            "not (grossMarginTtm > 50 and operatingMarginTtm > 30)"
+        ));
+    }
+
+    #[test]
+    fn missing_data_under_or() {
+        // true decides the result whatever the missing side would be
+        assert!(matches("grossMarginTtm > 50 or operatingMarginTtm > 10"));
+        assert!(!matches("grossMarginTtm > 50 or operatingMarginTtm > 30"));
+        assert!(!matches(
+            "not (grossMarginTtm > 50 or operatingMarginTtm > 30)"
+        ));
+    }
+}
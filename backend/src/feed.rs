@@ -0,0 +1,173 @@
+use crate::db;
+use crate::errors::BullsEyeError;
+use crate::source::{BoxFuture, FinancialDataSource};
+use chrono::{NaiveDateTime, Utc};
+use serde::Deserialize;
+use std::env;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::time::{self, Duration};
+
+const DEFAULT_POLL_SECONDS: u64 = 60;
+/// replayed gaps are capped so a recording spanning nights doesn't stall
+const MAX_REPLAY_GAP: Duration = Duration::from_secs(5);
+
+/// a single traded price
+#[derive(Deserialize, Clone)]
+pub struct PriceTick {
+    pub ticker: String,
+    pub price: f64,
+    pub at: NaiveDateTime,
+}
+
+/// source of live prices for the tickers clients are watching
+pub trait PriceFeed: Send + Sync {
+    /// waits for the next ticks of the given tickers
+    fn next_ticks<'a>(
+        &'a self,
+        tickers: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<PriceTick>, BullsEyeError>>;
+
+    /// whether the ticks are real prices to store, or only to broadcast
+    fn persists(&self) -> bool {
+        true
+    }
+}
+
+/// replays ticks recorded in a CSV file with `ticker,price,at` columns, looping at the end.
+/// keeps the recorded gaps between ticks, divided by `speed`
+pub struct ReplayFeed {
+    ticks: Vec<PriceTick>,
+    position: Mutex<usize>,
+    speed: f64,
+}
+
+impl ReplayFeed {
+    pub fn from_file(path: &Path, speed: f64) -> Result<Self, BullsEyeError> {
+        let mut ticks = csv::Reader::from_path(path)?
+            .deserialize()
+            .collect::<Result<Vec<PriceTick>, _>>()?;
+        ticks.sort_by_key(|tick| tick.at);
+        ticks
+            .iter_mut()
+            .for_each(|tick| tick.ticker = tick.ticker.to_uppercase());
+        Ok(ReplayFeed {
+            ticks,
+            position: Mutex::new(0),
+            speed: if speed > 0. { speed } else { 1. },
+        })
+    }
+
+    /// returns the next recorded tick and how long to wait before emitting it
+    fn advance(&self) -> (PriceTick, Duration) {
+        let mut position = self.position.lock().unwrap();
+        let tick = self.ticks[*position].clone();
+        let gap = match *position {
+            0 => Duration::ZERO,
+            prev => (tick.at - self.ticks[prev - 1].at)
+                .to_std()
+                .unwrap_or_default()
+                .div_f64(self.speed)
+                .min(MAX_REPLAY_GAP),
+        };
+        *position = (*position + 1) % self.ticks.len();
+        (tick, gap)
+    }
+}
+
+impl PriceFeed for ReplayFeed {
+    fn next_ticks<'a>(
+        &'a self,
+        tickers: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<PriceTick>, BullsEyeError>> {
+        Box::pin(async move {
+            if self.ticks.is_empty() {
+                time::sleep(MAX_REPLAY_GAP).await;
+                return Ok(Vec::new());
+            }
+            // after a full pass without a watched ticker the caller gets to refresh its tickers
+            for _ in 0..self.ticks.len() {
+                let (tick, gap) = self.advance();
+                time::sleep(gap).await;
+                if tickers.contains(&tick.ticker) {
+                    // replayed ticks are served as if they were live
+                    return Ok(vec![PriceTick {
+                        at: Utc::now().naive_utc(),
+                        ..tick
+                    }]);
+                }
+            }
+            Ok(Vec::new())
+        })
+    }
+
+    /// recordings are for local testing and must not overwrite real prices
+    fn persists(&self) -> bool {
+        false
+    }
+}
+
+/// polls the data source for the latest price of every watched ticker
+pub struct PollingFeed {
+    source: Arc<dyn FinancialDataSource>,
+    interval: Duration,
+}
+
+impl PollingFeed {
+    pub fn new(source: Arc<dyn FinancialDataSource>, interval: Duration) -> Self {
+        PollingFeed { source, interval }
+    }
+}
+
+impl PriceFeed for PollingFeed {
+    fn next_ticks<'a>(
+        &'a self,
+        tickers: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<PriceTick>, BullsEyeError>> {
+        Box::pin(async move {
+            time::sleep(self.interval).await;
+            let mut ticks = Vec::new();
+            for ticker in tickers {
+                let exchange = db::lookup_exchange(ticker);
+                match self.source.fetch_regular_update(ticker, &exchange).await {
+                    Ok(regular) => ticks.extend(regular.price.map(|price| PriceTick {
+                        ticker: ticker.clone(),
+                        price,
+                        at: Utc::now().naive_utc(),
+                    })),
+                    Err(e) => eprintln!("price poll failed for {}: {}", ticker, e),
+                }
+            }
+            Ok(ticks)
+        })
+    }
+}
+
+/// selects the price feed from the PRICE_FEED env variable ("poll" by default).
+/// "replay" reads PRICE_REPLAY_FILE at PRICE_REPLAY_SPEED, "poll" asks the data source every
+/// PRICE_POLL_SECONDS
+pub fn price_feed_from_env(
+    source: Arc<dyn FinancialDataSource>,
+) -> Result<Arc<dyn PriceFeed>, BullsEyeError> {
+    match env::var("PRICE_FEED").as_deref() {
+        Ok("replay") => {
+            let speed = env::var("PRICE_REPLAY_SPEED")
+                .ok()
+                .and_then(|val| val.parse().ok())
+                .unwrap_or(1.);
+            let path = env::var("PRICE_REPLAY_FILE")?;
+            Ok(Arc::new(ReplayFeed::from_file(Path::new(&path), speed)?))
+        }
+        _ => {
+            let seconds = env::var("PRICE_POLL_SECONDS")
+                .ok()
+                .and_then(|val| val.parse().ok())
+                .filter(|val| *val > 0)
+                .unwrap_or(DEFAULT_POLL_SECONDS);
+            Ok(Arc::new(PollingFeed::new(
+                source,
+                Duration::from_secs(seconds),
+            )))
+        }
+    }
+}
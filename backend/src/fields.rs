@@ -0,0 +1,135 @@
+/// every metric that can be referenced by its camelCase name, grouped by the table it's stored in.
+/// the screener, screening expressions, industry statistics and peer comparisons all expand this
+/// list, so adding a metric is a single edit here.
+/// invokes the given macro with the groups, after any extra tokens passed along.
+/// numeric columns of current_metrics marked `[peer]` are compared between peers
+macro_rules! metric_fields {
+    ($callback:ident $(, $($extra:tt)*)?) => {
+        $callback! {
+            $($($extra)*)?
+            current_metrics: [
+                "netInterestIncomeGrowthYoyTtm" => net_interest_income_growth_yoy_ttm,
+                "netInterestIncomeGrowthMultiYear" => net_interest_income_growth_multi_year,
+                "netInterestMarginTtm" => net_interest_margin_ttm,
+                "costOfRiskTtm" => cost_of_risk_ttm,
+                "revenueTtm" => revenue_ttm,
+                "revenueGrowthYoyTtm" => revenue_growth_yoy_ttm [peer],
+                "revenueGrowthMultiYear" => revenue_growth_multi_year [peer],
+                "grossProfitGrowthYoyTtm" => gross_profit_growth_yoy_ttm,
+                "grossProfitGrowthMultiYear" => gross_profit_growth_multi_year,
+                "grossMarginTtm" => gross_margin_ttm [peer],
+                "sgaRatioTtm" => sga_ratio_ttm [peer],
+                "rndRatioTtm" => rnd_ratio_ttm [peer],
+                "operatingMarginTtm" => operating_margin_ttm [peer],
+                "interestExpenseRatioTtm" => interest_expense_ratio_ttm,
+                "netMarginTtm" => net_margin_ttm [peer],
+                "theoreticalNetMargin" => theoretical_net_margin,
+                "sharesOutstandingDilutedTtm" => shares_outstanding_diluted_ttm,
+                "sharesChangeTtm" => shares_change_ttm [peer],
+                "sharesChangeMultiYear" => shares_change_multi_year,
+                "retainedEarningsTtm" => retained_earnings_ttm,
+                "netCashTtm" => net_cash_ttm,
+                "operatingCashFlowTtm" => operating_cash_flow_ttm,
+                "operatingCashFlowMarginTtm" => operating_cash_flow_margin_ttm [peer],
+                "freeCashFlowTtm" => free_cash_flow_ttm,
+                "freeCashFlowMarginTtm" => free_cash_flow_margin_ttm [peer],
+                "ffoMarginTtm" => ffo_margin_ttm,
+                "piotroskiFScore" => piotroski_f_score [peer],
+                "altmanZScore" => altman_z_score [peer],
+                "beneishMScore" => beneish_m_score,
+                "returnOnEquityTtm" => return_on_equity_ttm [peer],
+                "returnOnAssetsTtm" => return_on_assets_ttm [peer],
+                "returnOnInvestedCapitalTtm" => return_on_invested_capital_ttm [peer],
+                "assetTurnoverTtm" => asset_turnover_ttm,
+                "equityMultiplierTtm" => equity_multiplier_ttm,
+            ],
+            forecasts: [
+                "latestPrice" => latest_price,
+                "revenueNextYear" => revenue_next_year,
+                "revenueGrowthNextYear" => revenue_growth_next_year,
+                "impliedGrowth" => implied_growth,
+                "priceCurrentRevenueGrowth" => price_current_revenue_growth,
+                "priceCurrentGpGrowth" => price_current_gp_growth,
+                "priceNextYearRevenueGrowth" => price_next_year_revenue_growth,
+                "priceMultiYearRevenueGrowth" => price_multi_year_revenue_growth,
+                "priceMultiYearGpGrowth" => price_multi_year_gp_growth,
+                "priceDcf" => price_dcf,
+            ],
+            trend_fits: [
+                "netInterestMarginShortTermSlope" => net_interest_margin_short_term_slope,
+                "netInterestMarginShortTermRSquared" => net_interest_margin_short_term_r_squared,
+                "costOfRiskShortTermSlope" => cost_of_risk_short_term_slope,
+                "costOfRiskShortTermRSquared" => cost_of_risk_short_term_r_squared,
+                "grossMarginShortTermSlope" => gross_margin_short_term_slope,
+                "grossMarginShortTermRSquared" => gross_margin_short_term_r_squared,
+                "sgaShortTermSlope" => sga_short_term_slope,
+                "sgaShortTermRSquared" => sga_short_term_r_squared,
+                "rndShortTermSlope" => rnd_short_term_slope,
+                "rndShortTermRSquared" => rnd_short_term_r_squared,
+                "operatingMarginShortTermSlope" => operating_margin_short_term_slope,
+                "operatingMarginShortTermRSquared" => operating_margin_short_term_r_squared,
+                "returnOnEquityShortTermSlope" => return_on_equity_short_term_slope,
+                "returnOnEquityShortTermRSquared" => return_on_equity_short_term_r_squared,
+                "returnOnAssetsShortTermSlope" => return_on_assets_short_term_slope,
+                "returnOnAssetsShortTermRSquared" => return_on_assets_short_term_r_squared,
+                "returnOnInvestedCapitalShortTermSlope" => return_on_invested_capital_short_term_slope,
+                "returnOnInvestedCapitalShortTermRSquared" => return_on_invested_capital_short_term_r_squared,
+                "netInterestMarginLongTermSlope" => net_interest_margin_long_term_slope,
+                "netInterestMarginLongTermRSquared" => net_interest_margin_long_term_r_squared,
+                "costOfRiskLongTermSlope" => cost_of_risk_long_term_slope,
+                "costOfRiskLongTermRSquared" => cost_of_risk_long_term_r_squared,
+                "grossMarginLongTermSlope" => gross_margin_long_term_slope,
+                "grossMarginLongTermRSquared" => gross_margin_long_term_r_squared,
+                "sgaLongTermSlope" => sga_long_term_slope,
+                "sgaLongTermRSquared" => sga_long_term_r_squared,
+                "rndLongTermSlope" => rnd_long_term_slope,
+                "rndLongTermRSquared" => rnd_long_term_r_squared,
+                "operatingMarginLongTermSlope" => operating_margin_long_term_slope,
+                "operatingMarginLongTermRSquared" => operating_margin_long_term_r_squared,
+                "sharesChangeSlope" => shares_change_slope,
+                "sharesChangeRSquared" => shares_change_r_squared,
+                "retainedEarningsSlope" => retained_earnings_slope,
+                "retainedEarningsRSquared" => retained_earnings_r_squared,
+                "netCashSlope" => net_cash_slope,
+                "netCashRSquared" => net_cash_r_squared,
+                "operatingCashFlowMarginSlope" => operating_cash_flow_margin_slope,
+                "operatingCashFlowMarginRSquared" => operating_cash_flow_margin_r_squared,
+                "ffoMarginSlope" => ffo_margin_slope,
+                "ffoMarginRSquared" => ffo_margin_r_squared,
+                "returnOnEquityLongTermSlope" => return_on_equity_long_term_slope,
+                "returnOnEquityLongTermRSquared" => return_on_equity_long_term_r_squared,
+                "returnOnAssetsLongTermSlope" => return_on_assets_long_term_slope,
+                "returnOnAssetsLongTermRSquared" => return_on_assets_long_term_r_squared,
+                "returnOnInvestedCapitalLongTermSlope" => return_on_invested_capital_long_term_slope,
+                "returnOnInvestedCapitalLongTermRSquared" => return_on_invested_capital_long_term_r_squared,
+            ],
+            trends: [
+                "netInterestMarginShortTermTrend" => net_interest_margin_short_term_trend,
+                "netInterestMarginLongTermTrend" => net_interest_margin_long_term_trend,
+                "costOfRiskShortTermTrend" => cost_of_risk_short_term_trend,
+                "costOfRiskLongTermTrend" => cost_of_risk_long_term_trend,
+                "grossMarginShortTermTrend" => gross_margin_short_term_trend,
+                "grossMarginLongTermTrend" => gross_margin_long_term_trend,
+                "sgaShortTermTrend" => sga_short_term_trend,
+                "sgaLongTermTrend" => sga_long_term_trend,
+                "rndShortTermTrend" => rnd_short_term_trend,
+                "rndLongTermTrend" => rnd_long_term_trend,
+                "operatingMarginShortTermTrend" => operating_margin_short_term_trend,
+                "operatingMarginLongTermTrend" => operating_margin_long_term_trend,
+                "sharesChangeTrend" => shares_change_trend,
+                "retainedEarningsTrend" => retained_earnings_trend,
+                "netCashTrend" => net_cash_trend,
+                "operatingCashFlowMarginTrend" => operating_cash_flow_margin_trend,
+                "ffoMarginTrend" => ffo_margin_trend,
+                "returnOnEquityShortTermTrend" => return_on_equity_short_term_trend,
+                "returnOnEquityLongTermTrend" => return_on_equity_long_term_trend,
+                "returnOnAssetsShortTermTrend" => return_on_assets_short_term_trend,
+                "returnOnAssetsLongTermTrend" => return_on_assets_long_term_trend,
+                "returnOnInvestedCapitalShortTermTrend" => return_on_invested_capital_short_term_trend,
+                "returnOnInvestedCapitalLongTermTrend" => return_on_invested_capital_long_term_trend,
+            ],
+        }
+    };
+}
+
+pub(crate) use metric_fields;
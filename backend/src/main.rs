@@ -1,104 +1,440 @@
 // use axum::http::StatusCode;
 use axum::{
-    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::ws::WebSocketUpgrade,
+    extract::DefaultBodyLimit,
     extract::Path,
+    extract::Query,
     extract::State,
     response::Response,
-    routing::get,
+    routing::{delete, get, post},
     Json, Router,
 };
+use backtest::BacktestReport;
+use bullseye_api::model::get_exchange_string;
 use db::{establish_connection_pool, lookup_exchange};
 use diesel::pg::PgConnection;
 use diesel::r2d2::{ConnectionManager, Pool};
 use errors::BullsEyeError;
-use http::Method;
+use feed::price_feed_from_env;
+use http::header::CONTENT_TYPE;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use models::companies_model::Company;
 use models::earnings_model::EarningsReport;
 use models::forecast_models::Forecasts;
+use models::industry_model::{IndustryStat, IndustrySummary};
+use models::jobs_model::RefreshJob;
+use models::margin_factors_model::{EffectiveMarginFactor, MarginFactor};
 use models::metrics_model::CurrentMetrics;
+use models::params_model::{
+    BacktestParams, EarningsParams, EventParams, ExpressionParams, IndustryParams, JobParams,
+    MarginFactorParams, MonteCarloParams, PriceParams, SavedScreenParams, ScreenerParams,
+    SimulationParams, SnapshotDiffParams, SnapshotParams, TransferParams, TrendSettingParams,
+};
+use models::prices_model::PriceBar;
 use models::returning_model::ReturningModel;
-use rand::Rng;
-use tokio::time::{self, Duration};
+use models::screens_model::SavedScreen;
+use models::snapshot_model::{Snapshot, SnapshotDiff};
+use models::trend_fits_model::TrendFits;
+use models::trend_settings_model::TrendSettingRow;
+use peers::PeerComparison;
+use scheduler::Scheduler;
+use simulation::{MonteCarloResult, SimulationResult};
+use source::{data_source_from_env, FinancialDataSource};
+use state::AppState;
+use std::sync::Arc;
+use stream::PriceHub;
 use tower_http::cors::CorsLayer;
+use transfer::ImportSummary;
 
+mod backtest;
 mod calculate;
+mod cli;
 mod db;
+mod dcf;
+mod edgar;
 mod errors;
+mod events;
+mod expression;
+mod feed;
+mod fields;
 mod helper;
 mod metrics;
 mod models;
+mod peers;
 mod query;
+mod scheduler;
 mod schema;
+mod scores;
+mod screener;
 mod services;
+mod simulation;
+mod singleflight;
+mod source;
+mod state;
+mod stream;
+mod transfer;
+mod trend_config;
+
+/// earnings files of every company can exceed axum's 2MB default
+const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
 
 async fn search(
     State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    State(source): State<Arc<dyn FinancialDataSource>>,
+    State(scheduler): State<Scheduler>,
     Path(ticker): Path<String>,
 ) -> Result<Json<ReturningModel>, BullsEyeError> {
     let exchange = lookup_exchange(&ticker);
-    let conn = &mut pool.get().unwrap();
-    let company = services::get_company(&ticker, &exchange, conn).await?;
-    let forecast = Forecasts::load_by_id(company.id, conn)?;
-    let earnings_update_needed = forecast.is_earnings_update_needed();
-    if earnings_update_needed {
-        let latest_earnings = EarningsReport::latest_quarter_data_if_existed(company.id, conn)?;
-        let all_earnings = match latest_earnings {
-            Some(earnings) => earnings.quarter_str == 3,
-            None => true,
-        };
-        if all_earnings {
-            services::update_earnings_all(company.id, &ticker, &exchange, conn).await?;
-            services::update_metrics_annual(company.id, conn)?;
-        } else {
-            services::update_earnings_ttm(company.id, &ticker, &exchange, conn).await?;
-            services::update_metrics_ttm(company.id, conn)?;
+    let mut conn = pool.get().map_err(|_| BullsEyeError::DbPoolError)?;
+    let company = match Company::load_by_ticker_if_existed(
+        &ticker,
+        get_exchange_string(&exchange),
+        &mut conn,
+    )? {
+        Some(company) => {
+            // serve cached data and let the scheduler refresh it in the background
+            scheduler.enqueue_if_needed(&company, &mut conn)?;
+            company
         }
-    } else {
-        let regular_update_needed = forecast.is_regular_update_needed();
-        if regular_update_needed {
-            services::update_regular(company.id, &ticker, &exchange, conn).await?;
+        None => {
+            // nothing cached yet, so the first load runs inline
+            let company =
+                services::get_company(source.as_ref(), &ticker, &exchange, &mut conn).await?;
+            if let Some(kind) = services::refresh_needed(company.id, &mut conn)? {
+                // the refresh checks out its own connection, so give this one back meanwhile
+                drop(conn);
+                scheduler
+                    .refresh_now(company.id, &company.ticker, kind)
+                    .await?;
+                conn = pool.get().map_err(|_| BullsEyeError::DbPoolError)?;
+            }
+            company
         }
-        services::update_metrics_annual(company.id, conn)?;
-    }
+    };
+    let conn = &mut conn;
     let all_metrics = CurrentMetrics::load_by_id(company.id, conn)?;
     let all_forecasts = Forecasts::load_by_id(company.id, conn)?;
+    let all_fits = TrendFits::load_by_id(company.id, conn)?;
     Ok(Json(ReturningModel::new(
         company,
         all_metrics,
         all_forecasts,
+        all_fits,
     )))
 }
+async fn get_earnings(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(ticker): Path<String>,
+    Query(params): Query<EarningsParams>,
+) -> Result<Json<Vec<EarningsReport>>, BullsEyeError> {
+    let exchange = lookup_exchange(&ticker);
+    let conn = &mut pool.get().unwrap();
+    let company = services::get_existing_company(&ticker, &exchange, conn)?;
+    let earnings =
+        services::get_earnings_history(company.id, params.duration, params.limit(), conn)?;
+    Ok(Json(earnings))
+}
+
+async fn get_prices(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(ticker): Path<String>,
+    Query(params): Query<PriceParams>,
+) -> Result<Json<Vec<PriceBar>>, BullsEyeError> {
+    let exchange = lookup_exchange(&ticker);
+    let conn = &mut pool.get().unwrap();
+    let company = services::get_existing_company(&ticker, &exchange, conn)?;
+    let prices = services::get_price_history(company.id, &params, conn)?;
+    Ok(Json(prices))
+}
+
+async fn get_peers(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(ticker): Path<String>,
+) -> Result<Json<PeerComparison>, BullsEyeError> {
+    let exchange = lookup_exchange(&ticker);
+    let conn = &mut pool.get().unwrap();
+    let company = services::get_existing_company(&ticker, &exchange, conn)?;
+    Ok(Json(services::compare_with_peers(company, conn)?))
+}
+
+async fn list_industries(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+) -> Result<Json<Vec<IndustrySummary>>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::list_industries(conn)?))
+}
+
+async fn get_industry(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(name): Path<String>,
+    Query(params): Query<IndustryParams>,
+) -> Result<Json<Vec<IndustryStat>>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::get_industry_stats(&name, &params, conn)?))
+}
+
+async fn list_margin_factors(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+) -> Result<Json<Vec<MarginFactor>>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::list_margin_factors(conn)?))
+}
+
+async fn get_margin_factor(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(industry): Path<String>,
+) -> Result<Json<EffectiveMarginFactor>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::get_margin_factor(&industry, conn)?))
+}
+
+async fn set_margin_factor(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(industry): Path<String>,
+    Json(params): Json<MarginFactorParams>,
+) -> Result<Json<MarginFactor>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::set_margin_factor(&industry, &params, conn)?))
+}
+
+async fn delete_margin_factor(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(industry): Path<String>,
+) -> Result<StatusCode, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    services::delete_margin_factor(&industry, conn)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn derive_margin_factors(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+) -> Result<Json<Vec<MarginFactor>>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    services::derive_margin_factors(conn)?;
+    Ok(Json(services::list_margin_factors(conn)?))
+}
+
+async fn list_trend_settings(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+) -> Result<Json<Vec<TrendSettingRow>>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::list_trend_settings(conn)?))
+}
+
+async fn set_trend_setting(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Json(params): Json<TrendSettingParams>,
+) -> Result<Json<TrendSettingRow>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::set_trend_setting(&params, conn)?))
+}
+
+async fn delete_trend_setting(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(setting_id): Path<i32>,
+) -> Result<StatusCode, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    services::delete_trend_setting(setting_id, conn)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn run_backtest(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Query(params): Query<BacktestParams>,
+) -> Result<Json<BacktestReport>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    let report = services::run_backtest(params.horizon_months(), params.industry.as_deref(), conn)?;
+    Ok(Json(report))
+}
+
+async fn simulate(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(ticker): Path<String>,
+    Json(params): Json<SimulationParams>,
+) -> Result<Json<SimulationResult>, BullsEyeError> {
+    let exchange = lookup_exchange(&ticker);
+    let conn = &mut pool.get().unwrap();
+    let company = services::get_existing_company(&ticker, &exchange, conn)?;
+    let result = services::simulate(company.id, &params, conn)?;
+    Ok(Json(result))
+}
+
+async fn simulate_monte_carlo(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(ticker): Path<String>,
+    Query(params): Query<MonteCarloParams>,
+) -> Result<Json<MonteCarloResult>, BullsEyeError> {
+    let exchange = lookup_exchange(&ticker);
+    let conn = &mut pool.get().unwrap();
+    let company = services::get_existing_company(&ticker, &exchange, conn)?;
+    let result = services::simulate_monte_carlo(company.id, &params, conn)?;
+    Ok(Json(result))
+}
+
+async fn get_snapshots(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(ticker): Path<String>,
+    Query(params): Query<SnapshotParams>,
+) -> Result<Json<Vec<Snapshot>>, BullsEyeError> {
+    let exchange = lookup_exchange(&ticker);
+    let conn = &mut pool.get().unwrap();
+    let company = services::get_existing_company(&ticker, &exchange, conn)?;
+    let snapshots = services::get_snapshot_history(company.id, params.limit(), conn)?;
+    Ok(Json(snapshots))
+}
+
+async fn diff_snapshots(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(ticker): Path<String>,
+    Query(params): Query<SnapshotDiffParams>,
+) -> Result<Json<SnapshotDiff>, BullsEyeError> {
+    let exchange = lookup_exchange(&ticker);
+    let conn = &mut pool.get().unwrap();
+    let company = services::get_existing_company(&ticker, &exchange, conn)?;
+    let diff = services::diff_snapshots(company.id, &params, conn)?;
+    Ok(Json(diff))
+}
+
+async fn export_earnings(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Query(params): Query<TransferParams>,
+) -> Result<([(HeaderName, &'static str); 1], String), BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    let contents = services::export_earnings(params.ticker.as_deref(), params.format, conn)?;
+    Ok(([(CONTENT_TYPE, params.format.content_type())], contents))
+}
+
+async fn import_earnings(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Query(params): Query<TransferParams>,
+    body: String,
+) -> Result<Json<ImportSummary>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    let imported = services::import_earnings(&body, params.format, conn)?;
+    Ok(Json(ImportSummary { imported }))
+}
+
 async fn list_all(
     State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Query(params): Query<ScreenerParams>,
+) -> Result<(HeaderMap, Json<Vec<ReturningModel>>), BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    let (all_companies, total) = services::get_all_companies(&params, conn)?;
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(total));
+    Ok((headers, Json(all_companies)))
+}
+
+async fn list_screens(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+) -> Result<Json<Vec<SavedScreen>>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(SavedScreen::load_all(conn)?))
+}
+
+async fn create_screen(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Json(params): Json<SavedScreenParams>,
+) -> Result<(StatusCode, Json<SavedScreen>), BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    let screen = services::create_screen(&params, conn)?;
+    Ok((StatusCode::CREATED, Json(screen)))
+}
+
+async fn get_screen(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(screen_id): Path<i32>,
+) -> Result<Json<SavedScreen>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::get_screen(screen_id, conn)?))
+}
+
+async fn update_screen(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(screen_id): Path<i32>,
+    Json(params): Json<SavedScreenParams>,
+) -> Result<Json<SavedScreen>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::update_screen(screen_id, &params, conn)?))
+}
+
+async fn delete_screen(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(screen_id): Path<i32>,
+) -> Result<StatusCode, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    services::delete_screen(screen_id, conn)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn run_screen(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(screen_id): Path<i32>,
 ) -> Result<Json<Vec<ReturningModel>>, BullsEyeError> {
     let conn = &mut pool.get().unwrap();
-    let all_companies: Vec<ReturningModel> = services::get_all_companies(conn)?;
-    Ok(Json(all_companies))
+    Ok(Json(services::run_screen(screen_id, conn)?))
 }
 
-async fn get_stock_price(ws: WebSocketUpgrade) -> Response {
-    ws.on_upgrade(|socket| handle_socket(socket))
+async fn run_expression(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Json(params): Json<ExpressionParams>,
+) -> Result<Json<Vec<ReturningModel>>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::run_expression(&params.expression, conn)?))
 }
 
-async fn handle_socket(mut socket: WebSocket) {
-    let mut interval = time::interval(Duration::from_millis(100));
-    loop {
-        interval.tick().await;
-        let sample = {
-            let mut rng = rand::rng();
-            100.0 + (rng.random::<f64>() * 50.0)
-        };
-        if socket
-            .send(Message::Text(format!("Price: {}", sample).into()))
-            .await
-            .is_err()
-        {
-            return;
-        }
-    }
+async fn list_jobs(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Query(params): Query<JobParams>,
+) -> Result<Json<Vec<RefreshJob>>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::list_jobs(&params, conn)?))
+}
+
+async fn get_job(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    Path(job_id): Path<i32>,
+) -> Result<Json<RefreshJob>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    Ok(Json(services::get_job(job_id, conn)?))
+}
+
+async fn requeue_job(
+    State(pool): State<Pool<ConnectionManager<PgConnection>>>,
+    State(scheduler): State<Scheduler>,
+    Path(job_id): Path<i32>,
+) -> Result<Json<RefreshJob>, BullsEyeError> {
+    let conn = &mut pool.get().unwrap();
+    let job = services::requeue_job(job_id, conn)?;
+    scheduler.wake();
+    Ok(Json(job))
+}
+
+async fn stream_events(Query(params): Query<EventParams>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(|socket| events::handle_socket(socket, params.ticker))
+}
+
+async fn stream_prices(State(prices): State<PriceHub>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(|socket| prices.handle_socket(socket))
 }
 
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = trend_config::init() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if !args.is_empty() {
+        let pool = establish_connection_pool().unwrap();
+        let conn = &mut pool.get().unwrap();
+        if let Err(e) = cli::run_command(&args, conn) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // build our application with a single route
     let allowed_origins = vec![
         "http://localhost".parse().unwrap(),
@@ -107,13 +443,74 @@ async fn main() {
     ];
     let cors = CorsLayer::new()
         .allow_origin(allowed_origins)
-        .allow_methods([Method::GET]);
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([CONTENT_TYPE])
+        .expose_headers([HeaderName::from_static("x-total-count")]);
     let pool = establish_connection_pool().unwrap();
+    let source = data_source_from_env().unwrap();
+    let scheduler = Scheduler::start(pool.clone(), source.clone());
+    let feed = match price_feed_from_env(source.clone()) {
+        Ok(feed) => feed,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let prices = PriceHub::start(pool.clone(), feed);
+    let state = AppState {
+        pool,
+        source,
+        scheduler,
+        prices,
+    };
     let app = Router::new()
         .route("/screener", get(list_all))
         .route("/companies/{ticker}", get(search))
-        .route("/wstest", get(get_stock_price))
-        .with_state(pool)
+        .route("/companies/{ticker}/earnings", get(get_earnings))
+        .route("/companies/{ticker}/prices", get(get_prices))
+        .route("/companies/{ticker}/peers", get(get_peers))
+        .route("/companies/{ticker}/simulate", post(simulate))
+        .route(
+            "/companies/{ticker}/simulate/montecarlo",
+            get(simulate_monte_carlo),
+        )
+        .route("/companies/{ticker}/snapshots", get(get_snapshots))
+        .route("/companies/{ticker}/snapshots/diff", get(diff_snapshots))
+        .route("/earnings/export", get(export_earnings))
+        .route(
+            "/earnings/import",
+            post(import_earnings).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route("/screens", get(list_screens).post(create_screen))
+        .route("/screens/run", post(run_expression))
+        .route(
+            "/screens/{id}",
+            get(get_screen).put(update_screen).delete(delete_screen),
+        )
+        .route("/screens/{id}/run", get(run_screen))
+        .route("/backtest", get(run_backtest))
+        .route("/industries", get(list_industries))
+        .route("/industries/{name}", get(get_industry))
+        .route("/margin-factors", get(list_margin_factors))
+        .route("/margin-factors/derive", post(derive_margin_factors))
+        .route(
+            "/margin-factors/{industry}",
+            get(get_margin_factor)
+                .put(set_margin_factor)
+                .delete(delete_margin_factor),
+        )
+        .route(
+            "/trend-settings",
+            get(list_trend_settings).put(set_trend_setting),
+        )
+        .route("/trend-settings/{id}", delete(delete_trend_setting))
+        .route("/jobs", get(list_jobs))
+        .route("/jobs/{id}", get(get_job))
+        .route("/jobs/{id}/requeue", post(requeue_job))
+        .route("/ws/events", get(stream_events))
+        .route("/ws/prices", get(stream_prices))
+        .route("/wstest", get(stream_prices))
+        .with_state(state)
         .layer(cors);
 
     // run our app with hyper, listening globally on port 3000
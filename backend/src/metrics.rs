@@ -1,5 +1,7 @@
 use crate::db;
 use crate::models::metrics_model::Trend;
+use crate::models::trend_fits_model::TrendFit;
+use crate::trend_config::{TrendSetting, SHORT_TERM_AVERAGE_PERIODS};
 use crate::{calculate, models::earnings_model::EarningsReport};
 
 /// returns the oretical net margin calculated based on the current gross margin and their industry
@@ -13,6 +15,58 @@ pub fn is_net_margin_optimized(stock_data: &EarningsReport, margin_factor: f64)
     (curr_theoretical_net_margin, is_optimized)
 }
 
+/// theoretical price targets stored in the forecasts table, one per growth rate
+pub struct PriceTargets {
+    pub current_revenue_growth: Option<f64>,
+    pub current_gp_growth: Option<f64>,
+    pub multi_year_revenue_growth: Option<f64>,
+    pub multi_year_gp_growth: Option<f64>,
+}
+
+/// returns theoretical EPS. the actual net margin is kept once it's optimized
+pub fn get_theoretical_eps(
+    revenue: Option<f64>,
+    net_margin: Option<f64>,
+    theoretical_net_margin: f64,
+    is_optimized: bool,
+    shares_outstanding: Option<f64>,
+) -> Option<f64> {
+    let theoretical_net_income = match is_optimized {
+        true => calculate::calculate_margin_portion(revenue, net_margin),
+        false => revenue.map(|val| val * theoretical_net_margin / 100.),
+    };
+    calculate::calculate_per_share(theoretical_net_income, shares_outstanding)
+}
+
+/// calculates price targets from theoretical EPS with current-year and multi-year growth rates
+pub fn get_price_targets(
+    eps: Option<f64>,
+    revenue_growth: Option<f64>,
+    gp_growth: Option<f64>,
+    multi_year_revenue_growth: Option<f64>,
+    multi_year_gp_growth: Option<f64>,
+    shares_change: Option<f64>,
+) -> PriceTargets {
+    PriceTargets {
+        current_revenue_growth: calculate::calculate_price_target_option(
+            eps,
+            revenue_growth,
+            shares_change,
+        ),
+        current_gp_growth: calculate::calculate_price_target_option(eps, gp_growth, shares_change),
+        multi_year_revenue_growth: calculate::calculate_price_target_option(
+            eps,
+            multi_year_revenue_growth,
+            shares_change,
+        ),
+        multi_year_gp_growth: calculate::calculate_price_target_option(
+            eps,
+            multi_year_gp_growth,
+            shares_change,
+        ),
+    }
+}
+
 /// tells if the current net cash is at a healthy level
 pub fn has_healthy_cash_position(stock_data: &EarningsReport) -> bool {
     let curr_net_income = stock_data.net_income;
@@ -20,47 +74,73 @@ pub fn has_healthy_cash_position(stock_data: &EarningsReport) -> bool {
     curr_net_cash >= 0. || (-curr_net_cash / curr_net_income < 2. && curr_net_income > 0.)
 }
 
+/// latest TTM reports within the short-term window of the setting, the trailing averages included
+fn short_term_window<'a>(
+    target: &'a [EarningsReport],
+    setting: &TrendSetting,
+) -> &'a [EarningsReport] {
+    &target[..(setting.length + SHORT_TERM_AVERAGE_PERIODS).min(target.len())]
+}
+
+/// outputs short-term trend with the regression fit over the same TTM reports
 pub fn get_short_term_trend<F>(
     target: &[EarningsReport],
     field: F,
-    length: usize,
-    flat_threshold: f64,
-    count_threshold: usize,
-) -> Trend
+    setting: &TrendSetting,
+) -> (Trend, TrendFit)
 where
     F: Fn(&EarningsReport) -> f64,
 {
-    let values = db::extract_field(&target, field);
-    let trend_vec = calculate::calculate_short_term_trend(&values, length, flat_threshold);
-    let short_term_trend = calculate::concat_trend(trend_vec, count_threshold);
-    short_term_trend
+    let values = db::extract_field(short_term_window(target, setting), field);
+    let trend_vec =
+        calculate::calculate_short_term_trend(&values, setting.length, setting.flat_threshold);
+    let short_term_trend = calculate::concat_trend(trend_vec, setting.count_threshold);
+    let fit = calculate::calculate_trend_fit(&values, setting.flat_threshold);
+    (short_term_trend, fit)
 }
 
 pub fn get_short_term_trend_option<F>(
     target: &[EarningsReport],
     field: F,
-    length: usize,
     ignore_none: bool,
-    flat_threshold: f64,
-    count_threshold: usize,
-) -> Trend
+    setting: &TrendSetting,
+) -> (Trend, TrendFit)
 where
     F: Fn(&EarningsReport) -> Option<f64>,
 {
-    let values = db::extract_field(&target, field);
-    let trend_vec =
-        calculate::calculate_short_term_trend_option(&values, length, ignore_none, flat_threshold);
-    let short_term_trend = calculate::concat_trend(trend_vec, count_threshold);
-    short_term_trend
+    let values = db::extract_field(short_term_window(target, setting), field);
+    let trend_vec = calculate::calculate_short_term_trend_option(
+        &values,
+        setting.length,
+        ignore_none,
+        setting.flat_threshold,
+    );
+    let short_term_trend = calculate::concat_trend(trend_vec, setting.count_threshold);
+    let fit = calculate::calculate_trend_fit_option(&values, ignore_none, setting.flat_threshold);
+    (short_term_trend, fit)
 }
 
-pub fn get_long_term_trend<F>(target: &[EarningsReport], field: F, flat_threshold: f64) -> Trend
+/// latest reports within the long-term window of the setting
+fn long_term_window<'a>(
+    target: &'a [EarningsReport],
+    setting: &TrendSetting,
+) -> &'a [EarningsReport] {
+    &target[..setting.length.min(target.len())]
+}
+
+/// outputs long-term trend with the regression fit over the same annual reports
+pub fn get_long_term_trend<F>(
+    target: &[EarningsReport],
+    field: F,
+    setting: &TrendSetting,
+) -> (Trend, TrendFit)
 where
     F: Fn(&EarningsReport) -> f64,
 {
-    let values = db::extract_field(&target, field);
-    let long_term_trend = calculate::calculate_long_term_trend(&values, flat_threshold);
-    long_term_trend
+    let values = db::extract_field(long_term_window(target, setting), field);
+    let long_term_trend = calculate::calculate_long_term_trend(&values, setting.flat_threshold);
+    let fit = calculate::calculate_trend_fit(&values, setting.flat_threshold);
+    (long_term_trend, fit)
 }
 
 /// outputs long-term trend for the given metrics
@@ -68,13 +148,14 @@ pub fn get_long_term_trend_option<F>(
     target: &[EarningsReport],
     field: F,
     ignore_none: bool,
-    flat_threshold: f64,
-) -> Trend
+    setting: &TrendSetting,
+) -> (Trend, TrendFit)
 where
     F: Fn(&EarningsReport) -> Option<f64>,
 {
-    let values = db::extract_field(&target, field);
+    let values = db::extract_field(long_term_window(target, setting), field);
     let long_term_trend =
-        calculate::calculate_long_term_trend_option(&values, ignore_none, flat_threshold);
-    long_term_trend
+        calculate::calculate_long_term_trend_option(&values, ignore_none, setting.flat_threshold);
+    let fit = calculate::calculate_trend_fit_option(&values, ignore_none, setting.flat_threshold);
+    (long_term_trend, fit)
 }
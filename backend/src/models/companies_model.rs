@@ -1,8 +1,8 @@
 use crate::query;
 use crate::schema::companies;
+use crate::source::Profile;
 use bullseye_api::model::get_exchange_string;
 use bullseye_api::model::Exchange;
-use bullseye_api::profile::CompanyProfile;
 use chrono::{Duration, Local, NaiveDate};
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
@@ -25,11 +25,11 @@ pub struct Company {
 impl Company {
     /// loads campany data if existed
     pub fn load_if_existed(
-        company_profile: &CompanyProfile,
+        company_profile: &Profile,
         conn: &mut PgConnection,
     ) -> Result<Option<Self>, DieselError> {
         use crate::schema::companies::dsl::*;
-        let curr_isin = &company_profile.isin_number;
+        let curr_isin = &company_profile.isin;
         let target =
             query::load_first_row(companies.filter(isin.eq(curr_isin)), conn).optional()?;
         Ok(target)
@@ -50,6 +50,15 @@ impl Company {
         .optional()?;
         Ok(target)
     }
+    pub fn load_by_id(comp_id: i32, conn: &mut PgConnection) -> Result<Self, DieselError> {
+        use crate::schema::companies::dsl::*;
+        query::load_first_row(companies.filter(id.eq(comp_id)), conn)
+    }
+    /// loads every company
+    pub fn load_all(conn: &mut PgConnection) -> Result<Vec<Self>, DieselError> {
+        use crate::schema::companies::dsl::*;
+        companies.order_by(id).load(conn)
+    }
     pub fn ticker_check_needed(&self) -> bool {
         Local::now().date_naive() - self.last_updated >= Duration::days(90)
     }
@@ -1,4 +1,5 @@
 use crate::calculate;
+use crate::edgar::EdgarStatement;
 use crate::helper;
 use crate::query;
 use crate::schema::earnings_report;
@@ -13,9 +14,19 @@ use diesel::prelude::*;
 use diesel::result::Error as DieselError;
 use serde::{Deserialize, Serialize};
 
+/// duration of the earnings data. TTM is stored as "T" and annual as "Y"
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EarningsDuration {
+    #[default]
+    Ttm,
+    Annual,
+}
+
 #[derive(Queryable, Selectable, Serialize)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(table_name = earnings_report)]
+#[serde(rename_all = "camelCase")]
 pub struct EarningsReport {
     id: i32,
     company_id: i32,
@@ -35,12 +46,12 @@ pub struct EarningsReport {
     gross_profit: Option<f64>,
     pub gross_margin: Option<f64>,
     pub gross_profit_growth_yoy: Option<f64>,
-    sga_expenses: Option<f64>,
+    pub sga_expenses: Option<f64>,
     pub sga_gp_ratio: Option<f64>,
     rnd_expenses: Option<f64>,
     pub rnd_gp_ratio: Option<f64>,
     operating_expenses: f64,
-    operating_income: f64,
+    pub operating_income: f64,
     pub operating_margin: f64,
     interest_expenses: Option<f64>,
     pub interest_expenses_op_income_ratio: Option<f64>,
@@ -58,17 +69,17 @@ pub struct EarningsReport {
     cash_and_short_term_investments: Option<f64>,
     total_investments: Option<f64>,
     gross_loans: Option<f64>,
-    accounts_receivable: Option<f64>,
+    pub accounts_receivable: Option<f64>,
     inventory: Option<f64>,
-    total_current_assets: Option<f64>,
+    pub total_current_assets: Option<f64>,
     goodwill: Option<f64>,
-    total_assets: f64,
+    pub total_assets: f64,
     accounts_payable: Option<f64>,
-    total_current_liabilities: Option<f64>,
-    total_liabilities: f64,
+    pub total_current_liabilities: Option<f64>,
+    pub total_liabilities: f64,
     pub retained_earnings: f64,
     shareholders_equity: f64,
-    total_debt: Option<f64>,
+    pub total_debt: Option<f64>,
     pub net_cash: f64,
     depreciation_and_amortization: Option<f64>,
     stock_based_compensation: Option<f64>,
@@ -81,6 +92,12 @@ pub struct EarningsReport {
     pub free_cash_flow_margin: Option<f64>,
     ratio_calculated: bool,
     growth_calculated: bool,
+    pub return_on_equity: Option<f64>,
+    pub return_on_assets: Option<f64>,
+    pub return_on_invested_capital: Option<f64>,
+    pub effective_tax_rate: Option<f64>,
+    pub asset_turnover: Option<f64>,
+    pub equity_multiplier: Option<f64>,
 }
 impl EarningsReport {
     /// retrieves the lastest quarterly(TTM) earnings data for the given ticker
@@ -168,6 +185,22 @@ impl EarningsReport {
         let nt_margin = (self.net_income / self.revenue * 10000.).round() / 100.;
         let ocfm = calculate::calculate_ratio_as_pct(self.operating_cash_flow, self.revenue);
         let ffom = calculate::calculate_ratio_as_pct(self.ffo, self.revenue);
+        let roe =
+            calculate::calculate_ratio_as_pct(Some(self.net_income), self.shareholders_equity);
+        let roa = calculate::calculate_ratio_as_pct(Some(self.net_income), self.total_assets);
+        let tax_rate = calculate::calculate_effective_tax_rate(
+            self.operating_income,
+            self.interest_expenses,
+            self.net_income,
+        );
+        let nopat = self.operating_income * (1. - tax_rate / 100.);
+        let invested_capital =
+            self.shareholders_equity + self.total_debt.unwrap_or(0.) - self.cash_and_equivalents;
+        let roic = calculate::calculate_ratio_as_pct(Some(nopat), invested_capital);
+        // DuPont: roe = net margin * asset turnover * equity multiplier
+        let turnover = calculate::calculate_ratio(Some(self.revenue), self.total_assets);
+        let multiplier =
+            calculate::calculate_ratio(Some(self.total_assets), self.shareholders_equity);
         query::update_earnings_table(
             curr_id,
             (
@@ -180,6 +213,12 @@ impl EarningsReport {
                 net_margin.eq(nt_margin),
                 ffo_margin.eq(ffom),
                 operating_cash_flow_margin.eq(ocfm),
+                return_on_equity.eq(roe),
+                return_on_assets.eq(roa),
+                return_on_invested_capital.eq(roic),
+                effective_tax_rate.eq(tax_rate),
+                asset_turnover.eq(turnover),
+                equity_multiplier.eq(multiplier),
                 ratio_calculated.eq(true),
             ),
             conn,
@@ -219,15 +258,20 @@ impl EarningsReport {
     }
 }
 
-#[derive(Deserialize, Insertable)]
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(table_name = earnings_report)]
-pub struct NewEarningsReport<'a> {
+#[diesel(treat_none_as_null = true)]
+#[serde(rename_all = "camelCase")]
+pub struct NewEarningsReport {
+    #[serde(default, skip_serializing)]
     company_id: i32,
     duration: String,
     quarter_str: i16,
     year_str: i16,
     period_ending: NaiveDate,
-    currency: &'a str,
+    #[serde(default)]
+    currency: String,
     net_interest_income: Option<f64>,
     net_interest_growth_yoy: Option<f64>,
     net_interest_margin: Option<f64>,
@@ -283,13 +327,15 @@ pub struct NewEarningsReport<'a> {
     financing_cash_flow: Option<f64>,
     free_cash_flow: Option<f64>,
     free_cash_flow_margin: Option<f64>,
+    #[serde(default)]
     ratio_calculated: bool,
+    #[serde(default)]
     growth_calculated: bool,
 }
 
-impl<'a> NewEarningsReport<'a> {
+impl NewEarningsReport {
     /// adds new earnings data
-    pub fn create_new_entry(comp_id: i32, currency: &'a str, earnings_enum: Earnings) -> Vec<Self> {
+    pub fn create_new_entry(comp_id: i32, currency: &str, earnings_enum: Earnings) -> Vec<Self> {
         let statement: Vec<NewEarningsReport> = match earnings_enum {
             Earnings::Nominal(val_vec) => val_vec
                 .into_iter()
@@ -328,7 +374,7 @@ impl<'a> NewEarningsReport<'a> {
     }
     fn from_nominal(
         comp_id: i32,
-        currency: &'a str,
+        currency: &str,
         nominal_statement: NominalStatement,
     ) -> Result<Option<Self>, ParseError> {
         if let Some((fiscal_y, fiscal_q)) =
@@ -340,7 +386,7 @@ impl<'a> NewEarningsReport<'a> {
                 quarter_str: fiscal_q,
                 year_str: fiscal_y,
                 period_ending: helper::convert_period_ending_str(&nominal_statement.period_ending)?,
-                currency: currency,
+                currency: currency.to_string(),
                 net_interest_income: None,
                 net_interest_growth_yoy: None,
                 net_interest_margin: None,
@@ -409,7 +455,7 @@ impl<'a> NewEarningsReport<'a> {
     }
     fn from_bank(
         comp_id: i32,
-        currency: &'a str,
+        currency: &str,
         bank_statement: BankStatement,
     ) -> Result<Option<Self>, ParseError> {
         if let Some((fiscal_y, fiscal_q)) =
@@ -421,7 +467,7 @@ impl<'a> NewEarningsReport<'a> {
                 quarter_str: fiscal_q,
                 year_str: fiscal_y,
                 period_ending: helper::convert_period_ending_str(&bank_statement.period_ending)?,
-                currency: currency,
+                currency: currency.to_string(),
                 net_interest_income: Some(bank_statement.net_interest_income),
                 net_interest_growth_yoy: None,
                 net_interest_margin: None,
@@ -486,7 +532,7 @@ impl<'a> NewEarningsReport<'a> {
     }
     fn from_reits(
         comp_id: i32,
-        currency: &'a str,
+        currency: &str,
         reits_statement: ReitsStatement,
     ) -> Result<Option<Self>, ParseError> {
         if let Some((fiscal_y, fiscal_q)) =
@@ -498,7 +544,7 @@ impl<'a> NewEarningsReport<'a> {
                 quarter_str: fiscal_q,
                 year_str: fiscal_y,
                 period_ending: helper::convert_period_ending_str(&reits_statement.period_ending)?,
-                currency: currency,
+                currency: currency.to_string(),
                 net_interest_income: None,
                 net_interest_growth_yoy: None,
                 net_interest_margin: None,
@@ -563,7 +609,7 @@ impl<'a> NewEarningsReport<'a> {
     }
     fn from_other(
         comp_id: i32,
-        currency: &'a str,
+        currency: &str,
         other_statement: OtherStatement,
     ) -> Result<Option<Self>, ParseError> {
         if let Some((fiscal_y, fiscal_q)) =
@@ -575,7 +621,7 @@ impl<'a> NewEarningsReport<'a> {
                 quarter_str: fiscal_q,
                 year_str: fiscal_y,
                 period_ending: helper::convert_period_ending_str(&other_statement.period_ending)?,
-                currency: currency,
+                currency: currency.to_string(),
                 net_interest_income: None,
                 net_interest_growth_yoy: None,
                 net_interest_margin: None,
@@ -638,18 +684,128 @@ impl<'a> NewEarningsReport<'a> {
             Ok(None)
         }
     }
+    /// converts a statement imported from SEC EDGAR
+    pub fn from_edgar(comp_id: i32, currency: &str, statement: EdgarStatement) -> Self {
+        let net_cash = statement
+            .cash_and_short_term_investments
+            .unwrap_or(statement.cash_and_equivalents)
+            - statement.total_debt.unwrap_or(0.);
+        let free_cash_flow = statement
+            .operating_cash_flow
+            .map(|ocf| ocf + statement.capital_expenditure.unwrap_or(0.));
+        NewEarningsReport {
+            company_id: comp_id,
+            duration: statement.term.to_string(),
+            quarter_str: statement.fiscal_quarter,
+            year_str: statement.fiscal_year,
+            period_ending: statement.period_ending,
+            currency: currency.to_string(),
+            net_interest_income: None,
+            net_interest_growth_yoy: None,
+            net_interest_margin: None,
+            provision_for_loan_loss: None,
+            cost_of_risk: None,
+            revenue: statement.revenue,
+            revenue_growth_yoy: statement.revenue_growth_yoy,
+            cost_of_revenue: statement.cost_of_revenue,
+            gross_profit: statement.gross_profit,
+            gross_margin: calculate::calculate_ratio_as_pct(
+                statement.gross_profit,
+                statement.revenue,
+            ),
+            gross_profit_growth_yoy: None,
+            sga_expenses: statement.sga_expenses,
+            sga_gp_ratio: None,
+            rnd_expenses: statement.rnd_expenses,
+            rnd_gp_ratio: None,
+            operating_expenses: statement.operating_expenses,
+            operating_income: statement.operating_income,
+            operating_margin: calculate::calculate_ratio_as_pct(
+                Some(statement.operating_income),
+                statement.revenue,
+            )
+            .unwrap_or(0.),
+            interest_expenses: statement.interest_expenses,
+            interest_expenses_op_income_ratio: None,
+            goodwill_impairment: statement.goodwill_impairment,
+            net_income: statement.net_income,
+            net_margin: calculate::calculate_ratio_as_pct(
+                Some(statement.net_income),
+                statement.revenue,
+            )
+            .unwrap_or(0.),
+            eps_basic: statement.eps_basic,
+            eps_diluted: statement.eps_diluted,
+            shares_outstanding_basic: statement.shares_outstanding_basic,
+            shares_outstanding_diluted: statement.shares_outstanding_diluted,
+            shares_change_yoy: statement.shares_change_yoy,
+            ffo: None,
+            ffo_margin: None,
+            cash_and_equivalents: statement.cash_and_equivalents,
+            cash_and_short_term_investments: statement.cash_and_short_term_investments,
+            total_investments: None,
+            gross_loans: None,
+            accounts_receivable: statement.accounts_receivable,
+            inventory: statement.inventory,
+            total_current_assets: statement.total_current_assets,
+            goodwill: statement.goodwill,
+            total_assets: statement.total_assets,
+            accounts_payable: statement.accounts_payable,
+            total_current_liabilities: statement.total_current_liabilities,
+            total_liabilities: statement.total_liabilities,
+            retained_earnings: statement.retained_earnings,
+            shareholders_equity: statement.shareholders_equity,
+            total_debt: statement.total_debt,
+            net_cash,
+            depreciation_and_amortization: statement.depreciation_and_amortization,
+            stock_based_compensation: statement.stock_based_compensation,
+            operating_cash_flow: statement.operating_cash_flow,
+            operating_cash_flow_margin: None,
+            capital_expenditure: statement.capital_expenditure,
+            investing_cash_flow: statement.investing_cash_flow,
+            financing_cash_flow: statement.financing_cash_flow,
+            free_cash_flow,
+            free_cash_flow_margin: calculate::calculate_ratio_as_pct(
+                free_cash_flow,
+                statement.revenue,
+            ),
+            ratio_calculated: false,
+            growth_calculated: false,
+        }
+    }
+    /// assigns the owning company and currency to an entry loaded from outside the scraper
+    pub fn assign_company(&mut self, comp_id: i32, currency: &str) {
+        self.company_id = comp_id;
+        if self.currency.is_empty() {
+            self.currency = currency.to_string();
+        }
+    }
+    pub fn is_ttm(&self) -> bool {
+        self.duration == "T"
+    }
+    /// inserts the entry or overwrites the existing one for the same period
+    pub fn upsert(&self, conn: &mut PgConnection) -> Result<(), DieselError> {
+        use crate::schema::earnings_report::dsl::*;
+        diesel::insert_into(earnings_report)
+            .values(self)
+            .on_conflict((company_id, duration, quarter_str, year_str))
+            .do_update()
+            .set(self)
+            .execute(conn)?;
+        Ok(())
+    }
 }
 
-/// inserts multiple earnings to the database
+/// inserts multiple earnings to the database. returns the number of new rows
 pub fn insert_earnings_report_batch(
     earnings_entries: Vec<NewEarningsReport>,
     conn: &mut PgConnection,
-) -> Result<bool, DieselError> {
+) -> Result<usize, DieselError> {
     use crate::schema::earnings_report::dsl::*;
     let update_count = diesel::insert_into(earnings_report)
         .values(&earnings_entries)
         .on_conflict((company_id, duration, quarter_str, year_str))
         .do_nothing()
         .execute(conn)?;
-    Ok(update_count > 0)
+    Ok(update_count)
 }
@@ -25,6 +25,8 @@ pub struct Forecasts {
     pub price_next_year_revenue_growth: Option<f64>,
     pub price_multi_year_revenue_growth: Option<f64>,
     pub price_multi_year_gp_growth: Option<f64>,
+    pub price_dcf: Option<f64>,
+    pub implied_growth: Option<f64>,
 }
 
 impl Forecasts {
@@ -62,6 +64,8 @@ pub struct NewForecasts {
     price_next_year_revenue_growth: Option<f64>,
     price_multi_year_revenue_growth: Option<f64>,
     price_multi_year_gp_growth: Option<f64>,
+    price_dcf: Option<f64>,
+    implied_growth: Option<f64>,
 }
 impl NewForecasts {
     pub fn create_empty(company_id: i32) -> Self {
@@ -77,6 +81,8 @@ impl NewForecasts {
             price_next_year_revenue_growth: None,
             price_multi_year_revenue_growth: None,
             price_multi_year_gp_growth: None,
+            price_dcf: None,
+            implied_growth: None,
         }
     }
     pub fn create_new_entry(
@@ -98,6 +104,8 @@ impl NewForecasts {
             price_next_year_revenue_growth: None,
             price_multi_year_revenue_growth: None,
             price_multi_year_gp_growth: None,
+            price_dcf: None,
+            implied_growth: None,
         })
     }
     pub fn insert_new_forecast(&self, conn: &mut PgConnection) -> Result<bool, DieselError> {
@@ -0,0 +1,107 @@
+use crate::fields;
+use crate::schema::{companies, industry_stats};
+use chrono::NaiveDateTime;
+use diesel::dsl::count_star;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use serde::Serialize;
+
+/// exchange value of the rows aggregated over every exchange of the industry
+pub const ALL_EXCHANGES: &str = "All";
+
+macro_rules! stat_columns {
+    (
+        current_metrics: [$($metric_key:literal => $metric:ident $([$flag:ident])?),* $(,)?],
+        forecasts: [$($forecast_key:literal => $forecast:ident),* $(,)?],
+        $($rest:tt)*
+    ) => {
+        /// numeric columns of current_metrics (m) and forecasts (f) aggregated per industry,
+        /// by the camelCase name used in the screener
+        const STAT_COLUMNS: &[(&str, &str)] = &[
+            $(($metric_key, concat!("m.", stringify!($metric))),)*
+            $(($forecast_key, concat!("f.", stringify!($forecast))),)*
+        ];
+    };
+}
+
+fields::metric_fields!(stat_columns);
+
+/// distribution of one metric within an industry, over every exchange or a single one
+#[derive(Queryable, Selectable, Serialize)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = industry_stats)]
+#[serde(rename_all = "camelCase")]
+pub struct IndustryStat {
+    id: i32,
+    pub industry: String,
+    pub exchange: String,
+    pub metric: String,
+    pub count: i64,
+    pub mean: f64,
+    pub median: f64,
+    pub first_quartile: f64,
+    pub third_quartile: f64,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndustrySummary {
+    pub industry: String,
+    pub company_count: i64,
+}
+
+impl IndustryStat {
+    /// loads the statistics of the industry on the given exchange, ordered by metric
+    pub fn load_by_industry(
+        industry_name: &str,
+        exchange_name: &str,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Self>, DieselError> {
+        use crate::schema::industry_stats::dsl::*;
+        industry_stats
+            .filter(industry.eq(industry_name))
+            .filter(exchange.eq(exchange_name))
+            .order(metric.asc())
+            .load::<Self>(conn)
+    }
+    /// lists every industry of the stored companies with its number of companies
+    pub fn load_summaries(conn: &mut PgConnection) -> Result<Vec<IndustrySummary>, DieselError> {
+        companies::table
+            .group_by(companies::industry)
+            .select((companies::industry, count_star()))
+            .order(companies::industry.asc())
+            .load::<IndustrySummary>(conn)
+    }
+    /// recomputes the whole table from current_metrics and forecasts.
+    /// each metric is aggregated per industry and per industry and exchange
+    pub fn refresh_all(conn: &mut PgConnection) -> Result<usize, DieselError> {
+        let values = STAT_COLUMNS
+            .iter()
+            .map(|(name, column)| format!("('{}', {})", name, column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let insert = format!(
+            "INSERT INTO industry_stats \
+                 (industry, exchange, metric, count, mean, median, first_quartile, third_quartile, updated_at) \
+             SELECT c.industry, COALESCE(c.exchange, '{}'), v.metric, count(*), \
+                 round(avg(v.value)::numeric, 2)::float8, \
+                 round((percentile_cont(0.5) WITHIN GROUP (ORDER BY v.value))::numeric, 2)::float8, \
+                 round((percentile_cont(0.25) WITHIN GROUP (ORDER BY v.value))::numeric, 2)::float8, \
+                 round((percentile_cont(0.75) WITHIN GROUP (ORDER BY v.value))::numeric, 2)::float8, \
+                 now() AT TIME ZONE 'UTC' \
+             FROM companies c \
+             JOIN current_metrics m ON m.company_id = c.id \
+             JOIN forecasts f ON f.company_id = c.id \
+             CROSS JOIN LATERAL (VALUES {}) AS v(metric, value) \
+             WHERE v.value IS NOT NULL \
+             GROUP BY GROUPING SETS ((c.industry, v.metric), (c.industry, c.exchange, v.metric))",
+            ALL_EXCHANGES, values
+        );
+        conn.transaction(|conn| {
+            diesel::delete(industry_stats::table).execute(conn)?;
+            diesel::sql_query(insert).execute(conn)
+        })
+    }
+}
@@ -0,0 +1,240 @@
+use crate::query;
+use crate::schema::refresh_jobs;
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use serde::Serialize;
+
+const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+const BASE_BACKOFF_SECONDS: i64 = 60;
+const MAX_BACKOFF_SECONDS: i64 = 24 * 60 * 60;
+/// running jobs untouched for this long are assumed to belong to a dead worker
+const STALE_RUNNING_MINUTES: i64 = 60;
+
+/// lifecycle of a refresh job. stored as lowercase strings
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl JobState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Running => "running",
+            JobState::Succeeded => "succeeded",
+            JobState::Failed => "failed",
+        }
+    }
+}
+
+impl std::str::FromStr for JobState {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "queued" => Ok(JobState::Queued),
+            "running" => Ok(JobState::Running),
+            "succeeded" => Ok(JobState::Succeeded),
+            "failed" => Ok(JobState::Failed),
+            _ => Err(format!("unknown job state '{}'", s)),
+        }
+    }
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+/// delay before the next attempt, doubling after each failure
+fn backoff(attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+    Duration::seconds((BASE_BACKOFF_SECONDS * 2_i64.pow(exponent)).min(MAX_BACKOFF_SECONDS))
+}
+
+#[derive(Queryable, Selectable, Serialize)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = refresh_jobs)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshJob {
+    pub id: i32,
+    pub company_id: i32,
+    pub kind: String,
+    pub state: String,
+    pub attempts: i32,
+    max_attempts: i32,
+    pub last_error: Option<String>,
+    next_run_at: NaiveDateTime,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+impl RefreshJob {
+    /// loads the job if existed
+    pub fn load_by_id_if_existed(
+        job_id: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Option<Self>, DieselError> {
+        use crate::schema::refresh_jobs::dsl::*;
+        let target = query::load_first_row(refresh_jobs.filter(id.eq(job_id)), conn).optional()?;
+        Ok(target)
+    }
+    /// loads the latest jobs, optionally narrowed to a state or a company
+    pub fn load_filtered(
+        curr_state: Option<JobState>,
+        comp_id: Option<i32>,
+        num_row: i64,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Self>, DieselError> {
+        use crate::schema::refresh_jobs::dsl::*;
+        let mut target = refresh_jobs.order(id.desc()).limit(num_row).into_boxed();
+        if let Some(curr_state) = curr_state {
+            target = target.filter(state.eq(curr_state.as_str()));
+        }
+        if let Some(comp_id) = comp_id {
+            target = target.filter(company_id.eq(comp_id));
+        }
+        target.load::<Self>(conn)
+    }
+    /// tells if the company's last job ran out of retries within the max backoff
+    pub fn recently_failed(comp_id: i32, conn: &mut PgConnection) -> Result<bool, DieselError> {
+        use crate::schema::refresh_jobs::dsl::*;
+        let latest_state = refresh_jobs
+            .filter(company_id.eq(comp_id))
+            .filter(updated_at.gt(now() - Duration::seconds(MAX_BACKOFF_SECONDS)))
+            .order(id.desc())
+            .select(state)
+            .first::<String>(conn)
+            .optional()?;
+        Ok(latest_state.as_deref() == Some(JobState::Failed.as_str()))
+    }
+    /// claims the next due job and marks it running.
+    /// locked rows are skipped so several workers never pick the same job
+    pub fn claim_next(conn: &mut PgConnection) -> Result<Option<Self>, DieselError> {
+        use crate::schema::refresh_jobs::dsl::*;
+        conn.transaction(|conn| {
+            let due = refresh_jobs
+                .filter(state.eq(JobState::Queued.as_str()))
+                .filter(next_run_at.le(now()))
+                .order(next_run_at.asc())
+                .select(id)
+                .for_update()
+                .skip_locked()
+                .first::<i32>(conn)
+                .optional()?;
+            match due {
+                Some(job_id) => query::update_and_return_table(
+                    refresh_jobs.filter(id.eq(job_id)),
+                    (
+                        state.eq(JobState::Running.as_str()),
+                        attempts.eq(attempts + 1),
+                        updated_at.eq(now()),
+                    ),
+                    conn,
+                )
+                .map(Some),
+                None => Ok(None),
+            }
+        })
+    }
+    pub fn mark_succeeded(&self, conn: &mut PgConnection) -> Result<(), DieselError> {
+        use crate::schema::refresh_jobs::dsl::*;
+        diesel::update(refresh_jobs.filter(id.eq(self.id)))
+            .set((
+                state.eq(JobState::Succeeded.as_str()),
+                last_error.eq(None::<String>),
+                updated_at.eq(now()),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+    /// stores the error and schedules a retry with backoff, or gives up after max attempts
+    pub fn mark_failed(&self, error: &str, conn: &mut PgConnection) -> Result<(), DieselError> {
+        use crate::schema::refresh_jobs::dsl::*;
+        let next_state = if self.attempts >= self.max_attempts {
+            JobState::Failed
+        } else {
+            JobState::Queued
+        };
+        diesel::update(refresh_jobs.filter(id.eq(self.id)))
+            .set((
+                state.eq(next_state.as_str()),
+                last_error.eq(error),
+                next_run_at.eq(now() + backoff(self.attempts)),
+                updated_at.eq(now()),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+    /// puts a finished job back in the queue with a fresh attempt budget.
+    /// returns None when the job doesn't exist or is still waiting or running
+    pub fn requeue(job_id: i32, conn: &mut PgConnection) -> Result<Option<Self>, DieselError> {
+        use crate::schema::refresh_jobs::dsl::*;
+        query::update_and_return_table(
+            refresh_jobs
+                .filter(id.eq(job_id))
+                .filter(state.eq_any([JobState::Succeeded.as_str(), JobState::Failed.as_str()])),
+            (
+                state.eq(JobState::Queued.as_str()),
+                attempts.eq(0),
+                next_run_at.eq(now()),
+                updated_at.eq(now()),
+            ),
+            conn,
+        )
+        .optional()
+    }
+    /// requeues running jobs whose worker stopped without reporting back
+    pub fn requeue_stale(conn: &mut PgConnection) -> Result<usize, DieselError> {
+        use crate::schema::refresh_jobs::dsl::*;
+        diesel::update(
+            refresh_jobs
+                .filter(state.eq(JobState::Running.as_str()))
+                .filter(updated_at.lt(now() - Duration::minutes(STALE_RUNNING_MINUTES))),
+        )
+        .set((
+            state.eq(JobState::Queued.as_str()),
+            next_run_at.eq(now()),
+            updated_at.eq(now()),
+        ))
+        .execute(conn)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = refresh_jobs)]
+pub struct NewRefreshJob<'a> {
+    company_id: i32,
+    kind: &'a str,
+    state: &'a str,
+    max_attempts: i32,
+    next_run_at: NaiveDateTime,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+impl<'a> NewRefreshJob<'a> {
+    pub fn create_new_entry(company_id: i32, kind: &'a str) -> Self {
+        let created = now();
+        NewRefreshJob {
+            company_id,
+            kind,
+            state: JobState::Queued.as_str(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            next_run_at: created,
+            created_at: created,
+            updated_at: created,
+        }
+    }
+    /// inserts the job unless the company already has one waiting or running
+    pub fn insert_if_not_active(&self, conn: &mut PgConnection) -> Result<bool, DieselError> {
+        use crate::schema::refresh_jobs::dsl::*;
+        let inserted = diesel::insert_into(refresh_jobs)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(inserted > 0)
+    }
+}
@@ -0,0 +1,180 @@
+use crate::calculate;
+use crate::query;
+use crate::schema::industry_margin_factors;
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use diesel::sql_types::Integer;
+use serde::Serialize;
+
+/// industries with fewer profitable companies keep the default factor when deriving
+const MIN_DERIVED_SAMPLES: i32 = 5;
+
+/// where the net margin factor of an industry comes from. stored as lowercase strings
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FactorSource {
+    /// hard-coded in calculate::get_net_margin_factor, never stored
+    Default,
+    /// set through the admin api, never overwritten by deriving
+    Manual,
+    /// median gross-to-net margin ratio of the industry's stored companies
+    Derived,
+}
+
+impl FactorSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FactorSource::Default => "default",
+            FactorSource::Manual => "manual",
+            FactorSource::Derived => "derived",
+        }
+    }
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+#[derive(Queryable, Selectable, Serialize)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = industry_margin_factors)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginFactor {
+    id: i32,
+    pub industry: String,
+    pub factor: f64,
+    pub source: String,
+    /// number of companies the derived factor is based on
+    pub sample_count: Option<i32>,
+    updated_at: NaiveDateTime,
+}
+
+/// factor used for an industry, stored or default
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveMarginFactor {
+    pub industry: String,
+    pub factor: f64,
+    pub source: &'static str,
+}
+
+impl MarginFactor {
+    /// loads every stored factor ordered by industry
+    pub fn load_all(conn: &mut PgConnection) -> Result<Vec<Self>, DieselError> {
+        use crate::schema::industry_margin_factors::dsl::*;
+        industry_margin_factors
+            .order(industry.asc())
+            .load::<Self>(conn)
+    }
+    /// loads the stored factor of the industry if existed
+    pub fn load_by_industry_if_existed(
+        industry_name: &str,
+        conn: &mut PgConnection,
+    ) -> Result<Option<Self>, DieselError> {
+        use crate::schema::industry_margin_factors::dsl::*;
+        let target = query::load_first_row(
+            industry_margin_factors.filter(industry.eq(industry_name)),
+            conn,
+        )
+        .optional()?;
+        Ok(target)
+    }
+    /// returns the stored factor of the industry, falling back to the hard-coded one
+    pub fn effective(
+        industry_name: &str,
+        conn: &mut PgConnection,
+    ) -> Result<EffectiveMarginFactor, DieselError> {
+        let effective = match Self::load_by_industry_if_existed(industry_name, conn)? {
+            Some(stored) => EffectiveMarginFactor {
+                industry: stored.industry,
+                factor: stored.factor,
+                source: match stored.source.as_str() {
+                    "manual" => FactorSource::Manual.as_str(),
+                    _ => FactorSource::Derived.as_str(),
+                },
+            },
+            None => EffectiveMarginFactor {
+                industry: industry_name.to_string(),
+                factor: calculate::get_net_margin_factor(industry_name),
+                source: FactorSource::Default.as_str(),
+            },
+        };
+        Ok(effective)
+    }
+    /// deletes the stored factor so the industry falls back to the default, and tells if it existed
+    pub fn delete_by_industry(
+        industry_name: &str,
+        conn: &mut PgConnection,
+    ) -> Result<bool, DieselError> {
+        use crate::schema::industry_margin_factors::dsl::*;
+        let deleted = diesel::delete(industry_margin_factors.filter(industry.eq(industry_name)))
+            .execute(conn)?;
+        Ok(deleted > 0)
+    }
+    /// replaces every derived factor with the median gross-to-net margin ratio of the latest TTM
+    /// reports in each industry. only profitable companies count, industries with fewer than
+    /// MIN_DERIVED_SAMPLES of them fall back to the default and manual factors are kept
+    pub fn derive_all(conn: &mut PgConnection) -> Result<usize, DieselError> {
+        use crate::schema::industry_margin_factors::dsl::*;
+        let insert = format!(
+            "WITH latest AS ( \
+                 SELECT DISTINCT ON (e.company_id) c.industry, e.gross_margin, e.net_margin \
+                 FROM earnings_report e \
+                 JOIN companies c ON c.id = e.company_id \
+                 WHERE e.duration = 'T' \
+                 ORDER BY e.company_id, e.period_ending DESC \
+             ) \
+             INSERT INTO industry_margin_factors (industry, factor, source, sample_count, updated_at) \
+             SELECT industry, \
+                 round((percentile_cont(0.5) WITHIN GROUP (ORDER BY gross_margin / net_margin))::numeric, 2)::float8, \
+                 '{}', count(*), now() AT TIME ZONE 'UTC' \
+             FROM latest \
+             WHERE net_margin > 0 AND gross_margin >= net_margin \
+             GROUP BY industry \
+             HAVING count(*) >= $1 \
+             ON CONFLICT (industry) DO NOTHING",
+            FactorSource::Derived.as_str()
+        );
+        conn.transaction(|conn| {
+            diesel::delete(
+                industry_margin_factors.filter(source.eq(FactorSource::Derived.as_str())),
+            )
+            .execute(conn)?;
+            diesel::sql_query(insert)
+                .bind::<Integer, _>(MIN_DERIVED_SAMPLES)
+                .execute(conn)
+        })
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = industry_margin_factors)]
+pub struct NewMarginFactor<'a> {
+    industry: &'a str,
+    factor: f64,
+    source: &'a str,
+    sample_count: Option<i32>,
+    updated_at: NaiveDateTime,
+}
+impl<'a> NewMarginFactor<'a> {
+    pub fn create_manual_entry(industry: &'a str, factor: f64) -> Self {
+        NewMarginFactor {
+            industry,
+            factor,
+            source: FactorSource::Manual.as_str(),
+            sample_count: None,
+            updated_at: now(),
+        }
+    }
+    /// stores the factor, overwriting the industry's current one
+    pub fn upsert(&self, conn: &mut PgConnection) -> Result<MarginFactor, DieselError> {
+        use crate::schema::industry_margin_factors::dsl::*;
+        diesel::insert_into(industry_margin_factors)
+            .values(self)
+            .on_conflict(industry)
+            .do_update()
+            .set(self)
+            .get_result::<MarginFactor>(conn)
+    }
+}
@@ -9,6 +9,7 @@ use diesel::result::Error as DieselError;
 use diesel::serialize::{Output, ToSql};
 use diesel::sql_types::Text;
 use serde::Serialize;
+use std::str::FromStr;
 
 #[derive(Debug, PartialEq, Serialize, AsExpression)]
 #[diesel(sql_type = Text)]
@@ -19,17 +20,24 @@ pub enum Trend {
     Irrelevant,
 }
 
+impl Trend {
+    /// returns the value stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Trend::Uptrend => "up",
+            Trend::Downtrend => "down",
+            Trend::Flat => "flat",
+            Trend::Irrelevant => "irrelevant",
+        }
+    }
+}
+
 impl ToSql<Text, Pg> for Trend
 where
     str: ToSql<Text, Pg>,
 {
     fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> diesel::serialize::Result {
-        match self {
-            Trend::Uptrend => <str as ToSql<Text, Pg>>::to_sql("up", out),
-            Trend::Downtrend => <str as ToSql<Text, Pg>>::to_sql("down", out),
-            Trend::Flat => <str as ToSql<Text, Pg>>::to_sql("flat", out),
-            Trend::Irrelevant => <str as ToSql<Text, Pg>>::to_sql("irrelevant", out),
-        }
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
     }
 }
 
@@ -46,6 +54,19 @@ impl FromSql<Text, Pg> for Trend {
     }
 }
 
+impl FromStr for Trend {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "up" | "uptrend" => Ok(Trend::Uptrend),
+            "down" | "downtrend" => Ok(Trend::Downtrend),
+            "flat" => Ok(Trend::Flat),
+            "irrelevant" => Ok(Trend::Irrelevant),
+            x => Err(format!("Invalid trend value detected: {}", x)),
+        }
+    }
+}
+
 #[derive(Queryable, Selectable, Serialize)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(table_name = current_metrics)]
@@ -99,6 +120,20 @@ pub struct CurrentMetrics {
     pub free_cash_flow_margin_ttm: Option<f64>,
     pub ffo_margin_ttm: Option<f64>,
     pub ffo_margin_trend: Option<Trend>,
+    pub piotroski_f_score: Option<f64>,
+    pub altman_z_score: Option<f64>,
+    pub beneish_m_score: Option<f64>,
+    pub return_on_equity_ttm: Option<f64>,
+    pub return_on_equity_short_term_trend: Option<Trend>,
+    pub return_on_equity_long_term_trend: Option<Trend>,
+    pub return_on_assets_ttm: Option<f64>,
+    pub return_on_assets_short_term_trend: Option<Trend>,
+    pub return_on_assets_long_term_trend: Option<Trend>,
+    pub return_on_invested_capital_ttm: Option<f64>,
+    pub return_on_invested_capital_short_term_trend: Option<Trend>,
+    pub return_on_invested_capital_long_term_trend: Option<Trend>,
+    pub asset_turnover_ttm: Option<f64>,
+    pub equity_multiplier_ttm: Option<f64>,
 }
 impl CurrentMetrics {
     /// retrieve metric data for the given company id
@@ -159,6 +194,20 @@ pub struct NewCurrentMetrics<'a> {
     free_cash_flow_margin_ttm: Option<f64>,
     ffo_margin_ttm: Option<f64>,
     ffo_margin_trend: Option<Trend>,
+    piotroski_f_score: Option<f64>,
+    altman_z_score: Option<f64>,
+    beneish_m_score: Option<f64>,
+    return_on_equity_ttm: Option<f64>,
+    return_on_equity_short_term_trend: Option<Trend>,
+    return_on_equity_long_term_trend: Option<Trend>,
+    return_on_assets_ttm: Option<f64>,
+    return_on_assets_short_term_trend: Option<Trend>,
+    return_on_assets_long_term_trend: Option<Trend>,
+    return_on_invested_capital_ttm: Option<f64>,
+    return_on_invested_capital_short_term_trend: Option<Trend>,
+    return_on_invested_capital_long_term_trend: Option<Trend>,
+    asset_turnover_ttm: Option<f64>,
+    equity_multiplier_ttm: Option<f64>,
 }
 
 impl<'a> NewCurrentMetrics<'a> {
@@ -212,6 +261,20 @@ impl<'a> NewCurrentMetrics<'a> {
             free_cash_flow_margin_ttm: None,
             ffo_margin_ttm: None,
             ffo_margin_trend: None,
+            piotroski_f_score: None,
+            altman_z_score: None,
+            beneish_m_score: None,
+            return_on_equity_ttm: None,
+            return_on_equity_short_term_trend: None,
+            return_on_equity_long_term_trend: None,
+            return_on_assets_ttm: None,
+            return_on_assets_short_term_trend: None,
+            return_on_assets_long_term_trend: None,
+            return_on_invested_capital_ttm: None,
+            return_on_invested_capital_short_term_trend: None,
+            return_on_invested_capital_long_term_trend: None,
+            asset_turnover_ttm: None,
+            equity_multiplier_ttm: None,
         })
     }
     /// inserts new ticker data to the metrics database
@@ -1,5 +1,14 @@
 pub mod companies_model;
 pub mod earnings_model;
 pub mod forecast_models;
+pub mod industry_model;
+pub mod jobs_model;
+pub mod margin_factors_model;
 pub mod metrics_model;
+pub mod params_model;
+pub mod prices_model;
 pub mod returning_model;
+pub mod screens_model;
+pub mod snapshot_model;
+pub mod trend_fits_model;
+pub mod trend_settings_model;
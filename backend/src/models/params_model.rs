@@ -0,0 +1,283 @@
+use crate::models::earnings_model::EarningsDuration;
+use crate::models::trend_settings_model::TrendOverride;
+use crate::transfer::TransferFormat;
+use crate::trend_config::{self, TrendTerm, ALL_INDUSTRIES};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+const DEFAULT_EARNINGS_LIMIT: i64 = 20;
+const MAX_EARNINGS_LIMIT: i64 = 200;
+const DEFAULT_SCREENER_LIMIT: i64 = 100;
+const MAX_SCREENER_LIMIT: i64 = 1000;
+const DEFAULT_JOBS_LIMIT: i64 = 50;
+const MAX_JOBS_LIMIT: i64 = 500;
+const DEFAULT_BACKTEST_HORIZON_MONTHS: u32 = 12;
+const MAX_BACKTEST_HORIZON_MONTHS: u32 = 120;
+const DEFAULT_SNAPSHOTS_LIMIT: i64 = 20;
+const MAX_SNAPSHOTS_LIMIT: i64 = 200;
+const DEFAULT_MONTE_CARLO_RUNS: usize = 10000;
+const MAX_MONTE_CARLO_RUNS: usize = 100000;
+const DEFAULT_MONTE_CARLO_SEED: u64 = 42;
+// matches the varchar size of saved_screens.name
+const MAX_SCREEN_NAME_LENGTH: usize = 50;
+
+/// query parameters for the earnings history endpoint
+#[derive(Deserialize)]
+pub struct EarningsParams {
+    #[serde(default)]
+    pub duration: EarningsDuration,
+    limit: Option<i64>,
+}
+
+impl EarningsParams {
+    /// returns the number of rows to load, clamped to a sane range
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_EARNINGS_LIMIT)
+            .clamp(1, MAX_EARNINGS_LIMIT)
+    }
+}
+
+/// query parameters for the screener endpoint.
+/// filter, sort and exchange take comma separated lists and industry a pipe separated one, e.g.
+///     filter=revenueGrowthYoyTtm>20,grossMarginShortTermTrend=up
+///     sort=-revenueGrowthYoyTtm,ticker
+///     industry=Semiconductors|Furnishings, Fixtures & Appliances
+#[derive(Deserialize)]
+pub struct ScreenerParams {
+    pub filter: Option<String>,
+    pub industry: Option<String>,
+    pub exchange: Option<String>,
+    pub sort: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+impl ScreenerParams {
+    /// returns the page size, capped to avoid loading the whole table at once
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_SCREENER_LIMIT)
+            .clamp(1, MAX_SCREENER_LIMIT)
+    }
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+}
+
+/// request body for creating or updating a saved screen
+#[derive(Deserialize)]
+pub struct SavedScreenParams {
+    pub name: String,
+    pub expression: String,
+}
+
+impl SavedScreenParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() || self.name.chars().count() > MAX_SCREEN_NAME_LENGTH {
+            return Err(format!(
+                "name must be between 1 and {} characters",
+                MAX_SCREEN_NAME_LENGTH
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// request body for running an unsaved screening expression
+#[derive(Deserialize)]
+pub struct ExpressionParams {
+    pub expression: String,
+}
+
+/// query parameters for earnings export and import.
+/// export loads every company when ticker is omitted
+#[derive(Deserialize)]
+pub struct TransferParams {
+    #[serde(default)]
+    pub format: TransferFormat,
+    pub ticker: Option<String>,
+}
+
+/// query parameters for listing refresh jobs
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobParams {
+    pub state: Option<String>,
+    pub company_id: Option<i32>,
+    limit: Option<i64>,
+}
+
+impl JobParams {
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_JOBS_LIMIT)
+            .clamp(1, MAX_JOBS_LIMIT)
+    }
+}
+
+/// query parameters for the snapshot history
+#[derive(Deserialize)]
+pub struct SnapshotParams {
+    limit: Option<i64>,
+}
+
+impl SnapshotParams {
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_SNAPSHOTS_LIMIT)
+            .clamp(1, MAX_SNAPSHOTS_LIMIT)
+    }
+}
+
+/// snapshots to compare. `to` defaults to the latest one and `from` to the one before `to`
+#[derive(Deserialize)]
+pub struct SnapshotDiffParams {
+    pub from: Option<i32>,
+    pub to: Option<i32>,
+}
+
+/// date range of the price history. both ends are inclusive and optional
+#[derive(Deserialize)]
+pub struct PriceParams {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// query parameters of the price target backtest
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BacktestParams {
+    horizon_months: Option<u32>,
+    pub industry: Option<String>,
+}
+
+impl BacktestParams {
+    pub fn horizon_months(&self) -> u32 {
+        self.horizon_months
+            .unwrap_or(DEFAULT_BACKTEST_HORIZON_MONTHS)
+            .clamp(1, MAX_BACKTEST_HORIZON_MONTHS)
+    }
+}
+
+/// exchange of the industry statistics. every exchange is aggregated when omitted
+#[derive(Deserialize)]
+pub struct IndustryParams {
+    pub exchange: Option<String>,
+}
+
+/// request body for setting the net margin factor of an industry
+#[derive(Deserialize)]
+pub struct MarginFactorParams {
+    pub factor: f64,
+}
+
+impl MarginFactorParams {
+    /// the theoretical net margin is the gross margin divided by the factor,
+    /// so it can't be exceeded by the net margin
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1. ..=100.).contains(&self.factor) {
+            return Err("factor must be between 1 and 100".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// request body for overriding a trend setting. applies to every industry when none is given
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrendSettingParams {
+    pub metric: String,
+    pub term: String,
+    pub industry: Option<String>,
+    pub length: Option<usize>,
+    pub flat_threshold: Option<f64>,
+    pub count_threshold: Option<usize>,
+}
+
+impl TrendSettingParams {
+    pub fn term(&self) -> Result<TrendTerm, String> {
+        self.term.parse()
+    }
+
+    pub fn industry(&self) -> &str {
+        self.industry.as_deref().unwrap_or(ALL_INDUSTRIES)
+    }
+
+    pub fn as_override(&self) -> TrendOverride {
+        TrendOverride {
+            length: self.length,
+            flat_threshold: self.flat_threshold,
+            count_threshold: self.count_threshold,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let changes = self.as_override();
+        if changes.length.is_none()
+            && changes.flat_threshold.is_none()
+            && changes.count_threshold.is_none()
+        {
+            return Err(
+                "at least one of length, flatThreshold and countThreshold is required".to_string(),
+            );
+        }
+        trend_config::validate_override(self.term()?, &self.metric, &changes)
+    }
+}
+
+/// optional ticker filter of the refresh event stream
+#[derive(Deserialize)]
+pub struct EventParams {
+    pub ticker: Option<String>,
+}
+
+/// overrides of a price target simulation. missing values fall back to the stored metrics
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationParams {
+    pub net_margin: Option<f64>,
+    pub revenue_growth: Option<f64>,
+    pub share_change: Option<f64>,
+    pub revenue_next_year: Option<f64>,
+}
+
+impl SimulationParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .net_margin
+            .is_some_and(|val| !(-100. ..=100.).contains(&val))
+        {
+            return Err("netMargin must be between -100 and 100".to_string());
+        }
+        if self.revenue_growth.is_some_and(|val| val <= -100.) {
+            return Err("revenueGrowth must be greater than -100".to_string());
+        }
+        if self.share_change.is_some_and(|val| val <= -100.) {
+            return Err("shareChange must be greater than -100".to_string());
+        }
+        if self.revenue_next_year.is_some_and(|val| val <= 0.) {
+            return Err("revenueNextYear must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// number of draws and seed of the monte carlo simulation
+#[derive(Deserialize)]
+pub struct MonteCarloParams {
+    runs: Option<usize>,
+    seed: Option<u64>,
+}
+
+impl MonteCarloParams {
+    pub fn runs(&self) -> usize {
+        self.runs
+            .unwrap_or(DEFAULT_MONTE_CARLO_RUNS)
+            .clamp(1, MAX_MONTE_CARLO_RUNS)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed.unwrap_or(DEFAULT_MONTE_CARLO_SEED)
+    }
+}
@@ -0,0 +1,131 @@
+use crate::schema::price_history;
+use chrono::NaiveDate;
+use diesel::dsl::sql;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use diesel::sql_types::{Double, Nullable};
+use diesel::upsert::excluded;
+use serde::{Deserialize, Serialize};
+
+#[derive(Queryable, Selectable, Serialize)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = price_history)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceBar {
+    pub date: NaiveDate,
+    pub open: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub close: f64,
+    pub volume: Option<i64>,
+}
+
+impl PriceBar {
+    /// loads daily prices of the company between two dates (inclusive), oldest first
+    pub fn load_range(
+        comp_id: i32,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Self>, DieselError> {
+        use crate::schema::price_history::dsl::*;
+        let mut target = price_history
+            .filter(company_id.eq(comp_id))
+            .order(date.asc())
+            .select(PriceBar::as_select())
+            .into_boxed();
+        if let Some(from) = from {
+            target = target.filter(date.ge(from));
+        }
+        if let Some(to) = to {
+            target = target.filter(date.le(to));
+        }
+        target.load(conn)
+    }
+}
+
+/// one day of prices. column names of common OHLC CSV exports are accepted
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = price_history)]
+pub struct NewPriceBar {
+    #[serde(default, skip_deserializing)]
+    company_id: i32,
+    #[serde(alias = "Date")]
+    date: NaiveDate,
+    #[serde(alias = "Open")]
+    open: Option<f64>,
+    #[serde(alias = "High")]
+    high: Option<f64>,
+    #[serde(alias = "Low")]
+    low: Option<f64>,
+    #[serde(alias = "Close")]
+    close: f64,
+    #[serde(alias = "Volume")]
+    volume: Option<i64>,
+}
+
+impl NewPriceBar {
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+    /// a bar opened by a single price quote
+    pub fn from_price(company_id: i32, date: NaiveDate, price: f64) -> Self {
+        NewPriceBar {
+            company_id,
+            date,
+            open: Some(price),
+            high: Some(price),
+            low: Some(price),
+            close: price,
+            volume: None,
+        }
+    }
+    pub fn assign_company(&mut self, comp_id: i32) {
+        self.company_id = comp_id;
+    }
+    /// merges a quote into the day's bar: the first quote opens it,
+    /// later ones move high/low and always become the close
+    pub fn record_quote(&self, conn: &mut PgConnection) -> Result<usize, DieselError> {
+        use crate::schema::price_history::dsl::*;
+        diesel::insert_into(price_history)
+            .values(self)
+            .on_conflict((company_id, date))
+            .do_update()
+            .set((
+                high.eq(sql::<Nullable<Double>>(
+                    "GREATEST(price_history.high, excluded.high)",
+                )),
+                low.eq(sql::<Nullable<Double>>(
+                    "LEAST(price_history.low, excluded.low)",
+                )),
+                close.eq(excluded(close)),
+            ))
+            .execute(conn)
+    }
+}
+
+/// inserts imported bars, overwriting days that already exist
+pub fn upsert_price_batch(
+    bars: &[NewPriceBar],
+    conn: &mut PgConnection,
+) -> Result<usize, DieselError> {
+    use crate::schema::price_history::dsl::*;
+    let mut count = 0;
+    // stay below the bind parameter limit of postgres
+    for chunk in bars.chunks(5000) {
+        count += diesel::insert_into(price_history)
+            .values(chunk)
+            .on_conflict((company_id, date))
+            .do_update()
+            .set((
+                open.eq(excluded(open)),
+                high.eq(excluded(high)),
+                low.eq(excluded(low)),
+                close.eq(excluded(close)),
+                volume.eq(excluded(volume)),
+            ))
+            .execute(conn)?;
+    }
+    Ok(count)
+}
@@ -1,6 +1,8 @@
+use crate::fields;
 use crate::models::companies_model::Company;
 use crate::models::forecast_models::Forecasts;
 use crate::models::metrics_model::{CurrentMetrics, Trend};
+use crate::models::trend_fits_model::TrendFits;
 use chrono::NaiveDate;
 use serde::Serialize;
 
@@ -57,20 +59,43 @@ pub struct ReturningModel {
     free_cash_flow_margin_ttm: Option<f64>,
     ffo_margin_ttm: Option<f64>,
     ffo_margin_trend: Option<Trend>,
+    piotroski_f_score: Option<f64>,
+    altman_z_score: Option<f64>,
+    beneish_m_score: Option<f64>,
+    return_on_equity_ttm: Option<f64>,
+    return_on_equity_short_term_trend: Option<Trend>,
+    return_on_equity_long_term_trend: Option<Trend>,
+    return_on_assets_ttm: Option<f64>,
+    return_on_assets_short_term_trend: Option<Trend>,
+    return_on_assets_long_term_trend: Option<Trend>,
+    return_on_invested_capital_ttm: Option<f64>,
+    return_on_invested_capital_short_term_trend: Option<Trend>,
+    return_on_invested_capital_long_term_trend: Option<Trend>,
+    asset_turnover_ttm: Option<f64>,
+    equity_multiplier_ttm: Option<f64>,
     next_earnings_date: Option<NaiveDate>,
     latest_price: Option<f64>,
     last_updated: Option<NaiveDate>,
     revenue_next_year: Option<f64>,
     revenue_growth_next_year: Option<f64>,
+    implied_growth: Option<f64>,
     price_current_revenue_growth: Option<f64>,
     price_current_gp_growth: Option<f64>,
     price_next_year_revenue_growth: Option<f64>,
     price_multi_year_revenue_growth: Option<f64>,
     price_multi_year_gp_growth: Option<f64>,
+    price_dcf: Option<f64>,
+    #[serde(flatten)]
+    trend_fits: TrendFits,
 }
 
 impl ReturningModel {
-    pub fn new(company: Company, metrics: CurrentMetrics, forecasts: Forecasts) -> Self {
+    pub fn new(
+        company: Company,
+        metrics: CurrentMetrics,
+        forecasts: Forecasts,
+        trend_fits: TrendFits,
+    ) -> Self {
         ReturningModel {
             company_name: company.company_name,
             industry: company.industry,
@@ -122,16 +147,91 @@ impl ReturningModel {
             free_cash_flow_margin_ttm: metrics.free_cash_flow_margin_ttm,
             ffo_margin_ttm: metrics.ffo_margin_ttm,
             ffo_margin_trend: metrics.ffo_margin_trend,
+            piotroski_f_score: metrics.piotroski_f_score,
+            altman_z_score: metrics.altman_z_score,
+            beneish_m_score: metrics.beneish_m_score,
+            return_on_equity_ttm: metrics.return_on_equity_ttm,
+            return_on_equity_short_term_trend: metrics.return_on_equity_short_term_trend,
+            return_on_equity_long_term_trend: metrics.return_on_equity_long_term_trend,
+            return_on_assets_ttm: metrics.return_on_assets_ttm,
+            return_on_assets_short_term_trend: metrics.return_on_assets_short_term_trend,
+            return_on_assets_long_term_trend: metrics.return_on_assets_long_term_trend,
+            return_on_invested_capital_ttm: metrics.return_on_invested_capital_ttm,
+            return_on_invested_capital_short_term_trend: metrics
+                .return_on_invested_capital_short_term_trend,
+            return_on_invested_capital_long_term_trend: metrics
+                .return_on_invested_capital_long_term_trend,
+            asset_turnover_ttm: metrics.asset_turnover_ttm,
+            equity_multiplier_ttm: metrics.equity_multiplier_ttm,
             next_earnings_date: forecasts.next_earnings_date,
             latest_price: forecasts.latest_price,
             last_updated: forecasts.last_updated,
             revenue_next_year: forecasts.revenue_next_year,
             revenue_growth_next_year: forecasts.revenue_growth_next_year,
+            implied_growth: forecasts.implied_growth,
             price_current_revenue_growth: forecasts.price_current_revenue_growth,
             price_current_gp_growth: forecasts.price_current_gp_growth,
             price_next_year_revenue_growth: forecasts.price_next_year_revenue_growth,
             price_multi_year_revenue_growth: forecasts.price_multi_year_revenue_growth,
             price_multi_year_gp_growth: forecasts.price_multi_year_gp_growth,
+            price_dcf: forecasts.price_dcf,
+            trend_fits,
         }
     }
 }
+
+/// value of a single field, looked up by its serialized (camelCase) name
+pub enum FieldValue<'a> {
+    Number(Option<f64>),
+    Trend(Option<&'a Trend>),
+    Bool(Option<bool>),
+    Text(&'a str),
+}
+
+macro_rules! field_lookup {
+    (
+        bools: [$($bool_key:literal => $bool_field:ident),* $(,)?],
+        texts: [$($text_key:literal => $text_field:ident),* $(,)?],
+        current_metrics: [$($metric_key:literal => $metric_field:ident $([$flag:ident])?),* $(,)?],
+        forecasts: [$($forecast_key:literal => $forecast_field:ident),* $(,)?],
+        trend_fits: [$($fit_key:literal => $fit_field:ident),* $(,)?],
+        trends: [$($trend_key:literal => $trend_field:ident),* $(,)?] $(,)?
+    ) => {
+        impl ReturningModel {
+            /// names of every field that can be referenced from a screening expression
+            pub const FIELD_NAMES: &'static [&'static str] = &[
+                $($metric_key,)* $($forecast_key,)* $($fit_key,)* $($trend_key,)*
+                $($bool_key,)* $($text_key,)*
+            ];
+
+            /// returns the value of the field with the given camelCase name
+            pub fn field_value(&self, name: &str) -> Option<FieldValue<'_>> {
+                match name {
+                    $($metric_key => Some(FieldValue::Number(self.$metric_field)),)*
+                    $($forecast_key => Some(FieldValue::Number(self.$forecast_field)),)*
+                    $($fit_key => Some(FieldValue::Number(self.trend_fits.$fit_field)),)*
+                    $($trend_key => Some(FieldValue::Trend(self.$trend_field.as_ref())),)*
+                    $($bool_key => Some(FieldValue::Bool(self.$bool_field)),)*
+                    $($text_key => Some(FieldValue::Text(&self.$text_field)),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+// numeric and trend fields come from the shared list, the rest only exist in this model
+fields::metric_fields!(
+    field_lookup,
+    bools: [
+        "isNetMarginOptimized" => is_net_margin_optimized,
+        "hasHealthyNetCash" => has_healthy_net_cash,
+    ],
+    texts: [
+        "companyName" => company_name,
+        "industry" => industry,
+        "exchange" => exchange,
+        "ticker" => ticker,
+        "currency" => currency,
+    ],
+);
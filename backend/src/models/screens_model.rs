@@ -0,0 +1,74 @@
+use crate::query;
+use crate::schema::saved_screens;
+use chrono::{Local, NaiveDate};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use serde::Serialize;
+
+#[derive(Queryable, Selectable, Serialize)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = saved_screens)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedScreen {
+    pub id: i32,
+    pub name: String,
+    pub expression: String,
+    last_updated: NaiveDate,
+}
+impl SavedScreen {
+    /// loads every saved screen ordered by name
+    pub fn load_all(conn: &mut PgConnection) -> Result<Vec<Self>, DieselError> {
+        use crate::schema::saved_screens::dsl::*;
+        saved_screens.order(name.asc()).load::<Self>(conn)
+    }
+    /// loads the saved screen if existed
+    pub fn load_by_id_if_existed(
+        screen_id: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Option<Self>, DieselError> {
+        use crate::schema::saved_screens::dsl::*;
+        let target =
+            query::load_first_row(saved_screens.filter(id.eq(screen_id)), conn).optional()?;
+        Ok(target)
+    }
+    /// deletes the saved screen and tells if it existed
+    pub fn delete_by_id(screen_id: i32, conn: &mut PgConnection) -> Result<bool, DieselError> {
+        use crate::schema::saved_screens::dsl::*;
+        let deleted = diesel::delete(saved_screens.filter(id.eq(screen_id))).execute(conn)?;
+        Ok(deleted > 0)
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = saved_screens)]
+pub struct NewSavedScreen<'a> {
+    name: &'a str,
+    expression: &'a str,
+    last_updated: NaiveDate,
+}
+impl<'a> NewSavedScreen<'a> {
+    pub fn create_new_entry(name: &'a str, expression: &'a str) -> Self {
+        NewSavedScreen {
+            name,
+            expression,
+            last_updated: Local::now().date_naive(),
+        }
+    }
+    pub fn insert_new_screen(&self, conn: &mut PgConnection) -> Result<SavedScreen, DieselError> {
+        use crate::schema::saved_screens::dsl::*;
+        diesel::insert_into(saved_screens)
+            .values(self)
+            .get_result::<SavedScreen>(conn)
+    }
+    /// overwrites the given saved screen, returning None if it doesn't exist
+    pub fn update_screen(
+        &self,
+        screen_id: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Option<SavedScreen>, DieselError> {
+        use crate::schema::saved_screens::dsl::*;
+        query::update_and_return_table(saved_screens.filter(id.eq(screen_id)), self, conn)
+            .optional()
+    }
+}
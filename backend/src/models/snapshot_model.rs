@@ -0,0 +1,203 @@
+use crate::schema::{forecast_snapshots, metrics_snapshots};
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// fields that never change between snapshots of the same company
+const IGNORED_FIELDS: [&str; 2] = ["id", "companyId"];
+
+#[derive(Queryable, Selectable)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = metrics_snapshots)]
+pub struct MetricsSnapshot {
+    id: i32,
+    company_id: i32,
+    reason: String,
+    taken_at: NaiveDateTime,
+    metrics: Value,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = forecast_snapshots)]
+pub struct ForecastSnapshot {
+    forecast: Value,
+}
+
+/// current_metrics and forecasts as they were after one recomputation
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub id: i32,
+    pub company_id: i32,
+    pub reason: String,
+    pub taken_at: NaiveDateTime,
+    pub metrics: Value,
+    pub forecast: Value,
+}
+
+/// a single field that differs between two snapshots
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    pub section: &'static str,
+    pub field: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDiff {
+    pub from: i32,
+    pub to: i32,
+    pub changes: Vec<FieldChange>,
+}
+
+impl Snapshot {
+    fn from_row((metrics, forecast): (MetricsSnapshot, Option<ForecastSnapshot>)) -> Self {
+        Snapshot {
+            id: metrics.id,
+            company_id: metrics.company_id,
+            reason: metrics.reason,
+            taken_at: metrics.taken_at,
+            metrics: metrics.metrics,
+            forecast: forecast.map(|row| row.forecast).unwrap_or(Value::Null),
+        }
+    }
+    /// loads the latest snapshots of the company, newest first.
+    /// only snapshots older than `before` are loaded when given
+    pub fn load_history(
+        comp_id: i32,
+        before: Option<i32>,
+        num_row: i64,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Self>, DieselError> {
+        let mut target = metrics_snapshots::table
+            .left_join(forecast_snapshots::table)
+            .filter(metrics_snapshots::company_id.eq(comp_id))
+            .select((
+                MetricsSnapshot::as_select(),
+                Option::<ForecastSnapshot>::as_select(),
+            ))
+            .order(metrics_snapshots::id.desc())
+            .limit(num_row)
+            .into_boxed();
+        if let Some(before) = before {
+            target = target.filter(metrics_snapshots::id.lt(before));
+        }
+        let rows = target.load::<(MetricsSnapshot, Option<ForecastSnapshot>)>(conn)?;
+        Ok(rows.into_iter().map(Snapshot::from_row).collect())
+    }
+    /// loads the company's snapshot if existed
+    pub fn load_by_id_if_existed(
+        comp_id: i32,
+        snapshot_id: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Option<Self>, DieselError> {
+        let row = metrics_snapshots::table
+            .left_join(forecast_snapshots::table)
+            .filter(metrics_snapshots::company_id.eq(comp_id))
+            .filter(metrics_snapshots::id.eq(snapshot_id))
+            .select((
+                MetricsSnapshot::as_select(),
+                Option::<ForecastSnapshot>::as_select(),
+            ))
+            .first::<(MetricsSnapshot, Option<ForecastSnapshot>)>(conn)
+            .optional()?;
+        Ok(row.map(Snapshot::from_row))
+    }
+    /// lists every field whose value changed from this snapshot to the other one
+    pub fn diff(&self, other: &Snapshot) -> SnapshotDiff {
+        let mut changes = diff_values("metrics", &self.metrics, &other.metrics);
+        changes.extend(diff_values("forecast", &self.forecast, &other.forecast));
+        SnapshotDiff {
+            from: self.id,
+            to: other.id,
+            changes,
+        }
+    }
+}
+
+fn diff_values(section: &'static str, from: &Value, to: &Value) -> Vec<FieldChange> {
+    let (Some(from), Some(to)) = (from.as_object(), to.as_object()) else {
+        return Vec::new();
+    };
+    let fields: BTreeSet<&String> = from.keys().chain(to.keys()).collect();
+    fields
+        .into_iter()
+        .filter(|field| !IGNORED_FIELDS.contains(&field.as_str()))
+        .filter_map(|field| {
+            let before = from.get(field).unwrap_or(&Value::Null);
+            let after = to.get(field).unwrap_or(&Value::Null);
+            (before != after).then(|| FieldChange {
+                section,
+                field: field.clone(),
+                from: before.clone(),
+                to: after.clone(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = metrics_snapshots)]
+pub struct NewMetricsSnapshot<'a> {
+    company_id: i32,
+    reason: &'a str,
+    taken_at: NaiveDateTime,
+    metrics: Value,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = forecast_snapshots)]
+struct NewForecastSnapshot {
+    company_id: i32,
+    metrics_snapshot_id: i32,
+    taken_at: NaiveDateTime,
+    forecast: Value,
+}
+
+impl<'a> NewMetricsSnapshot<'a> {
+    pub fn create_new_entry(company_id: i32, reason: &'a str, metrics: Value) -> Self {
+        NewMetricsSnapshot {
+            company_id,
+            reason,
+            taken_at: Utc::now().naive_utc(),
+            metrics,
+        }
+    }
+    /// stores the metrics with the forecast taken at the same time.
+    /// skips the insert when nothing changed since the latest snapshot
+    pub fn insert_with_forecast(
+        &self,
+        forecast: Value,
+        conn: &mut PgConnection,
+    ) -> Result<bool, DieselError> {
+        conn.transaction(|conn| {
+            let latest = Snapshot::load_history(self.company_id, None, 1, conn)?;
+            if let Some(latest) = latest.first() {
+                if latest.metrics == self.metrics && latest.forecast == forecast {
+                    return Ok(false);
+                }
+            }
+            let snapshot_id = diesel::insert_into(metrics_snapshots::table)
+                .values(self)
+                .returning(metrics_snapshots::id)
+                .get_result::<i32>(conn)?;
+            diesel::insert_into(forecast_snapshots::table)
+                .values(NewForecastSnapshot {
+                    company_id: self.company_id,
+                    metrics_snapshot_id: snapshot_id,
+                    taken_at: self.taken_at,
+                    forecast,
+                })
+                .execute(conn)?;
+            Ok(true)
+        })
+    }
+}
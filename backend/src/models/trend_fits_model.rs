@@ -0,0 +1,184 @@
+use crate::query;
+use crate::schema::trend_fits;
+use diesel::deserialize::FromSql;
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgConnection, PgValue};
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use diesel::serialize::{Output, ToSql};
+use diesel::sql_types::Text;
+use serde::Serialize;
+
+/// whether a trend gains or loses momentum, from the curvature of a quadratic fit
+#[derive(Debug, PartialEq, Serialize, AsExpression)]
+#[diesel(sql_type = Text)]
+pub enum Acceleration {
+    Accelerating,
+    Decelerating,
+    Steady,
+    Irrelevant,
+}
+
+impl Acceleration {
+    /// returns the value stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Acceleration::Accelerating => "accelerating",
+            Acceleration::Decelerating => "decelerating",
+            Acceleration::Steady => "steady",
+            Acceleration::Irrelevant => "irrelevant",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for Acceleration
+where
+    str: ToSql<Text, Pg>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> diesel::serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for Acceleration {
+    fn from_sql(bytes: PgValue<'_>) -> diesel::deserialize::Result<Self> {
+        let s = std::str::from_utf8(bytes.as_bytes())?;
+        match s {
+            "accelerating" => Ok(Acceleration::Accelerating),
+            "decelerating" => Ok(Acceleration::Decelerating),
+            "steady" => Ok(Acceleration::Steady),
+            "irrelevant" => Ok(Acceleration::Irrelevant),
+            x => Err(format!("Invalid acceleration value detected: {}", x).into()),
+        }
+    }
+}
+
+/// least-squares fit of a metric over the periods of its trend.
+/// the slope is the change per period (TTM quarter or year) and R² tells how well the line fits
+#[derive(Debug, PartialEq)]
+pub struct TrendFit {
+    pub slope: Option<f64>,
+    pub r_squared: Option<f64>,
+    pub acceleration: Acceleration,
+}
+
+impl TrendFit {
+    pub fn irrelevant() -> Self {
+        TrendFit {
+            slope: None,
+            r_squared: None,
+            acceleration: Acceleration::Irrelevant,
+        }
+    }
+}
+
+/// regression fits of every trend in the metrics table, one row per company
+#[derive(Queryable, Selectable, Serialize)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = trend_fits)]
+#[serde(rename_all = "camelCase")]
+pub struct TrendFits {
+    #[serde(skip)]
+    id: i32,
+    #[serde(skip)]
+    company_id: i32,
+    pub net_interest_margin_short_term_slope: Option<f64>,
+    pub net_interest_margin_short_term_r_squared: Option<f64>,
+    pub net_interest_margin_short_term_acceleration: Option<Acceleration>,
+    pub cost_of_risk_short_term_slope: Option<f64>,
+    pub cost_of_risk_short_term_r_squared: Option<f64>,
+    pub cost_of_risk_short_term_acceleration: Option<Acceleration>,
+    pub gross_margin_short_term_slope: Option<f64>,
+    pub gross_margin_short_term_r_squared: Option<f64>,
+    pub gross_margin_short_term_acceleration: Option<Acceleration>,
+    pub sga_short_term_slope: Option<f64>,
+    pub sga_short_term_r_squared: Option<f64>,
+    pub sga_short_term_acceleration: Option<Acceleration>,
+    pub rnd_short_term_slope: Option<f64>,
+    pub rnd_short_term_r_squared: Option<f64>,
+    pub rnd_short_term_acceleration: Option<Acceleration>,
+    pub operating_margin_short_term_slope: Option<f64>,
+    pub operating_margin_short_term_r_squared: Option<f64>,
+    pub operating_margin_short_term_acceleration: Option<Acceleration>,
+    pub return_on_equity_short_term_slope: Option<f64>,
+    pub return_on_equity_short_term_r_squared: Option<f64>,
+    pub return_on_equity_short_term_acceleration: Option<Acceleration>,
+    pub return_on_assets_short_term_slope: Option<f64>,
+    pub return_on_assets_short_term_r_squared: Option<f64>,
+    pub return_on_assets_short_term_acceleration: Option<Acceleration>,
+    pub return_on_invested_capital_short_term_slope: Option<f64>,
+    pub return_on_invested_capital_short_term_r_squared: Option<f64>,
+    pub return_on_invested_capital_short_term_acceleration: Option<Acceleration>,
+    pub net_interest_margin_long_term_slope: Option<f64>,
+    pub net_interest_margin_long_term_r_squared: Option<f64>,
+    pub net_interest_margin_long_term_acceleration: Option<Acceleration>,
+    pub cost_of_risk_long_term_slope: Option<f64>,
+    pub cost_of_risk_long_term_r_squared: Option<f64>,
+    pub cost_of_risk_long_term_acceleration: Option<Acceleration>,
+    pub gross_margin_long_term_slope: Option<f64>,
+    pub gross_margin_long_term_r_squared: Option<f64>,
+    pub gross_margin_long_term_acceleration: Option<Acceleration>,
+    pub sga_long_term_slope: Option<f64>,
+    pub sga_long_term_r_squared: Option<f64>,
+    pub sga_long_term_acceleration: Option<Acceleration>,
+    pub rnd_long_term_slope: Option<f64>,
+    pub rnd_long_term_r_squared: Option<f64>,
+    pub rnd_long_term_acceleration: Option<Acceleration>,
+    pub operating_margin_long_term_slope: Option<f64>,
+    pub operating_margin_long_term_r_squared: Option<f64>,
+    pub operating_margin_long_term_acceleration: Option<Acceleration>,
+    pub shares_change_slope: Option<f64>,
+    pub shares_change_r_squared: Option<f64>,
+    pub shares_change_acceleration: Option<Acceleration>,
+    pub retained_earnings_slope: Option<f64>,
+    pub retained_earnings_r_squared: Option<f64>,
+    pub retained_earnings_acceleration: Option<Acceleration>,
+    pub net_cash_slope: Option<f64>,
+    pub net_cash_r_squared: Option<f64>,
+    pub net_cash_acceleration: Option<Acceleration>,
+    pub operating_cash_flow_margin_slope: Option<f64>,
+    pub operating_cash_flow_margin_r_squared: Option<f64>,
+    pub operating_cash_flow_margin_acceleration: Option<Acceleration>,
+    pub ffo_margin_slope: Option<f64>,
+    pub ffo_margin_r_squared: Option<f64>,
+    pub ffo_margin_acceleration: Option<Acceleration>,
+    pub return_on_equity_long_term_slope: Option<f64>,
+    pub return_on_equity_long_term_r_squared: Option<f64>,
+    pub return_on_equity_long_term_acceleration: Option<Acceleration>,
+    pub return_on_assets_long_term_slope: Option<f64>,
+    pub return_on_assets_long_term_r_squared: Option<f64>,
+    pub return_on_assets_long_term_acceleration: Option<Acceleration>,
+    pub return_on_invested_capital_long_term_slope: Option<f64>,
+    pub return_on_invested_capital_long_term_r_squared: Option<f64>,
+    pub return_on_invested_capital_long_term_acceleration: Option<Acceleration>,
+}
+
+impl TrendFits {
+    pub fn load_by_id(comp_id: i32, conn: &mut PgConnection) -> Result<Self, DieselError> {
+        use crate::schema::trend_fits::dsl::*;
+        let target = query::load_first_row(trend_fits.filter(company_id.eq(comp_id)), conn)?;
+        Ok(target)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = trend_fits)]
+pub struct NewTrendFits {
+    company_id: i32,
+}
+
+impl NewTrendFits {
+    ///creates an empty entry, filled by the next trend update
+    pub fn create_empty(company_id: i32) -> Self {
+        NewTrendFits { company_id }
+    }
+    pub fn insert_new_fits(&self, conn: &mut PgConnection) -> Result<bool, DieselError> {
+        use crate::schema::trend_fits::dsl::*;
+        let updated = diesel::insert_into(trend_fits)
+            .values(self)
+            .on_conflict(company_id)
+            .do_nothing()
+            .execute(conn)?;
+        Ok(updated > 0)
+    }
+}
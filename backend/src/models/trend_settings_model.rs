@@ -0,0 +1,111 @@
+use crate::schema::trend_settings;
+use crate::trend_config::ALL_INDUSTRIES;
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use serde::{Deserialize, Serialize};
+use std::num::TryFromIntError;
+
+/// changes to the built-in setting of a trend. missing values are left as they are
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct TrendOverride {
+    pub length: Option<usize>,
+    pub flat_threshold: Option<f64>,
+    pub count_threshold: Option<usize>,
+}
+
+/// override of a trend setting stored in the database, for every industry or a single one
+#[derive(Queryable, Selectable, Serialize)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = trend_settings)]
+#[serde(rename_all = "camelCase")]
+pub struct TrendSettingRow {
+    pub id: i32,
+    pub metric: String,
+    pub term: String,
+    pub industry: String,
+    length: Option<i32>,
+    flat_threshold: Option<f64>,
+    count_threshold: Option<i32>,
+    updated_at: NaiveDateTime,
+}
+
+impl TrendSettingRow {
+    pub fn as_override(&self) -> TrendOverride {
+        TrendOverride {
+            // a negative value edited into the table falls back to the default instead of wrapping
+            length: self.length.and_then(|val| usize::try_from(val).ok()),
+            flat_threshold: self.flat_threshold,
+            count_threshold: self
+                .count_threshold
+                .and_then(|val| usize::try_from(val).ok()),
+        }
+    }
+    /// loads every stored override
+    pub fn load_all(conn: &mut PgConnection) -> Result<Vec<Self>, DieselError> {
+        use crate::schema::trend_settings::dsl::*;
+        trend_settings
+            .order((industry.asc(), term.asc(), metric.asc()))
+            .load::<Self>(conn)
+    }
+    /// loads the overrides applying to the industry, the ones for every industry first
+    pub fn load_for_industry(
+        industry_name: &str,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Self>, DieselError> {
+        use crate::schema::trend_settings::dsl::*;
+        trend_settings
+            .filter(industry.eq_any([ALL_INDUSTRIES, industry_name]))
+            .order((industry.eq(ALL_INDUSTRIES).desc(), id.asc()))
+            .load::<Self>(conn)
+    }
+    /// deletes the override and tells if it existed
+    pub fn delete_by_id(setting_id: i32, conn: &mut PgConnection) -> Result<bool, DieselError> {
+        use crate::schema::trend_settings::dsl::*;
+        let deleted = diesel::delete(trend_settings.filter(id.eq(setting_id))).execute(conn)?;
+        Ok(deleted > 0)
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = trend_settings)]
+#[diesel(treat_none_as_null = true)]
+pub struct NewTrendSettingRow<'a> {
+    metric: &'a str,
+    term: &'a str,
+    industry: &'a str,
+    length: Option<i32>,
+    flat_threshold: Option<f64>,
+    count_threshold: Option<i32>,
+    updated_at: NaiveDateTime,
+}
+impl<'a> NewTrendSettingRow<'a> {
+    pub fn create_new_entry(
+        metric: &'a str,
+        term: &'a str,
+        industry: &'a str,
+        changes: &TrendOverride,
+    ) -> Result<Self, TryFromIntError> {
+        Ok(NewTrendSettingRow {
+            metric,
+            term,
+            industry,
+            length: changes.length.map(i32::try_from).transpose()?,
+            flat_threshold: changes.flat_threshold,
+            count_threshold: changes.count_threshold.map(i32::try_from).transpose()?,
+            updated_at: Utc::now().naive_utc(),
+        })
+    }
+    /// stores the override, replacing the one of the same metric, term and industry
+    pub fn upsert(&self, conn: &mut PgConnection) -> Result<TrendSettingRow, DieselError> {
+        use crate::schema::trend_settings::dsl::*;
+        diesel::insert_into(trend_settings)
+            .values(self)
+            .on_conflict((metric, term, industry))
+            .do_update()
+            .set(self)
+            .get_result::<TrendSettingRow>(conn)
+    }
+}
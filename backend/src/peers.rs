@@ -0,0 +1,138 @@
+use crate::fields;
+use crate::models::companies_model::Company;
+use crate::models::returning_model::ReturningModel;
+use crate::schema::{companies, current_metrics, forecasts, trend_fits};
+use crate::screener::ScreenerRow;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use diesel::sql_types::{BigInt, Double, Integer, Nullable, Text, Varchar};
+use serde::Serialize;
+
+macro_rules! peer_metrics {
+    (
+        current_metrics: [$($key:literal => $column:ident $([$flag:ident])?),* $(,)?],
+        $($rest:tt)*
+    ) => {
+        /// current_metrics columns compared between peers, by their camelCase name.
+        /// None for the columns not marked as peer metrics
+        const PEER_METRICS: &[Option<(&str, &str)>] = &[
+            $(peer_metric!($key, $column $(, $flag)?),)*
+        ];
+    };
+}
+
+macro_rules! peer_metric {
+    ($key:literal, $column:ident, peer) => {
+        Some(($key, stringify!($column)))
+    };
+    ($key:literal, $column:ident) => {
+        None
+    };
+}
+
+fields::metric_fields!(peer_metrics);
+
+/// where the company stands within its industry for one metric
+#[derive(QueryableByName, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerMetric {
+    #[diesel(sql_type = Text)]
+    pub metric: String,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub value: Option<f64>,
+    /// share of peers with a lower value, in percentage. None when the company has no value
+    #[diesel(sql_type = Nullable<Double>)]
+    pub percentile_rank: Option<f64>,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub industry_median: Option<f64>,
+    /// number of companies in the industry with a value, the company included
+    #[diesel(sql_type = BigInt)]
+    pub peer_count: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerComparison {
+    pub ticker: String,
+    pub industry: String,
+    pub peers: Vec<ReturningModel>,
+    pub metrics: Vec<PeerMetric>,
+}
+
+/// loads the other companies of the industry with their metrics, forecasts and trend fits
+fn load_peers(company: &Company, conn: &mut PgConnection) -> Result<Vec<ScreenerRow>, DieselError> {
+    companies::table
+        .inner_join(current_metrics::table)
+        .inner_join(forecasts::table)
+        .inner_join(trend_fits::table)
+        .filter(companies::industry.eq(&company.industry))
+        .filter(companies::id.ne(company.id))
+        .order(companies::ticker.asc())
+        .load::<ScreenerRow>(conn)
+}
+
+/// builds the ranking query. every metric becomes a row of the lateral VALUES list,
+/// so a single window partitioned by metric ranks all of them at once
+fn ranking_query() -> String {
+    let values = PEER_METRICS
+        .iter()
+        .flatten()
+        .map(|(name, column)| format!("('{}', m.{})", name, column))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "SELECT ranked.metric, \
+             max(ranked.value) FILTER (WHERE ranked.company_id = $2) AS value, \
+             max(ranked.percentile_rank) FILTER (WHERE ranked.company_id = $2) AS percentile_rank, \
+             percentile_cont(0.5) WITHIN GROUP (ORDER BY ranked.value) AS industry_median, \
+             count(*) AS peer_count \
+         FROM ( \
+             SELECT m.company_id, v.metric, v.value, \
+                 round((percent_rank() OVER (PARTITION BY v.metric ORDER BY v.value) * 100)::numeric, 2)::float8 AS percentile_rank \
+             FROM current_metrics m \
+             JOIN companies c ON c.id = m.company_id \
+             CROSS JOIN LATERAL (VALUES {}) AS v(metric, value) \
+             WHERE c.industry = $1 AND v.value IS NOT NULL \
+         ) ranked \
+         GROUP BY ranked.metric",
+        values
+    )
+}
+
+/// percentile ranks and industry medians of the company's key metrics
+fn rank_metrics(
+    company: &Company,
+    conn: &mut PgConnection,
+) -> Result<Vec<PeerMetric>, DieselError> {
+    let mut ranked = diesel::sql_query(ranking_query())
+        .bind::<Varchar, _>(&company.industry)
+        .bind::<Integer, _>(company.id)
+        .load::<PeerMetric>(conn)?;
+    // keeps the order of PEER_METRICS instead of the alphabetical group order
+    ranked.sort_by_key(|row| {
+        PEER_METRICS
+            .iter()
+            .flatten()
+            .position(|(name, _)| *name == row.metric)
+    });
+    Ok(ranked)
+}
+
+/// compares the company with every stored company of the same industry
+pub fn compare_with_peers(
+    company: Company,
+    conn: &mut PgConnection,
+) -> Result<PeerComparison, DieselError> {
+    let peers = load_peers(&company, conn)?
+        .into_iter()
+        .map(|(peer, metrics, forecast, fits)| ReturningModel::new(peer, metrics, forecast, fits))
+        .collect();
+    let metrics = rank_metrics(&company, conn)?;
+    Ok(PeerComparison {
+        ticker: company.ticker,
+        industry: company.industry,
+        peers,
+        metrics,
+    })
+}
@@ -1,5 +1,5 @@
 use crate::models::earnings_model::EarningsReport;
-use crate::schema::{current_metrics, earnings_report, forecasts};
+use crate::schema::{current_metrics, earnings_report, forecasts, trend_fits};
 use chrono::Local;
 use diesel::associations::HasTable;
 use diesel::helper_types::Limit;
@@ -154,6 +154,19 @@ where
     )
 }
 
+pub fn update_trend_fits_table<U>(
+    comp_id: i32,
+    updates: U,
+    conn: &mut PgConnection,
+) -> Result<usize, DieselError>
+where
+    U: AsChangeset<Target = trend_fits::table>,
+    U::Changeset: QueryFragment<Pg>,
+{
+    use crate::schema::trend_fits::dsl::*;
+    update_table(trend_fits.filter(company_id.eq(comp_id)), updates, conn)
+}
+
 pub fn update_forecasts_table<U>(
     comp_id: i32,
     updates: U,
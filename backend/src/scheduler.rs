@@ -0,0 +1,337 @@
+use crate::db;
+use crate::errors::BullsEyeError;
+use crate::events;
+use crate::events::RefreshEvent;
+use crate::models::companies_model::Company;
+use crate::models::jobs_model::{NewRefreshJob, RefreshJob};
+use crate::services;
+use crate::singleflight::SingleFlight;
+use crate::source::FinancialDataSource;
+use diesel::pg::PgConnection;
+use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use std::env;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::time::{self, Duration, Instant};
+
+const DEFAULT_REFRESH_INTERVAL_MINUTES: u64 = 60;
+const DEFAULT_WORKER_COUNT: usize = 1;
+const DEFAULT_INDUSTRY_STATS_INTERVAL_MINUTES: u64 = 360;
+/// idle workers look for due retries at least this often
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// how often to retry while another instance holds the company's refresh lock
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+/// gives up on the refresh when another instance holds the lock for longer than this
+const LOCK_WAIT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// refresh decided from the forecast staleness checks
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RefreshKind {
+    /// earnings date, price and estimates only
+    Regular,
+    /// after Q1-Q3 earnings
+    EarningsTtm,
+    /// after Q4 earnings or for a company without earnings
+    EarningsAll,
+}
+
+impl RefreshKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RefreshKind::Regular => "regular",
+            RefreshKind::EarningsTtm => "earnings_ttm",
+            RefreshKind::EarningsAll => "earnings_all",
+        }
+    }
+}
+
+impl std::str::FromStr for RefreshKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "regular" => Ok(RefreshKind::Regular),
+            "earnings_ttm" => Ok(RefreshKind::EarningsTtm),
+            "earnings_all" => Ok(RefreshKind::EarningsAll),
+            _ => Err(format!("unknown refresh kind '{}'", s)),
+        }
+    }
+}
+
+/// handle to the refresh_jobs queue. workers are woken up right away when a job is added.
+/// refreshes of the same company are deduplicated between requests and workers
+#[derive(Clone)]
+pub struct Scheduler {
+    notify: Arc<Notify>,
+    flights: SingleFlight,
+    pool: Pool<ConnectionManager<PgConnection>>,
+    source: Arc<dyn FinancialDataSource>,
+}
+
+impl Scheduler {
+    /// spawns the workers, the crawler walking every company periodically and the industry
+    /// statistics refresh. configured with REFRESH_WORKERS (1 by default),
+    /// REFRESH_INTERVAL_MINUTES (60 by default) and INDUSTRY_STATS_INTERVAL_MINUTES (360 by default).
+    /// net margin factors are derived along with the statistics when NET_MARGIN_FACTORS=derived
+    pub fn start(
+        pool: Pool<ConnectionManager<PgConnection>>,
+        source: Arc<dyn FinancialDataSource>,
+    ) -> Self {
+        let scheduler = Scheduler {
+            notify: Arc::new(Notify::new()),
+            flights: SingleFlight::default(),
+            pool,
+            source,
+        };
+        for _ in 0..env_or("REFRESH_WORKERS", DEFAULT_WORKER_COUNT) {
+            tokio::spawn(run_worker(scheduler.clone()));
+        }
+        let period = Duration::from_secs(
+            env_or("REFRESH_INTERVAL_MINUTES", DEFAULT_REFRESH_INTERVAL_MINUTES) * 60,
+        );
+        tokio::spawn(run_crawler(scheduler.clone(), period));
+        let stats_period = Duration::from_secs(
+            env_or(
+                "INDUSTRY_STATS_INTERVAL_MINUTES",
+                DEFAULT_INDUSTRY_STATS_INTERVAL_MINUTES,
+            ) * 60,
+        );
+        let derive_factors = env::var("NET_MARGIN_FACTORS").is_ok_and(|mode| mode == "derived");
+        tokio::spawn(run_industry_stats(
+            scheduler.pool.clone(),
+            stats_period,
+            derive_factors,
+        ));
+        scheduler
+    }
+
+    /// refreshes the company right away. joins the running refresh if there is one.
+    /// when another instance held the lock, the refresh only runs if it's still needed
+    pub async fn refresh_now(
+        &self,
+        company_id: i32,
+        ticker: &str,
+        kind: RefreshKind,
+    ) -> Result<(), BullsEyeError> {
+        let pool = self.pool.clone();
+        let source = self.source.clone();
+        let ticker = ticker.to_string();
+        self.flights
+            .run(company_id, async move {
+                let conn = pool.get().map_err(|_| BullsEyeError::DbPoolError)?;
+                let (mut lock, waited) = CompanyLock::acquire(conn, company_id).await?;
+                let conn = &mut lock.conn;
+                let kind = match waited {
+                    true => services::refresh_needed(company_id, conn),
+                    false => Ok(Some(kind)),
+                };
+                let result = match kind {
+                    Ok(Some(kind)) => {
+                        services::refresh_company(source.as_ref(), company_id, &ticker, kind, conn)
+                            .await
+                    }
+                    Ok(None) => Ok(()),
+                    Err(e) => Err(e),
+                };
+                drop(lock);
+                let event = match &result {
+                    Ok(()) => RefreshEvent::Completed,
+                    Err(e) => RefreshEvent::Failed {
+                        error: e.to_string(),
+                    },
+                };
+                events::publish(company_id, &ticker, event);
+                result
+            })
+            .await
+    }
+
+    /// wakes an idle worker
+    pub fn wake(&self) {
+        self.notify.notify_one();
+    }
+
+    /// queues the job unless the company already has one waiting or running
+    pub fn enqueue(
+        &self,
+        company_id: i32,
+        kind: RefreshKind,
+        conn: &mut PgConnection,
+    ) -> Result<bool, BullsEyeError> {
+        let inserted = NewRefreshJob::create_new_entry(company_id, kind.as_str())
+            .insert_if_not_active(conn)?;
+        if inserted {
+            self.wake();
+        }
+        Ok(inserted)
+    }
+
+    /// queues the refresh the company needs, if any.
+    /// companies that just ran out of retries are left alone until the backoff window passes
+    pub fn enqueue_if_needed(
+        &self,
+        company: &Company,
+        conn: &mut PgConnection,
+    ) -> Result<Option<RefreshKind>, BullsEyeError> {
+        let kind = services::refresh_needed(company.id, conn)?;
+        if let Some(kind) = kind {
+            if !RefreshJob::recently_failed(company.id, conn)? {
+                self.enqueue(company.id, kind, conn)?;
+            }
+        }
+        Ok(kind)
+    }
+}
+
+/// session level refresh lock of a company, released when dropped.
+/// a panicking refresh would otherwise put the connection back into the pool still holding it
+struct CompanyLock {
+    conn: PooledConnection<ConnectionManager<PgConnection>>,
+    company_id: i32,
+}
+
+impl CompanyLock {
+    /// waits for another instance's refresh to release the lock, up to LOCK_WAIT_TIMEOUT.
+    /// also tells if it had to wait
+    async fn acquire(
+        mut conn: PooledConnection<ConnectionManager<PgConnection>>,
+        company_id: i32,
+    ) -> Result<(Self, bool), BullsEyeError> {
+        let started = Instant::now();
+        let mut waited = false;
+        while !db::try_lock_company(company_id, &mut conn)? {
+            if started.elapsed() >= LOCK_WAIT_TIMEOUT {
+                return Err(BullsEyeError::RefreshFailedError(format!(
+                    "timed out waiting for the refresh lock of company {}",
+                    company_id
+                )));
+            }
+            waited = true;
+            time::sleep(LOCK_RETRY_INTERVAL).await;
+        }
+        Ok((CompanyLock { conn, company_id }, waited))
+    }
+}
+
+impl Drop for CompanyLock {
+    fn drop(&mut self) {
+        if let Err(e) = db::unlock_company(self.company_id, &mut self.conn) {
+            eprintln!(
+                "failed to release refresh lock of company {}: {}",
+                self.company_id, e
+            );
+        }
+    }
+}
+
+fn env_or<T: std::str::FromStr + PartialOrd + Default>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|val| val.parse::<T>().ok())
+        .filter(|val| *val > T::default())
+        .unwrap_or(default)
+}
+
+/// claims and runs due jobs until the queue is empty, then waits for a wake up or the next poll
+async fn run_worker(scheduler: Scheduler) {
+    loop {
+        match run_next_job(&scheduler).await {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(e) => eprintln!("refresh worker failed: {}", e),
+        }
+        tokio::select! {
+            _ = scheduler.notify.notified() => {}
+            _ = time::sleep(POLL_INTERVAL) => {}
+        }
+    }
+}
+
+/// runs a single job and records the outcome. returns false when nothing was due
+async fn run_next_job(scheduler: &Scheduler) -> Result<bool, BullsEyeError> {
+    let conn = &mut scheduler
+        .pool
+        .get()
+        .map_err(|_| BullsEyeError::DbPoolError)?;
+    let Some(job) = RefreshJob::claim_next(conn)? else {
+        return Ok(false);
+    };
+    match run_job(scheduler, &job, conn).await {
+        Ok(()) => job.mark_succeeded(conn)?,
+        Err(e) => job.mark_failed(&e.to_string(), conn)?,
+    }
+    Ok(true)
+}
+
+async fn run_job(
+    scheduler: &Scheduler,
+    job: &RefreshJob,
+    conn: &mut PgConnection,
+) -> Result<(), BullsEyeError> {
+    let mut kind = job
+        .kind
+        .parse::<RefreshKind>()
+        .map_err(BullsEyeError::InvalidQueryError)?;
+    // a retry may be due long after a search or another job already refreshed the company
+    if job.attempts > 1 {
+        match services::refresh_needed(job.company_id, conn)? {
+            Some(needed) => kind = needed,
+            None => return Ok(()),
+        }
+    }
+    let company = Company::load_by_id(job.company_id, conn)?;
+    scheduler
+        .refresh_now(company.id, &company.ticker, kind)
+        .await
+}
+
+/// walks every company on each tick and queues the stale ones
+async fn run_crawler(scheduler: Scheduler, period: Duration) {
+    let mut interval = time::interval(period);
+    loop {
+        interval.tick().await;
+        if let Err(e) = crawl(&scheduler) {
+            eprintln!("refresh crawl failed: {}", e);
+        }
+    }
+}
+
+fn crawl(scheduler: &Scheduler) -> Result<(), BullsEyeError> {
+    let conn = &mut scheduler
+        .pool
+        .get()
+        .map_err(|_| BullsEyeError::DbPoolError)?;
+    if RefreshJob::requeue_stale(conn)? > 0 {
+        scheduler.wake();
+    }
+    for company in Company::load_all(conn)? {
+        scheduler.enqueue_if_needed(&company, conn)?;
+    }
+    Ok(())
+}
+
+/// recomputes the industry statistics, and the derived net margin factors if enabled, on each tick
+async fn run_industry_stats(
+    pool: Pool<ConnectionManager<PgConnection>>,
+    period: Duration,
+    derive_factors: bool,
+) {
+    let mut interval = time::interval(period);
+    loop {
+        interval.tick().await;
+        if let Err(e) = refresh_industries(&pool, derive_factors) {
+            eprintln!("industry stats refresh failed: {}", e);
+        }
+    }
+}
+
+fn refresh_industries(
+    pool: &Pool<ConnectionManager<PgConnection>>,
+    derive_factors: bool,
+) -> Result<(), BullsEyeError> {
+    let conn = &mut pool.get().map_err(|_| BullsEyeError::DbPoolError)?;
+    services::refresh_industry_stats(conn)?;
+    if derive_factors {
+        services::derive_margin_factors(conn)?;
+    }
+    Ok(())
+}
@@ -68,6 +68,20 @@ diesel::table! {
         free_cash_flow_margin_ttm -> Nullable<Float8>,
         ffo_margin_ttm -> Nullable<Float8>,
         ffo_margin_trend -> Nullable<Text>,
+        piotroski_f_score -> Nullable<Float8>,
+        altman_z_score -> Nullable<Float8>,
+        beneish_m_score -> Nullable<Float8>,
+        return_on_equity_ttm -> Nullable<Float8>,
+        return_on_equity_short_term_trend -> Nullable<Text>,
+        return_on_equity_long_term_trend -> Nullable<Text>,
+        return_on_assets_ttm -> Nullable<Float8>,
+        return_on_assets_short_term_trend -> Nullable<Text>,
+        return_on_assets_long_term_trend -> Nullable<Text>,
+        return_on_invested_capital_ttm -> Nullable<Float8>,
+        return_on_invested_capital_short_term_trend -> Nullable<Text>,
+        return_on_invested_capital_long_term_trend -> Nullable<Text>,
+        asset_turnover_ttm -> Nullable<Float8>,
+        equity_multiplier_ttm -> Nullable<Float8>,
     }
 }
 
@@ -139,6 +153,22 @@ diesel::table! {
         free_cash_flow_margin -> Nullable<Float8>,
         ratio_calculated -> Bool,
         growth_calculated -> Bool,
+        return_on_equity -> Nullable<Float8>,
+        return_on_assets -> Nullable<Float8>,
+        return_on_invested_capital -> Nullable<Float8>,
+        effective_tax_rate -> Nullable<Float8>,
+        asset_turnover -> Nullable<Float8>,
+        equity_multiplier -> Nullable<Float8>,
+    }
+}
+
+diesel::table! {
+    forecast_snapshots (id) {
+        id -> Int4,
+        company_id -> Int4,
+        metrics_snapshot_id -> Int4,
+        taken_at -> Timestamp,
+        forecast -> Jsonb,
     }
 }
 
@@ -156,16 +186,207 @@ diesel::table! {
         price_next_year_revenue_growth -> Nullable<Float8>,
         price_multi_year_revenue_growth -> Nullable<Float8>,
         price_multi_year_gp_growth -> Nullable<Float8>,
+        price_dcf -> Nullable<Float8>,
+        implied_growth -> Nullable<Float8>,
+    }
+}
+
+diesel::table! {
+    industry_margin_factors (id) {
+        id -> Int4,
+        #[max_length = 50]
+        industry -> Varchar,
+        factor -> Float8,
+        #[max_length = 10]
+        source -> Varchar,
+        sample_count -> Nullable<Int4>,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    industry_stats (id) {
+        id -> Int4,
+        #[max_length = 50]
+        industry -> Varchar,
+        #[max_length = 9]
+        exchange -> Varchar,
+        #[max_length = 50]
+        metric -> Varchar,
+        count -> Int8,
+        mean -> Float8,
+        median -> Float8,
+        first_quartile -> Float8,
+        third_quartile -> Float8,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    metrics_snapshots (id) {
+        id -> Int4,
+        company_id -> Int4,
+        #[max_length = 10]
+        reason -> Varchar,
+        taken_at -> Timestamp,
+        metrics -> Jsonb,
+    }
+}
+
+diesel::table! {
+    price_history (id) {
+        id -> Int4,
+        company_id -> Int4,
+        date -> Date,
+        open -> Nullable<Float8>,
+        high -> Nullable<Float8>,
+        low -> Nullable<Float8>,
+        close -> Float8,
+        volume -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    refresh_jobs (id) {
+        id -> Int4,
+        company_id -> Int4,
+        #[max_length = 20]
+        kind -> Varchar,
+        #[max_length = 20]
+        state -> Varchar,
+        attempts -> Int4,
+        max_attempts -> Int4,
+        last_error -> Nullable<Text>,
+        next_run_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    saved_screens (id) {
+        id -> Int4,
+        #[max_length = 50]
+        name -> Varchar,
+        expression -> Text,
+        last_updated -> Date,
+    }
+}
+
+diesel::table! {
+    trend_fits (id) {
+        id -> Int4,
+        company_id -> Int4,
+        net_interest_margin_short_term_slope -> Nullable<Float8>,
+        net_interest_margin_short_term_r_squared -> Nullable<Float8>,
+        net_interest_margin_short_term_acceleration -> Nullable<Text>,
+        cost_of_risk_short_term_slope -> Nullable<Float8>,
+        cost_of_risk_short_term_r_squared -> Nullable<Float8>,
+        cost_of_risk_short_term_acceleration -> Nullable<Text>,
+        gross_margin_short_term_slope -> Nullable<Float8>,
+        gross_margin_short_term_r_squared -> Nullable<Float8>,
+        gross_margin_short_term_acceleration -> Nullable<Text>,
+        sga_short_term_slope -> Nullable<Float8>,
+        sga_short_term_r_squared -> Nullable<Float8>,
+        sga_short_term_acceleration -> Nullable<Text>,
+        rnd_short_term_slope -> Nullable<Float8>,
+        rnd_short_term_r_squared -> Nullable<Float8>,
+        rnd_short_term_acceleration -> Nullable<Text>,
+        operating_margin_short_term_slope -> Nullable<Float8>,
+        operating_margin_short_term_r_squared -> Nullable<Float8>,
+        operating_margin_short_term_acceleration -> Nullable<Text>,
+        return_on_equity_short_term_slope -> Nullable<Float8>,
+        return_on_equity_short_term_r_squared -> Nullable<Float8>,
+        return_on_equity_short_term_acceleration -> Nullable<Text>,
+        return_on_assets_short_term_slope -> Nullable<Float8>,
+        return_on_assets_short_term_r_squared -> Nullable<Float8>,
+        return_on_assets_short_term_acceleration -> Nullable<Text>,
+        return_on_invested_capital_short_term_slope -> Nullable<Float8>,
+        return_on_invested_capital_short_term_r_squared -> Nullable<Float8>,
+        return_on_invested_capital_short_term_acceleration -> Nullable<Text>,
+        net_interest_margin_long_term_slope -> Nullable<Float8>,
+        net_interest_margin_long_term_r_squared -> Nullable<Float8>,
+        net_interest_margin_long_term_acceleration -> Nullable<Text>,
+        cost_of_risk_long_term_slope -> Nullable<Float8>,
+        cost_of_risk_long_term_r_squared -> Nullable<Float8>,
+        cost_of_risk_long_term_acceleration -> Nullable<Text>,
+        gross_margin_long_term_slope -> Nullable<Float8>,
+        gross_margin_long_term_r_squared -> Nullable<Float8>,
+        gross_margin_long_term_acceleration -> Nullable<Text>,
+        sga_long_term_slope -> Nullable<Float8>,
+        sga_long_term_r_squared -> Nullable<Float8>,
+        sga_long_term_acceleration -> Nullable<Text>,
+        rnd_long_term_slope -> Nullable<Float8>,
+        rnd_long_term_r_squared -> Nullable<Float8>,
+        rnd_long_term_acceleration -> Nullable<Text>,
+        operating_margin_long_term_slope -> Nullable<Float8>,
+        operating_margin_long_term_r_squared -> Nullable<Float8>,
+        operating_margin_long_term_acceleration -> Nullable<Text>,
+        shares_change_slope -> Nullable<Float8>,
+        shares_change_r_squared -> Nullable<Float8>,
+        shares_change_acceleration -> Nullable<Text>,
+        retained_earnings_slope -> Nullable<Float8>,
+        retained_earnings_r_squared -> Nullable<Float8>,
+        retained_earnings_acceleration -> Nullable<Text>,
+        net_cash_slope -> Nullable<Float8>,
+        net_cash_r_squared -> Nullable<Float8>,
+        net_cash_acceleration -> Nullable<Text>,
+        operating_cash_flow_margin_slope -> Nullable<Float8>,
+        operating_cash_flow_margin_r_squared -> Nullable<Float8>,
+        operating_cash_flow_margin_acceleration -> Nullable<Text>,
+        ffo_margin_slope -> Nullable<Float8>,
+        ffo_margin_r_squared -> Nullable<Float8>,
+        ffo_margin_acceleration -> Nullable<Text>,
+        return_on_equity_long_term_slope -> Nullable<Float8>,
+        return_on_equity_long_term_r_squared -> Nullable<Float8>,
+        return_on_equity_long_term_acceleration -> Nullable<Text>,
+        return_on_assets_long_term_slope -> Nullable<Float8>,
+        return_on_assets_long_term_r_squared -> Nullable<Float8>,
+        return_on_assets_long_term_acceleration -> Nullable<Text>,
+        return_on_invested_capital_long_term_slope -> Nullable<Float8>,
+        return_on_invested_capital_long_term_r_squared -> Nullable<Float8>,
+        return_on_invested_capital_long_term_acceleration -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    trend_settings (id) {
+        id -> Int4,
+        #[max_length = 50]
+        metric -> Varchar,
+        #[max_length = 5]
+        term -> Varchar,
+        #[max_length = 50]
+        industry -> Varchar,
+        length -> Nullable<Int4>,
+        flat_threshold -> Nullable<Float8>,
+        count_threshold -> Nullable<Int4>,
+        updated_at -> Timestamp,
     }
 }
 
 diesel::joinable!(current_metrics -> companies (company_id));
 diesel::joinable!(earnings_report -> companies (company_id));
+diesel::joinable!(forecast_snapshots -> companies (company_id));
+diesel::joinable!(forecast_snapshots -> metrics_snapshots (metrics_snapshot_id));
 diesel::joinable!(forecasts -> companies (company_id));
+diesel::joinable!(metrics_snapshots -> companies (company_id));
+diesel::joinable!(price_history -> companies (company_id));
+diesel::joinable!(refresh_jobs -> companies (company_id));
+diesel::joinable!(trend_fits -> companies (company_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     companies,
     current_metrics,
     earnings_report,
+    forecast_snapshots,
     forecasts,
+    industry_margin_factors,
+    industry_stats,
+    metrics_snapshots,
+    price_history,
+    refresh_jobs,
+    saved_screens,
+    trend_fits,
+    trend_settings,
 );
@@ -0,0 +1,95 @@
+use crate::models::earnings_model::EarningsReport;
+
+/// divides when the denominator is usable
+fn ratio(value: f64, total: f64) -> Option<f64> {
+    if total == 0. {
+        return None;
+    }
+    Some(value / total)
+}
+
+fn round_score(score: f64) -> f64 {
+    (score * 100.).round() / 100.
+}
+
+/// piotroski F-score (0 to 9) comparing the latest annual report with the previous one.
+/// 8 or 9 points to a strong balance sheet and improving profitability, 0 to 2 to a weak one
+pub fn calculate_piotroski_f_score(curr: &EarningsReport, prev: &EarningsReport) -> Option<f64> {
+    let curr_roa = ratio(curr.net_income, curr.total_assets)?;
+    let prev_roa = ratio(prev.net_income, prev.total_assets)?;
+    let curr_cash_flow = curr.operating_cash_flow?;
+    // missing debt is reported as none, so it counts as no debt
+    let curr_leverage = ratio(curr.total_debt.unwrap_or(0.), curr.total_assets)?;
+    let prev_leverage = ratio(prev.total_debt.unwrap_or(0.), prev.total_assets)?;
+    let curr_current_ratio = ratio(curr.total_current_assets?, curr.total_current_liabilities?)?;
+    let prev_current_ratio = ratio(prev.total_current_assets?, prev.total_current_liabilities?)?;
+    let curr_turnover = ratio(curr.revenue, curr.total_assets)?;
+    let prev_turnover = ratio(prev.revenue, prev.total_assets)?;
+    let signals = [
+        // profitability
+        curr_roa > 0.,
+        curr_cash_flow > 0.,
+        curr_roa > prev_roa,
+        curr_cash_flow > curr.net_income,
+        // leverage, liquidity and source of funds
+        curr_leverage < prev_leverage,
+        curr_current_ratio > prev_current_ratio,
+        curr.shares_outstanding_diluted <= prev.shares_outstanding_diluted,
+        // operating efficiency
+        curr.gross_margin? > prev.gross_margin?,
+        curr_turnover > prev_turnover,
+    ];
+    Some(signals.iter().filter(|signal| **signal).count() as f64)
+}
+
+/// altman Z-score of the latest annual report, using the market value of equity.
+/// below 1.81 is the distress zone and above 2.99 the safe zone
+pub fn calculate_altman_z_score(curr: &EarningsReport, market_cap: Option<f64>) -> Option<f64> {
+    let working_capital = curr.total_current_assets? - curr.total_current_liabilities?;
+    let score = 1.2 * ratio(working_capital, curr.total_assets)?
+        + 1.4 * ratio(curr.retained_earnings, curr.total_assets)?
+        + 3.3 * ratio(curr.operating_income, curr.total_assets)?
+        + 0.6 * ratio(market_cap?, curr.total_liabilities)?
+        + ratio(curr.revenue, curr.total_assets)?;
+    Some(round_score(score))
+}
+
+/// beneish M-score comparing the latest annual report with the previous one.
+/// above -1.78 hints at earnings manipulation.
+/// property, plant and equipment isn't stored, so the asset quality index only excludes current
+/// assets and the depreciation index is neutral
+pub fn calculate_beneish_m_score(curr: &EarningsReport, prev: &EarningsReport) -> Option<f64> {
+    let days_sales_receivable = ratio(
+        ratio(curr.accounts_receivable?, curr.revenue)?,
+        ratio(prev.accounts_receivable?, prev.revenue)?,
+    )?;
+    let gross_margin = ratio(prev.gross_margin?, curr.gross_margin?)?;
+    let asset_quality = ratio(
+        1. - ratio(curr.total_current_assets?, curr.total_assets)?,
+        1. - ratio(prev.total_current_assets?, prev.total_assets)?,
+    )?;
+    let sales_growth = ratio(curr.revenue, prev.revenue)?;
+    let depreciation = 1.;
+    let sga = ratio(
+        ratio(curr.sga_expenses?, curr.revenue)?,
+        ratio(prev.sga_expenses?, prev.revenue)?,
+    )?;
+    let leverage = ratio(
+        ratio(curr.total_liabilities, curr.total_assets)?,
+        ratio(prev.total_liabilities, prev.total_assets)?,
+    )?;
+    let total_accruals = ratio(
+        curr.net_income - curr.operating_cash_flow?,
+        curr.total_assets,
+    )?;
+    let score = -4.84
+        + 0.92 * days_sales_receivable
+        + 0.528 * gross_margin
+        + 0.404 * asset_quality
+        + 0.892 * sales_growth
+        + 0.115 * depreciation
+        - 0.172 * sga
+        + 4.679 * total_accruals
+        - 0.327 * leverage;
+    Some(round_score(score))
+}
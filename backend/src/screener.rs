@@ -0,0 +1,307 @@
+use crate::errors::BullsEyeError;
+use crate::fields;
+use crate::models::companies_model::Company;
+use crate::models::forecast_models::Forecasts;
+use crate::models::metrics_model::{CurrentMetrics, Trend};
+use crate::models::params_model::ScreenerParams;
+use crate::models::trend_fits_model::TrendFits;
+use crate::schema::{companies, current_metrics, forecasts, trend_fits};
+use diesel::dsl::{InnerJoin, InnerJoinQuerySource, IntoBoxed};
+use diesel::expression::BoxableExpression;
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+use diesel::sql_types::{Double, Nullable, Text, Varchar};
+
+type ScreenerTable = InnerJoin<
+    InnerJoin<InnerJoin<companies::table, current_metrics::table>, forecasts::table>,
+    trend_fits::table,
+>;
+type ScreenerSource = InnerJoinQuerySource<
+    InnerJoinQuerySource<
+        InnerJoinQuerySource<companies::table, current_metrics::table>,
+        forecasts::table,
+    >,
+    trend_fits::table,
+>;
+/// a company with its metrics, forecasts and trend fits
+pub type ScreenerRow = (Company, CurrentMetrics, Forecasts, TrendFits);
+type ScreenerQuery<'a> = IntoBoxed<'a, ScreenerTable, Pg>;
+type NumericColumn = Box<dyn BoxableExpression<ScreenerSource, Pg, SqlType = Nullable<Double>>>;
+type TrendColumn = Box<dyn BoxableExpression<ScreenerSource, Pg, SqlType = Nullable<Text>>>;
+type TextColumn = Box<dyn BoxableExpression<ScreenerSource, Pg, SqlType = Varchar>>;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ScreenerFilter {
+    Numeric {
+        field: String,
+        comparison: Comparison,
+        value: f64,
+    },
+    Trend {
+        field: String,
+        trend: Trend,
+        negate: bool,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SortKey {
+    Numeric(String, bool),
+    Text(String, bool),
+}
+
+macro_rules! column_lookup {
+    (
+        current_metrics: [$($metric_key:literal => $metric:ident $([$flag:ident])?),* $(,)?],
+        forecasts: [$($forecast_key:literal => $forecast:ident),* $(,)?],
+        trend_fits: [$($fit_key:literal => $fit:ident),* $(,)?],
+        trends: [$($trend_key:literal => $trend:ident),* $(,)?] $(,)?
+    ) => {
+        /// looks up a numeric column of the metrics, forecasts or trend fits table by its camelCase name
+        fn numeric_column(name: &str) -> Option<NumericColumn> {
+            match name {
+                $($metric_key => Some(Box::new(current_metrics::$metric)),)*
+                $($forecast_key => Some(Box::new(forecasts::$forecast)),)*
+                $($fit_key => Some(Box::new(trend_fits::$fit)),)*
+                _ => None,
+            }
+        }
+
+        /// looks up a trend column of the metrics table by its camelCase name
+        fn trend_column(name: &str) -> Option<TrendColumn> {
+            match name {
+                $($trend_key => Some(Box::new(current_metrics::$trend)),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+fields::metric_fields!(column_lookup);
+
+/// looks up a text column of the companies table by its camelCase name
+fn text_column(name: &str) -> Option<TextColumn> {
+    match name {
+        "companyName" => Some(Box::new(companies::company_name)),
+        "industry" => Some(Box::new(companies::industry)),
+        "exchange" => Some(Box::new(companies::exchange)),
+        "ticker" => Some(Box::new(companies::ticker)),
+        _ => None,
+    }
+}
+
+/// loads every company with its metrics, forecasts and trend fits
+pub fn load_all(conn: &mut PgConnection) -> Result<Vec<ScreenerRow>, BullsEyeError> {
+    let rows = companies::table
+        .inner_join(current_metrics::table)
+        .inner_join(forecasts::table)
+        .inner_join(trend_fits::table)
+        .order(companies::id.asc())
+        .load::<ScreenerRow>(conn)?;
+    Ok(rows)
+}
+
+/// splits a single filter expression (e.g. "grossMarginTtm>=50") into field, operator and value
+fn split_comparison(expr: &str) -> Option<(&str, Comparison, &str)> {
+    let op_start = expr.find(['<', '>', '=', '!'])?;
+    let (field, rest) = expr.split_at(op_start);
+    let (comparison, op_len) = match rest {
+        r if r.starts_with(">=") => (Comparison::GreaterOrEqual, 2),
+        r if r.starts_with("<=") => (Comparison::LessOrEqual, 2),
+        r if r.starts_with("!=") => (Comparison::NotEqual, 2),
+        r if r.starts_with("==") => (Comparison::Equal, 2),
+        r if r.starts_with('>') => (Comparison::Greater, 1),
+        r if r.starts_with('<') => (Comparison::Less, 1),
+        r if r.starts_with('=') => (Comparison::Equal, 1),
+        _ => return None,
+    };
+    Some((field.trim(), comparison, rest[op_len..].trim()))
+}
+
+/// parses a filter expression into a numeric range filter or a trend equality filter
+pub fn parse_filter(expr: &str) -> Result<ScreenerFilter, BullsEyeError> {
+    let invalid = || BullsEyeError::InvalidQueryError(format!("invalid filter '{}'", expr));
+    let (field, comparison, value) = split_comparison(expr).ok_or_else(invalid)?;
+    if numeric_column(field).is_some() {
+        let value = value.parse::<f64>().map_err(|_| invalid())?;
+        Ok(ScreenerFilter::Numeric {
+            field: field.to_string(),
+            comparison,
+            value,
+        })
+    } else if trend_column(field).is_some() {
+        let negate = match comparison {
+            Comparison::Equal => false,
+            Comparison::NotEqual => true,
+            _ => return Err(invalid()),
+        };
+        let trend = value.parse::<Trend>().map_err(|_| invalid())?;
+        Ok(ScreenerFilter::Trend {
+            field: field.to_string(),
+            trend,
+            negate,
+        })
+    } else {
+        Err(BullsEyeError::InvalidQueryError(format!(
+            "unknown filter field '{}'",
+            field
+        )))
+    }
+}
+
+/// parses a sort key. A leading '-' sorts in descending order
+pub fn parse_sort_key(key: &str) -> Result<SortKey, BullsEyeError> {
+    let (field, descending) = match key.strip_prefix('-') {
+        Some(field) => (field, true),
+        None => (key.strip_prefix('+').unwrap_or(key), false),
+    };
+    if numeric_column(field).is_some() {
+        Ok(SortKey::Numeric(field.to_string(), descending))
+    } else if text_column(field).is_some() {
+        Ok(SortKey::Text(field.to_string(), descending))
+    } else {
+        Err(BullsEyeError::InvalidQueryError(format!(
+            "unknown sort field '{}'",
+            field
+        )))
+    }
+}
+
+/// industry names contain commas (e.g. "Furnishings, Fixtures & Appliances"), so they're
+/// separated by pipes instead
+const INDUSTRY_SEPARATOR: char = '|';
+
+fn split_list(list: &Option<String>, separator: char) -> Vec<&str> {
+    list.as_deref()
+        .map(|val| {
+            val.split(separator)
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// parsed and validated screener request
+pub struct Screen<'a> {
+    filters: Vec<ScreenerFilter>,
+    industries: Vec<&'a str>,
+    exchanges: Vec<&'a str>,
+    sort_keys: Vec<SortKey>,
+    limit: i64,
+    offset: i64,
+}
+
+impl<'a> Screen<'a> {
+    pub fn from_params(params: &'a ScreenerParams) -> Result<Self, BullsEyeError> {
+        let filters = split_list(&params.filter, ',')
+            .into_iter()
+            .map(parse_filter)
+            .collect::<Result<Vec<_>, _>>()?;
+        let sort_keys = split_list(&params.sort, ',')
+            .into_iter()
+            .map(parse_sort_key)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Screen {
+            filters,
+            industries: split_list(&params.industry, INDUSTRY_SEPARATOR),
+            exchanges: split_list(&params.exchange, ','),
+            sort_keys,
+            limit: params.limit(),
+            offset: params.offset(),
+        })
+    }
+
+    /// builds the joined query with every filter pushed down to the database
+    fn filtered_query(&self) -> ScreenerQuery<'a> {
+        let mut query = companies::table
+            .inner_join(current_metrics::table)
+            .inner_join(forecasts::table)
+            .inner_join(trend_fits::table)
+            .into_boxed();
+        if !self.industries.is_empty() {
+            query = query.filter(companies::industry.eq_any(self.industries.clone()));
+        }
+        if !self.exchanges.is_empty() {
+            query = query.filter(companies::exchange.eq_any(self.exchanges.clone()));
+        }
+        for filter in &self.filters {
+            query = match filter {
+                ScreenerFilter::Numeric {
+                    field,
+                    comparison,
+                    value,
+                } => {
+                    let column = numeric_column(field).expect("validated when parsing");
+                    match comparison {
+                        Comparison::Greater => query.filter(column.gt(*value)),
+                        Comparison::GreaterOrEqual => query.filter(column.ge(*value)),
+                        Comparison::Less => query.filter(column.lt(*value)),
+                        Comparison::LessOrEqual => query.filter(column.le(*value)),
+                        Comparison::Equal => query.filter(column.eq(*value)),
+                        Comparison::NotEqual => query.filter(column.ne(*value)),
+                    }
+                }
+                ScreenerFilter::Trend {
+                    field,
+                    trend,
+                    negate,
+                } => {
+                    let column = trend_column(field).expect("validated when parsing");
+                    let trend_str = trend.as_str();
+                    match negate {
+                        true => query.filter(column.ne(trend_str)),
+                        false => query.filter(column.eq(trend_str)),
+                    }
+                }
+            };
+        }
+        query
+    }
+
+    /// counts every row matching the filters, ignoring pagination
+    pub fn count(&self, conn: &mut PgConnection) -> Result<i64, BullsEyeError> {
+        let total = self.filtered_query().count().get_result::<i64>(conn)?;
+        Ok(total)
+    }
+
+    /// loads one page of rows matching the filters in the requested order
+    pub fn load(&self, conn: &mut PgConnection) -> Result<Vec<ScreenerRow>, BullsEyeError> {
+        let mut query = self.filtered_query();
+        for key in &self.sort_keys {
+            query = match key {
+                SortKey::Numeric(field, descending) => {
+                    let column = numeric_column(field).expect("validated when parsing");
+                    match descending {
+                        true => query.then_order_by(column.desc().nulls_last()),
+                        false => query.then_order_by(column.asc().nulls_last()),
+                    }
+                }
+                SortKey::Text(field, descending) => {
+                    let column = text_column(field).expect("validated when parsing");
+                    match descending {
+                        true => query.then_order_by(column.desc()),
+                        false => query.then_order_by(column.asc()),
+                    }
+                }
+            };
+        }
+        // keeps pages stable when sort keys tie
+        let rows = query
+            .then_order_by(companies::id.asc())
+            .limit(self.limit)
+            .offset(self.offset)
+            .load::<ScreenerRow>(conn)?;
+        Ok(rows)
+    }
+}
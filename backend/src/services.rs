@@ -1,59 +1,642 @@
+use crate::backtest;
+use crate::backtest::BacktestReport;
 use crate::db;
 use crate::errors::BullsEyeError;
+use crate::events;
+use crate::events::RefreshEvent;
+use crate::expression::Expression;
 use crate::models::companies_model::{Company, NewCompany};
 use crate::models::earnings_model;
-use crate::models::earnings_model::NewEarningsReport;
-use crate::models::forecast_models::NewForecasts;
+use crate::models::earnings_model::{EarningsDuration, EarningsReport};
+use crate::models::forecast_models::{Forecasts, NewForecasts};
+use crate::models::industry_model;
+use crate::models::industry_model::{IndustryStat, IndustrySummary};
+use crate::models::jobs_model::{JobState, RefreshJob};
+use crate::models::margin_factors_model::{EffectiveMarginFactor, MarginFactor, NewMarginFactor};
 use crate::models::metrics_model::{CurrentMetrics, NewCurrentMetrics};
+use crate::models::params_model::{
+    IndustryParams, JobParams, MarginFactorParams, MonteCarloParams, PriceParams,
+    SavedScreenParams, ScreenerParams, SimulationParams, SnapshotDiffParams, TrendSettingParams,
+};
+use crate::models::prices_model;
+use crate::models::prices_model::{NewPriceBar, PriceBar};
 use crate::models::returning_model::ReturningModel;
+use crate::models::screens_model::{NewSavedScreen, SavedScreen};
+use crate::models::snapshot_model::{NewMetricsSnapshot, Snapshot, SnapshotDiff};
+use crate::models::trend_fits_model::NewTrendFits;
+use crate::models::trend_settings_model::{NewTrendSettingRow, TrendSettingRow};
+use crate::peers;
+use crate::peers::PeerComparison;
 use crate::query;
+use crate::scheduler::RefreshKind;
+use crate::screener;
+use crate::screener::Screen;
+use crate::simulation::{MonteCarloResult, SimulationResult};
+use crate::source::{FinancialDataSource, Profile, RegularData};
+use crate::transfer;
+use crate::transfer::TransferFormat;
+use crate::trend_config::TrendSettings;
 use bullseye_api::model::get_exchange_string;
 use bullseye_api::model::Exchange;
 use diesel::pg::PgConnection;
+use diesel::Connection;
+use std::collections::BTreeMap;
 
 /// runs when handling new ticker data.
 /// creates new company row for all 3 tables.
 pub async fn get_company(
+    source: &dyn FinancialDataSource,
     ticker: &str,
     exchange: &Exchange,
     conn: &mut PgConnection,
 ) -> Result<Company, BullsEyeError> {
+    let mut existing_id = None;
     if let Some(company) =
         Company::load_by_ticker_if_existed(ticker, get_exchange_string(exchange), conn)?
     {
         if !company.ticker_check_needed() {
             return Ok(company);
         }
+        existing_id = Some(company.id);
     }
-    let company_profile = bullseye_api::scrape_profile(ticker, exchange).await?;
-    if let Some(company) = Company::load_if_existed(&company_profile, conn)? {
+    match fetch_company(source, ticker, exchange, conn).await {
+        Ok(company) => Ok(company),
+        Err(e) => {
+            let event = RefreshEvent::Failed {
+                error: e.to_string(),
+            };
+            match existing_id {
+                Some(company_id) => events::publish(company_id, ticker, event),
+                None => events::publish_unresolved(ticker, event),
+            }
+            Err(e)
+        }
+    }
+}
+
+/// fetches the profile and creates the company unless it's already stored under another ticker
+async fn fetch_company(
+    source: &dyn FinancialDataSource,
+    ticker: &str,
+    exchange: &Exchange,
+    conn: &mut PgConnection,
+) -> Result<Company, BullsEyeError> {
+    let company_profile = source.fetch_profile(ticker, exchange).await?;
+    let company = if let Some(company) = Company::load_if_existed(&company_profile, conn)? {
         query::update_company_table(company.id, conn)?; //TODO: reflect ticker change
-        Ok(company)
+        company
     } else {
-        let new_company_entry = NewCompany::create_new_entry(
-            &company_profile.company_name,
-            &company_profile.industry,
-            &company_profile.isin_number,
-            exchange,
-            ticker,
-        );
-        let new_company = new_company_entry.add_new_company(conn)?;
-        let new_metrics_entry = NewCurrentMetrics::create_new_entry(new_company.id, "")?;
-        new_metrics_entry.insert_new_metrics(conn)?;
-        let new_forecast_entry = NewForecasts::create_empty(new_company.id);
-        new_forecast_entry.insert_new_forecast(conn)?;
-        Ok(new_company)
-    }
-}
-
-///returns vector of returning model for the list view
-pub fn get_all_companies(conn: &mut PgConnection) -> Result<Vec<ReturningModel>, BullsEyeError> {
-    let joined_db = db::join_data(conn)?;
-    let returning_vec = joined_db
+        create_company(&company_profile, exchange, ticker, conn)?
+    };
+    events::publish(
+        company.id,
+        &company.ticker,
+        RefreshEvent::ProfileFetched {
+            company_name: company_profile.company_name,
+        },
+    );
+    Ok(company)
+}
+
+/// creates new company row for all 3 tables from the given profile
+pub fn create_company(
+    company_profile: &Profile,
+    exchange: &Exchange,
+    ticker: &str,
+    conn: &mut PgConnection,
+) -> Result<Company, BullsEyeError> {
+    let new_company_entry = NewCompany::create_new_entry(
+        &company_profile.company_name,
+        &company_profile.industry,
+        &company_profile.isin,
+        exchange,
+        ticker,
+    );
+    let new_company = new_company_entry.add_new_company(conn)?;
+    let new_metrics_entry = NewCurrentMetrics::create_new_entry(new_company.id, "")?;
+    new_metrics_entry.insert_new_metrics(conn)?;
+    let new_forecast_entry = NewForecasts::create_empty(new_company.id);
+    new_forecast_entry.insert_new_forecast(conn)?;
+    NewTrendFits::create_empty(new_company.id).insert_new_fits(conn)?;
+    Ok(new_company)
+}
+
+/// exports earnings of one or all companies as CSV or JSON
+pub fn export_earnings(
+    ticker: Option<&str>,
+    format: TransferFormat,
+    conn: &mut PgConnection,
+) -> Result<String, BullsEyeError> {
+    let records = transfer::load_records(ticker, conn)?;
+    transfer::write_records(&records, format)
+}
+
+/// imports earnings exported by export_earnings, calculates growths and ratios of rows that
+/// were exported uncalculated and refreshes metrics of companies with TTM data.
+/// everything is rolled back if any row fails
+pub fn import_earnings(
+    contents: &str,
+    format: TransferFormat,
+    conn: &mut PgConnection,
+) -> Result<usize, BullsEyeError> {
+    let records = transfer::read_records(contents, format)?;
+    let count = records.len();
+    let recomputed = conn.transaction::<_, BullsEyeError, _>(|conn| {
+        let touched = transfer::import_records(records, conn)?;
+        if !touched.is_empty() {
+            db::update_growths_batch(conn)?;
+            db::update_ratios_batch(conn)?;
+        }
+        let mut recomputed = Vec::new();
+        for (comp_id, has_ttm) in touched {
+            if has_ttm {
+                recompute_metrics_annual(comp_id, conn)?;
+                recomputed.push(comp_id);
+            }
+        }
+        Ok(recomputed)
+    })?;
+    // clients only hear about metrics that were committed
+    for comp_id in recomputed {
+        publish_metrics_recomputed(comp_id, conn)?;
+    }
+    Ok(count)
+}
+
+/// imports daily prices of an existing company. returns the number of stored days.
+/// the last bar wins when a day appears more than once
+pub fn import_prices(
+    ticker: &str,
+    contents: &str,
+    format: TransferFormat,
+    conn: &mut PgConnection,
+) -> Result<usize, BullsEyeError> {
+    let exchange = db::lookup_exchange(ticker);
+    let company = get_existing_company(ticker, &exchange, conn)?;
+    let mut bars: Vec<NewPriceBar> = transfer::read_price_bars(contents, format)?
         .into_iter()
-        .map(|(company, metrics, forecast)| ReturningModel::new(company, metrics, forecast))
+        // postgres refuses to upsert the same row twice in one statement
+        .map(|bar| (bar.date(), bar))
+        .collect::<BTreeMap<_, _>>()
+        .into_values()
         .collect();
-    Ok(returning_vec)
+    bars.iter_mut()
+        .for_each(|bar| bar.assign_company(company.id));
+    Ok(conn.transaction(|conn| prices_model::upsert_price_batch(&bars, conn))?)
+}
+
+/// loads daily prices of the company within the requested range
+pub fn get_price_history(
+    company_id: i32,
+    params: &PriceParams,
+    conn: &mut PgConnection,
+) -> Result<Vec<PriceBar>, BullsEyeError> {
+    if let (Some(from), Some(to)) = (params.from, params.to) {
+        if from > to {
+            return Err(BullsEyeError::InvalidQueryError(format!(
+                "from ({}) is after to ({})",
+                from, to
+            )));
+        }
+    }
+    Ok(PriceBar::load_range(
+        company_id,
+        params.from,
+        params.to,
+        conn,
+    )?)
+}
+
+/// loads the company for the given ticker without scraping
+pub fn get_existing_company(
+    ticker: &str,
+    exchange: &Exchange,
+    conn: &mut PgConnection,
+) -> Result<Company, BullsEyeError> {
+    Company::load_by_ticker_if_existed(ticker, get_exchange_string(exchange), conn)?
+        .ok_or_else(|| BullsEyeError::CompanyNotFoundError(ticker.to_string()))
+}
+
+/// returns stored earnings history for the given company, latest period first
+pub fn get_earnings_history(
+    company_id: i32,
+    duration: EarningsDuration,
+    limit: i64,
+    conn: &mut PgConnection,
+) -> Result<Vec<EarningsReport>, BullsEyeError> {
+    let earnings = match duration {
+        EarningsDuration::Ttm => query::load_multiple_earnings_ttm(company_id, limit, conn)?,
+        EarningsDuration::Annual => query::load_multiple_earnings_annual(company_id, limit, conn)?,
+    };
+    Ok(earnings)
+}
+
+/// ranks the company's metrics against the other companies of its industry
+pub fn compare_with_peers(
+    company: Company,
+    conn: &mut PgConnection,
+) -> Result<PeerComparison, BullsEyeError> {
+    Ok(peers::compare_with_peers(company, conn)?)
+}
+
+/// lists the industries of the stored companies
+pub fn list_industries(conn: &mut PgConnection) -> Result<Vec<IndustrySummary>, BullsEyeError> {
+    Ok(IndustryStat::load_summaries(conn)?)
+}
+
+/// loads the metric distributions of the industry or fails with not found
+pub fn get_industry_stats(
+    industry: &str,
+    params: &IndustryParams,
+    conn: &mut PgConnection,
+) -> Result<Vec<IndustryStat>, BullsEyeError> {
+    let exchange = params
+        .exchange
+        .as_deref()
+        .unwrap_or(industry_model::ALL_EXCHANGES);
+    let stats = IndustryStat::load_by_industry(industry, exchange, conn)?;
+    if stats.is_empty() {
+        return Err(BullsEyeError::IndustryNotFoundError(industry.to_string()));
+    }
+    Ok(stats)
+}
+
+/// recomputes the statistics of every industry, returning the number of rows stored
+pub fn refresh_industry_stats(conn: &mut PgConnection) -> Result<usize, BullsEyeError> {
+    Ok(IndustryStat::refresh_all(conn)?)
+}
+
+/// lists the stored net margin factors. industries without one use the default
+pub fn list_margin_factors(conn: &mut PgConnection) -> Result<Vec<MarginFactor>, BullsEyeError> {
+    Ok(MarginFactor::load_all(conn)?)
+}
+
+/// returns the net margin factor used for the industry
+pub fn get_margin_factor(
+    industry: &str,
+    conn: &mut PgConnection,
+) -> Result<EffectiveMarginFactor, BullsEyeError> {
+    Ok(MarginFactor::effective(industry, conn)?)
+}
+
+/// stores a manual net margin factor, which deriving never overwrites
+pub fn set_margin_factor(
+    industry: &str,
+    params: &MarginFactorParams,
+    conn: &mut PgConnection,
+) -> Result<MarginFactor, BullsEyeError> {
+    params
+        .validate()
+        .map_err(BullsEyeError::InvalidQueryError)?;
+    Ok(NewMarginFactor::create_manual_entry(industry, params.factor).upsert(conn)?)
+}
+
+/// deletes the stored factor so the industry uses the default again
+pub fn delete_margin_factor(industry: &str, conn: &mut PgConnection) -> Result<(), BullsEyeError> {
+    match MarginFactor::delete_by_industry(industry, conn)? {
+        true => Ok(()),
+        false => Err(BullsEyeError::MarginFactorNotFoundError(
+            industry.to_string(),
+        )),
+    }
+}
+
+/// derives the net margin factors from the stored earnings, returning the number of industries
+pub fn derive_margin_factors(conn: &mut PgConnection) -> Result<usize, BullsEyeError> {
+    Ok(MarginFactor::derive_all(conn)?)
+}
+
+pub fn list_trend_settings(conn: &mut PgConnection) -> Result<Vec<TrendSettingRow>, BullsEyeError> {
+    Ok(TrendSettingRow::load_all(conn)?)
+}
+
+/// stores the override of the metric's trend, replacing the previous one of the same industry.
+/// stored trends keep their old values until recompute_all_trends runs
+pub fn set_trend_setting(
+    params: &TrendSettingParams,
+    conn: &mut PgConnection,
+) -> Result<TrendSettingRow, BullsEyeError> {
+    params
+        .validate()
+        .map_err(BullsEyeError::InvalidQueryError)?;
+    let term = params.term().map_err(BullsEyeError::InvalidQueryError)?;
+    let changes = params.as_override();
+    // checked against the settings it ends up in, without the stored override it replaces
+    let rows: Vec<TrendSettingRow> = TrendSettingRow::load_for_industry(params.industry(), conn)?
+        .into_iter()
+        .filter(|row| {
+            row.metric != params.metric
+                || row.term != term.as_str()
+                || row.industry != params.industry()
+        })
+        .collect();
+    TrendSettings::with_rows(params.industry(), &rows)
+        .check(term, &params.metric, &changes)
+        .map_err(BullsEyeError::InvalidQueryError)?;
+    let new_setting = NewTrendSettingRow::create_new_entry(
+        &params.metric,
+        term.as_str(),
+        params.industry(),
+        &changes,
+    )
+    .map_err(|e| BullsEyeError::InvalidQueryError(e.to_string()))?;
+    Ok(new_setting.upsert(conn)?)
+}
+
+/// deletes the override so the file or built-in setting applies again
+pub fn delete_trend_setting(setting_id: i32, conn: &mut PgConnection) -> Result<(), BullsEyeError> {
+    match TrendSettingRow::delete_by_id(setting_id, conn)? {
+        true => Ok(()),
+        false => Err(BullsEyeError::TrendSettingNotFoundError(setting_id)),
+    }
+}
+
+/// recalculates the short and long term trends of every company with the current settings,
+/// returning the number of companies
+pub fn recompute_all_trends(conn: &mut PgConnection) -> Result<usize, BullsEyeError> {
+    let all_companies = Company::load_all(conn)?;
+    for company in &all_companies {
+        db::update_short_term_trends(company.id, conn)?;
+        db::update_long_term_trends(company.id, conn)?;
+        take_snapshot(company.id, "trends", conn)?;
+    }
+    Ok(all_companies.len())
+}
+
+///returns vector of returning model for the list view along with the total number of matches
+pub fn get_all_companies(
+    params: &ScreenerParams,
+    conn: &mut PgConnection,
+) -> Result<(Vec<ReturningModel>, i64), BullsEyeError> {
+    let screen = Screen::from_params(params)?;
+    let total = screen.count(conn)?;
+    let returning_vec = screen
+        .load(conn)?
+        .into_iter()
+        .map(|(company, metrics, forecast, fits)| {
+            ReturningModel::new(company, metrics, forecast, fits)
+        })
+        .collect();
+    Ok((returning_vec, total))
+}
+
+/// returns every company satisfying the screening expression
+pub fn run_expression(
+    source: &str,
+    conn: &mut PgConnection,
+) -> Result<Vec<ReturningModel>, BullsEyeError> {
+    let expression = Expression::parse(source)?;
+    let mut matched = Vec::new();
+    for (company, metrics, forecast, fits) in screener::load_all(conn)? {
+        let model = ReturningModel::new(company, metrics, forecast, fits);
+        if expression.matches(&model)? {
+            matched.push(model);
+        }
+    }
+    Ok(matched)
+}
+
+/// loads the saved screen or fails with not found
+pub fn get_screen(screen_id: i32, conn: &mut PgConnection) -> Result<SavedScreen, BullsEyeError> {
+    SavedScreen::load_by_id_if_existed(screen_id, conn)?
+        .ok_or(BullsEyeError::ScreenNotFoundError(screen_id))
+}
+
+/// validates the expression and stores it as a new named screen
+pub fn create_screen(
+    params: &SavedScreenParams,
+    conn: &mut PgConnection,
+) -> Result<SavedScreen, BullsEyeError> {
+    params
+        .validate()
+        .map_err(BullsEyeError::InvalidQueryError)?;
+    Expression::parse(&params.expression)?;
+    let new_screen = NewSavedScreen::create_new_entry(&params.name, &params.expression);
+    Ok(new_screen.insert_new_screen(conn)?)
+}
+
+/// validates the expression and overwrites the saved screen
+pub fn update_screen(
+    screen_id: i32,
+    params: &SavedScreenParams,
+    conn: &mut PgConnection,
+) -> Result<SavedScreen, BullsEyeError> {
+    params
+        .validate()
+        .map_err(BullsEyeError::InvalidQueryError)?;
+    Expression::parse(&params.expression)?;
+    let new_screen = NewSavedScreen::create_new_entry(&params.name, &params.expression);
+    new_screen
+        .update_screen(screen_id, conn)?
+        .ok_or(BullsEyeError::ScreenNotFoundError(screen_id))
+}
+
+/// deletes the saved screen or fails with not found
+pub fn delete_screen(screen_id: i32, conn: &mut PgConnection) -> Result<(), BullsEyeError> {
+    match SavedScreen::delete_by_id(screen_id, conn)? {
+        true => Ok(()),
+        false => Err(BullsEyeError::ScreenNotFoundError(screen_id)),
+    }
+}
+
+/// runs the saved screen against every company
+pub fn run_screen(
+    screen_id: i32,
+    conn: &mut PgConnection,
+) -> Result<Vec<ReturningModel>, BullsEyeError> {
+    let screen = get_screen(screen_id, conn)?;
+    run_expression(&screen.expression, conn)
+}
+
+/// lists the latest refresh jobs
+pub fn list_jobs(
+    params: &JobParams,
+    conn: &mut PgConnection,
+) -> Result<Vec<RefreshJob>, BullsEyeError> {
+    let state = params
+        .state
+        .as_deref()
+        .map(str::parse::<JobState>)
+        .transpose()
+        .map_err(BullsEyeError::InvalidQueryError)?;
+    Ok(RefreshJob::load_filtered(
+        state,
+        params.company_id,
+        params.limit(),
+        conn,
+    )?)
+}
+
+/// loads the refresh job or fails with not found
+pub fn get_job(job_id: i32, conn: &mut PgConnection) -> Result<RefreshJob, BullsEyeError> {
+    RefreshJob::load_by_id_if_existed(job_id, conn)?.ok_or(BullsEyeError::JobNotFoundError(job_id))
+}
+
+/// queues a finished or failed job again. active jobs can't be requeued
+pub fn requeue_job(job_id: i32, conn: &mut PgConnection) -> Result<RefreshJob, BullsEyeError> {
+    match RefreshJob::requeue(job_id, conn)? {
+        Some(job) => Ok(job),
+        None => {
+            get_job(job_id, conn)?;
+            Err(BullsEyeError::JobActiveError(job_id))
+        }
+    }
+}
+
+/// compares replayed price targets with the prices stored `horizon_months` after each period
+pub fn run_backtest(
+    horizon_months: u32,
+    industry: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<BacktestReport, BullsEyeError> {
+    Ok(backtest::run_backtest(horizon_months, industry, conn)?)
+}
+
+/// simulates price targets with hypothetical margin, growth and dilution
+pub fn simulate(
+    company_id: i32,
+    params: &SimulationParams,
+    conn: &mut PgConnection,
+) -> Result<SimulationResult, BullsEyeError> {
+    params
+        .validate()
+        .map_err(BullsEyeError::InvalidQueryError)?;
+    Ok(db::run_sim(company_id, params, conn)?)
+}
+
+/// distribution of the price target when margin, growth and dilution vary like they did historically
+pub fn simulate_monte_carlo(
+    company_id: i32,
+    params: &MonteCarloParams,
+    conn: &mut PgConnection,
+) -> Result<MonteCarloResult, BullsEyeError> {
+    db::run_monte_carlo(company_id, params.runs(), params.seed(), conn)?.ok_or_else(|| {
+        BullsEyeError::InsufficientDataError(
+            "at least two annual reports and TTM revenue and shares are required".to_string(),
+        )
+    })
+}
+
+/// lists the latest metrics and forecast snapshots of the company, newest first
+pub fn get_snapshot_history(
+    company_id: i32,
+    limit: i64,
+    conn: &mut PgConnection,
+) -> Result<Vec<Snapshot>, BullsEyeError> {
+    Ok(Snapshot::load_history(company_id, None, limit, conn)?)
+}
+
+/// loads the company's snapshot or fails with not found
+fn get_snapshot(
+    company_id: i32,
+    snapshot_id: i32,
+    conn: &mut PgConnection,
+) -> Result<Snapshot, BullsEyeError> {
+    Snapshot::load_by_id_if_existed(company_id, snapshot_id, conn)?
+        .ok_or(BullsEyeError::SnapshotNotFoundError(snapshot_id))
+}
+
+/// compares two snapshots of the company field by field
+pub fn diff_snapshots(
+    company_id: i32,
+    params: &SnapshotDiffParams,
+    conn: &mut PgConnection,
+) -> Result<SnapshotDiff, BullsEyeError> {
+    let to = match params.to {
+        Some(snapshot_id) => get_snapshot(company_id, snapshot_id, conn)?,
+        None => Snapshot::load_history(company_id, None, 1, conn)?
+            .pop()
+            .ok_or(BullsEyeError::InvalidQueryError(
+                "company has no snapshots yet".to_string(),
+            ))?,
+    };
+    let from = match params.from {
+        Some(snapshot_id) => get_snapshot(company_id, snapshot_id, conn)?,
+        None => Snapshot::load_history(company_id, Some(to.id), 1, conn)?
+            .pop()
+            .ok_or(BullsEyeError::InvalidQueryError(format!(
+                "no snapshot before {}",
+                to.id
+            )))?,
+    };
+    Ok(from.diff(&to))
+}
+
+/// decides which refresh the company needs from its forecast staleness.
+/// a full refresh runs after Q4 or when no earnings are stored yet.
+/// a company whose profile wasn't checked for 90 days gets at least a regular one
+pub fn refresh_needed(
+    company_id: i32,
+    conn: &mut PgConnection,
+) -> Result<Option<RefreshKind>, BullsEyeError> {
+    let forecast = Forecasts::load_by_id(company_id, conn)?;
+    if forecast.is_earnings_update_needed() {
+        let latest_earnings = EarningsReport::latest_quarter_data_if_existed(company_id, conn)?;
+        let all_earnings = match latest_earnings {
+            Some(earnings) => earnings.quarter_str == 3,
+            None => true,
+        };
+        if all_earnings {
+            Ok(Some(RefreshKind::EarningsAll))
+        } else {
+            Ok(Some(RefreshKind::EarningsTtm))
+        }
+    } else if forecast.is_regular_update_needed()
+        || Company::load_by_id(company_id, conn)?.ticker_check_needed()
+    {
+        Ok(Some(RefreshKind::Regular))
+    } else {
+        Ok(None)
+    }
+}
+
+/// fetches the data for the refresh and recalculates metrics
+pub async fn refresh_company(
+    source: &dyn FinancialDataSource,
+    company_id: i32,
+    ticker: &str,
+    kind: RefreshKind,
+    conn: &mut PgConnection,
+) -> Result<(), BullsEyeError> {
+    let exchange = db::lookup_exchange(ticker);
+    if Company::load_by_id(company_id, conn)?.ticker_check_needed() {
+        recheck_profile(source, company_id, ticker, &exchange, conn).await?;
+    }
+    match kind {
+        RefreshKind::EarningsAll => {
+            update_earnings_all(source, company_id, ticker, &exchange, conn).await?;
+            update_metrics_annual(company_id, conn)?;
+        }
+        RefreshKind::EarningsTtm => {
+            update_earnings_ttm(source, company_id, ticker, &exchange, conn).await?;
+            update_metrics_ttm(company_id, conn)?;
+        }
+        RefreshKind::Regular => {
+            update_regular(source, company_id, ticker, &exchange, conn).await?;
+            update_metrics_annual(company_id, conn)?;
+        }
+    }
+    Ok(())
+}
+
+/// fetches the profile again so the ticker is confirmed to still belong to the company
+async fn recheck_profile(
+    source: &dyn FinancialDataSource,
+    company_id: i32,
+    ticker: &str,
+    exchange: &Exchange,
+    conn: &mut PgConnection,
+) -> Result<(), BullsEyeError> {
+    let company_profile = source.fetch_profile(ticker, exchange).await?;
+    query::update_company_table(company_id, conn)?; //TODO: reflect ticker change
+    events::publish(
+        company_id,
+        ticker,
+        RefreshEvent::ProfileFetched {
+            company_name: company_profile.company_name,
+        },
+    );
+    Ok(())
 }
 
 /// runs after Q4 Earnings or for the initial update.
@@ -62,26 +645,30 @@ pub fn get_all_companies(conn: &mut PgConnection) -> Result<Vec<ReturningModel>,
 ///     filling missing fields
 ///     updating estimates and current stock price
 pub async fn update_earnings_all(
+    source: &dyn FinancialDataSource,
     company_id: i32,
     ticker: &str,
     exchange: &Exchange,
     conn: &mut PgConnection,
 ) -> Result<(), BullsEyeError> {
-    let (earnings_enum_ttm, earnings_enum_annual, currency, earnings_date, price, next_yr_rev) =
-        bullseye_api::scrape_all(ticker, exchange).await?;
-    let ttm_entries = NewEarningsReport::create_new_entry(company_id, &currency, earnings_enum_ttm);
-    let annual_entries =
-        NewEarningsReport::create_new_entry(company_id, &currency, earnings_enum_annual);
-    let is_ttm_entries_existed = earnings_model::insert_earnings_report_batch(ttm_entries, conn)?;
-    let is_annual_entries_existed =
-        earnings_model::insert_earnings_report_batch(annual_entries, conn)?;
-    if is_ttm_entries_existed || is_annual_entries_existed {
+    let earnings = source
+        .fetch_all_earnings(company_id, ticker, exchange)
+        .await?;
+    let ttm_rows = earnings_model::insert_earnings_report_batch(earnings.ttm, conn)?;
+    let annual_rows = earnings_model::insert_earnings_report_batch(earnings.annual, conn)?;
+    if ttm_rows > 0 || annual_rows > 0 {
         db::update_growths_batch(conn)?;
         db::update_ratios_batch(conn)?;
     }
-    db::update_earnings_date(company_id, earnings_date, conn)?;
-    db::update_estimate(company_id, next_yr_rev, conn)?;
-    db::update_price(company_id, price, conn)?;
+    events::publish(
+        company_id,
+        ticker,
+        RefreshEvent::EarningsInserted {
+            ttm_rows,
+            annual_rows,
+        },
+    );
+    store_regular_data(company_id, earnings.regular, conn)?;
     Ok(())
 }
 
@@ -91,37 +678,55 @@ pub async fn update_earnings_all(
 ///     filling missing fields
 ///     updating estimates and current stock price
 pub async fn update_earnings_ttm(
+    source: &dyn FinancialDataSource,
     company_id: i32,
     ticker: &str,
     exchange: &Exchange,
     conn: &mut PgConnection,
 ) -> Result<(), BullsEyeError> {
-    let (earnings_enum_ttm, currency, earnings_date, price, next_yr_rev) =
-        bullseye_api::scrape_quarter_update(ticker, exchange).await?;
-    let ttm_entries = NewEarningsReport::create_new_entry(company_id, &currency, earnings_enum_ttm);
-    let is_entries_existed = earnings_model::insert_earnings_report_batch(ttm_entries, conn)?;
-    if is_entries_existed {
+    let earnings = source
+        .fetch_quarter_update(company_id, ticker, exchange)
+        .await?;
+    let ttm_rows = earnings_model::insert_earnings_report_batch(earnings.ttm, conn)?;
+    if ttm_rows > 0 {
         db::update_growths_batch(conn)?;
         db::update_ratios_batch(conn)?;
     }
-    db::update_earnings_date(company_id, earnings_date, conn)?;
-    db::update_estimate(company_id, next_yr_rev, conn)?;
-    db::update_price(company_id, price, conn)?;
+    events::publish(
+        company_id,
+        ticker,
+        RefreshEvent::EarningsInserted {
+            ttm_rows,
+            annual_rows: 0,
+        },
+    );
+    store_regular_data(company_id, earnings.regular, conn)?;
     Ok(())
 }
 
 /// updates earnings date and current stock price
 pub async fn update_regular(
+    source: &dyn FinancialDataSource,
     company_id: i32,
     ticker: &str,
     exchange: &Exchange,
     conn: &mut PgConnection,
 ) -> Result<(), BullsEyeError> {
-    let (earnings_date, price, next_yr_rev) =
-        bullseye_api::scrape_regular_update(ticker, exchange).await?;
-    db::update_earnings_date(company_id, earnings_date, conn)?;
-    db::update_estimate(company_id, next_yr_rev, conn)?;
-    db::update_price(company_id, price, conn)?;
+    let regular = source.fetch_regular_update(ticker, exchange).await?;
+    store_regular_data(company_id, regular, conn)?;
+    events::publish(company_id, ticker, RefreshEvent::RegularDataUpdated);
+    Ok(())
+}
+
+/// stores earnings date, next year estimate and current stock price
+fn store_regular_data(
+    company_id: i32,
+    regular: RegularData,
+    conn: &mut PgConnection,
+) -> Result<(), BullsEyeError> {
+    db::update_earnings_date(company_id, regular.earnings_date, conn)?;
+    db::update_estimate(company_id, regular.revenue_next_year, conn)?;
+    db::update_price(company_id, regular.price, conn)?;
     Ok(())
 }
 
@@ -134,6 +739,9 @@ pub fn update_metrics_ttm(
     let latest_metrics = db::update_short_term_trends(comp_id, conn)?;
     db::update_price_target(comp_id, conn)?;
     db::update_guidance(comp_id, conn)?;
+    db::update_dcf(comp_id, conn)?;
+    take_snapshot(comp_id, "ttm", conn)?;
+    publish_metrics_recomputed(comp_id, conn)?;
     Ok(latest_metrics)
 }
 
@@ -141,12 +749,44 @@ pub fn update_metrics_ttm(
 pub fn update_metrics_annual(
     comp_id: i32,
     conn: &mut PgConnection,
+) -> Result<CurrentMetrics, BullsEyeError> {
+    let latest_metrics = recompute_metrics_annual(comp_id, conn)?;
+    publish_metrics_recomputed(comp_id, conn)?;
+    Ok(latest_metrics)
+}
+
+/// update_metrics_annual without the event, for callers inside a transaction that publish once
+/// it commits
+pub fn recompute_metrics_annual(
+    comp_id: i32,
+    conn: &mut PgConnection,
 ) -> Result<CurrentMetrics, BullsEyeError> {
     db::copy_latest_data(comp_id, conn)?;
     db::update_short_term_trends(comp_id, conn)?;
     db::update_multi_yr_growth(comp_id, conn)?;
+    db::update_quality_scores(comp_id, conn)?;
     let latest_metrics = db::update_long_term_trends(comp_id, conn)?;
     db::update_price_target(comp_id, conn)?;
     db::update_guidance(comp_id, conn)?;
+    db::update_dcf(comp_id, conn)?;
+    take_snapshot(comp_id, "annual", conn)?;
     Ok(latest_metrics)
 }
+
+pub fn publish_metrics_recomputed(
+    comp_id: i32,
+    conn: &mut PgConnection,
+) -> Result<(), BullsEyeError> {
+    let company = Company::load_by_id(comp_id, conn)?;
+    events::publish(comp_id, &company.ticker, RefreshEvent::MetricsRecomputed);
+    Ok(())
+}
+
+/// records the current metrics and forecast unless nothing changed since the last snapshot
+fn take_snapshot(comp_id: i32, reason: &str, conn: &mut PgConnection) -> Result<(), BullsEyeError> {
+    let metrics = serde_json::to_value(CurrentMetrics::load_by_id(comp_id, conn)?)?;
+    let forecast = serde_json::to_value(Forecasts::load_by_id(comp_id, conn)?)?;
+    NewMetricsSnapshot::create_new_entry(comp_id, reason, metrics)
+        .insert_with_forecast(forecast, conn)?;
+    Ok(())
+}
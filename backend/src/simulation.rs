@@ -0,0 +1,232 @@
+use crate::calculate;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+/// net margin offsets of the sensitivity grid, in percentage points
+const MARGIN_OFFSETS: [f64; 5] = [-4., -2., 0., 2., 4.];
+/// revenue growth offsets of the sensitivity grid, in percentage points
+const GROWTH_OFFSETS: [f64; 5] = [-10., -5., 0., 5., 10.];
+/// a distribution needs at least two historical values to have a spread
+const MIN_OBSERVATIONS: usize = 2;
+
+/// inputs of one scenario after applying the overrides to the stored metrics.
+/// amounts are in millions, margins and growth rates in percentage
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct Scenario {
+    pub revenue: Option<f64>,
+    pub net_margin: Option<f64>,
+    pub shares_outstanding_diluted: Option<f64>,
+    pub revenue_growth: Option<f64>,
+    pub share_change: Option<f64>,
+    pub revenue_next_year: Option<f64>,
+    pub revenue_growth_next_year: Option<f64>,
+}
+
+/// price targets of a scenario and how they react to margin and growth changes
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationResult {
+    pub scenario: Scenario,
+    pub eps: Option<f64>,
+    pub price_target: Option<f64>,
+    pub eps_next_year: Option<f64>,
+    pub price_target_next_year: Option<f64>,
+    pub sensitivity: SensitivityGrid,
+}
+
+/// price target for every net margin (rows) and revenue growth (columns) combination
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SensitivityGrid {
+    pub net_margins: Vec<f64>,
+    pub revenue_growths: Vec<f64>,
+    pub prices: Vec<Vec<Option<f64>>>,
+}
+
+fn round_price(price: Option<f64>) -> Option<f64> {
+    price.map(|val| (val * 100.).round() / 100.)
+}
+
+impl Scenario {
+    fn eps(&self) -> Option<f64> {
+        let net_income = calculate::calculate_margin_portion(self.revenue, self.net_margin);
+        calculate::calculate_per_share(net_income, self.shares_outstanding_diluted)
+    }
+
+    fn eps_next_year(&self) -> Option<f64> {
+        let net_income =
+            calculate::calculate_margin_portion(self.revenue_next_year, self.net_margin);
+        calculate::calculate_per_share(net_income, self.shares_outstanding_diluted)
+    }
+
+    fn price_target(&self) -> Option<f64> {
+        calculate::calculate_price_target_option(self.eps(), self.revenue_growth, self.share_change)
+    }
+
+    fn price_target_next_year(&self) -> Option<f64> {
+        calculate::calculate_price_target_option(
+            self.eps_next_year(),
+            self.revenue_growth_next_year,
+            self.share_change,
+        )
+    }
+
+    fn sensitivity(&self) -> SensitivityGrid {
+        let (Some(net_margin), Some(revenue_growth)) = (self.net_margin, self.revenue_growth)
+        else {
+            return SensitivityGrid {
+                net_margins: Vec::new(),
+                revenue_growths: Vec::new(),
+                prices: Vec::new(),
+            };
+        };
+        let net_margins: Vec<f64> = MARGIN_OFFSETS
+            .iter()
+            .map(|offset| net_margin + offset)
+            .collect();
+        let revenue_growths: Vec<f64> = GROWTH_OFFSETS
+            .iter()
+            .map(|offset| revenue_growth + offset)
+            .collect();
+        let prices = net_margins
+            .iter()
+            .map(|&margin| {
+                revenue_growths
+                    .iter()
+                    .map(|&growth| {
+                        let scenario = Scenario {
+                            net_margin: Some(margin),
+                            revenue_growth: Some(growth),
+                            ..*self
+                        };
+                        round_price(scenario.price_target())
+                    })
+                    .collect()
+            })
+            .collect();
+        SensitivityGrid {
+            net_margins,
+            revenue_growths,
+            prices,
+        }
+    }
+}
+
+/// runs the scenario through the same per-share and growth factor calculation as the stored targets
+pub fn simulate(scenario: Scenario) -> SimulationResult {
+    SimulationResult {
+        eps: round_price(scenario.eps()),
+        price_target: round_price(scenario.price_target()),
+        eps_next_year: round_price(scenario.eps_next_year()),
+        price_target_next_year: round_price(scenario.price_target_next_year()),
+        sensitivity: scenario.sensitivity(),
+        scenario,
+    }
+}
+
+/// normal distribution fitted to the historical values of one input
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct Distribution {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub observations: usize,
+}
+
+impl Distribution {
+    /// fits mean and sample standard deviation. None with fewer than two values
+    pub fn fit(vals: &[f64]) -> Option<Self> {
+        if vals.len() < MIN_OBSERVATIONS {
+            return None;
+        }
+        let count = vals.len() as f64;
+        let mean = vals.iter().sum::<f64>() / count;
+        let variance = vals.iter().map(|val| (val - mean).powi(2)).sum::<f64>() / (count - 1.);
+        Some(Distribution {
+            mean,
+            std_dev: variance.sqrt(),
+            observations: vals.len(),
+        })
+    }
+
+    /// draws a value with the box-muller transform
+    fn sample(&self, rng: &mut StdRng) -> f64 {
+        // 1 - [0, 1) keeps the logarithm finite
+        let u1: f64 = 1. - rng.random::<f64>();
+        let u2: f64 = rng.random();
+        let standard = (-2. * u1.ln()).sqrt() * (2. * std::f64::consts::PI * u2).cos();
+        self.mean + self.std_dev * standard
+    }
+}
+
+/// latest figures and the distributions the uncertain inputs are drawn from
+pub struct MonteCarloInputs {
+    pub revenue: f64,
+    pub shares_outstanding_diluted: f64,
+    pub net_margin: Distribution,
+    pub revenue_growth: Distribution,
+    pub share_change: Distribution,
+}
+
+/// percentiles of the simulated price target
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonteCarloResult {
+    pub runs: usize,
+    pub seed: u64,
+    pub net_margin: Distribution,
+    pub revenue_growth: Distribution,
+    pub share_change: Distribution,
+    pub mean: Option<f64>,
+    pub p10: Option<f64>,
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+}
+
+/// nearest rank percentile of sorted values
+fn percentile(sorted: &[f64], pct: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (pct / 100. * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// samples margin, growth and dilution independently and runs every draw through the
+/// stored price target formula. the same seed always gives the same result
+pub fn run_monte_carlo(inputs: &MonteCarloInputs, runs: usize, seed: u64) -> MonteCarloResult {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut prices: Vec<f64> = (0..runs)
+        .filter_map(|_| {
+            let scenario = Scenario {
+                revenue: Some(inputs.revenue),
+                net_margin: Some(inputs.net_margin.sample(&mut rng).clamp(-100., 100.)),
+                shares_outstanding_diluted: Some(inputs.shares_outstanding_diluted),
+                revenue_growth: Some(inputs.revenue_growth.sample(&mut rng)),
+                share_change: Some(inputs.share_change.sample(&mut rng)),
+                revenue_next_year: None,
+                revenue_growth_next_year: None,
+            };
+            scenario.price_target()
+        })
+        .filter(|price| price.is_finite())
+        .collect();
+    prices.sort_by(f64::total_cmp);
+    let mean = match prices.len() {
+        0 => None,
+        count => Some(prices.iter().sum::<f64>() / count as f64),
+    };
+    MonteCarloResult {
+        runs,
+        seed,
+        net_margin: inputs.net_margin,
+        revenue_growth: inputs.revenue_growth,
+        share_change: inputs.share_change,
+        mean: round_price(mean),
+        p10: round_price(percentile(&prices, 10.)),
+        p50: round_price(percentile(&prices, 50.)),
+        p90: round_price(percentile(&prices, 90.)),
+    }
+}
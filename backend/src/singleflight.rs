@@ -0,0 +1,69 @@
+use crate::errors::BullsEyeError;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// outcome shared with every waiter. None while the task is running
+type Outcome = Option<Result<(), String>>;
+type Inflight = Arc<Mutex<HashMap<i32, watch::Receiver<Outcome>>>>;
+
+/// runs at most one task per key. callers arriving while a task runs wait for it
+/// and share its outcome instead of starting their own
+#[derive(Clone, Default)]
+pub struct SingleFlight {
+    inflight: Inflight,
+}
+
+/// removes the key even if the task panics, so later callers start a new task
+struct InflightGuard {
+    inflight: Inflight,
+    key: i32,
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.inflight.lock().unwrap().remove(&self.key);
+    }
+}
+
+impl SingleFlight {
+    /// runs the task unless one is already running for the key.
+    /// the task is spawned so a dropped request never cancels it for the others
+    pub async fn run<F>(&self, key: i32, task: F) -> Result<(), BullsEyeError>
+    where
+        F: Future<Output = Result<(), BullsEyeError>> + Send + 'static,
+    {
+        let mut receiver = {
+            let mut inflight = self.inflight.lock().unwrap();
+            match inflight.get(&key) {
+                Some(receiver) => receiver.clone(),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    inflight.insert(key, receiver.clone());
+                    let guard = InflightGuard {
+                        inflight: self.inflight.clone(),
+                        key,
+                    };
+                    tokio::spawn(async move {
+                        let outcome = task.await.map_err(|e| e.to_string());
+                        drop(guard);
+                        let _ = sender.send(Some(outcome));
+                    });
+                    receiver
+                }
+            }
+        };
+        let outcome = receiver
+            .wait_for(Option::is_some)
+            .await
+            .map(|outcome| outcome.clone());
+        match outcome {
+            Ok(Some(Ok(()))) => Ok(()),
+            Ok(Some(Err(message))) => Err(BullsEyeError::RefreshFailedError(message)),
+            _ => Err(BullsEyeError::RefreshFailedError(
+                "refresh was aborted".to_string(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use crate::errors::BullsEyeError;
+use crate::models::earnings_model::NewEarningsReport;
+use bullseye_api::model::Exchange;
+use serde::Deserialize;
+use std::env;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// basic company information used to create a company row
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub company_name: String,
+    pub industry: String,
+    pub isin: String,
+}
+
+/// data refreshed on every regular update
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegularData {
+    pub earnings_date: Option<String>,
+    pub price: Option<f64>,
+    pub revenue_next_year: Option<f64>,
+}
+
+/// earnings entries ready to be inserted along with the latest regular data.
+/// annual is left empty for quarterly updates
+pub struct EarningsData {
+    pub ttm: Vec<NewEarningsReport>,
+    pub annual: Vec<NewEarningsReport>,
+    pub regular: RegularData,
+}
+
+/// provider of company profiles, earnings and prices
+pub trait FinancialDataSource: Send + Sync {
+    fn fetch_profile<'a>(
+        &'a self,
+        ticker: &'a str,
+        exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<Profile, BullsEyeError>>;
+
+    /// fetches every available TTM and annual earnings
+    fn fetch_all_earnings<'a>(
+        &'a self,
+        company_id: i32,
+        ticker: &'a str,
+        exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<EarningsData, BullsEyeError>>;
+
+    /// fetches the latest TTM earnings only
+    fn fetch_quarter_update<'a>(
+        &'a self,
+        company_id: i32,
+        ticker: &'a str,
+        exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<EarningsData, BullsEyeError>>;
+
+    /// fetches earnings date, price and estimates
+    fn fetch_regular_update<'a>(
+        &'a self,
+        ticker: &'a str,
+        exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<RegularData, BullsEyeError>>;
+}
+
+/// data source backed by the bullseye_api scraper. Requires a running chrome driver
+pub struct ScraperSource;
+
+impl FinancialDataSource for ScraperSource {
+    fn fetch_profile<'a>(
+        &'a self,
+        ticker: &'a str,
+        exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<Profile, BullsEyeError>> {
+        Box::pin(async move {
+            let company_profile = bullseye_api::scrape_profile(ticker, exchange).await?;
+            Ok(Profile {
+                company_name: company_profile.company_name,
+                industry: company_profile.industry,
+                isin: company_profile.isin_number,
+            })
+        })
+    }
+
+    fn fetch_all_earnings<'a>(
+        &'a self,
+        company_id: i32,
+        ticker: &'a str,
+        exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<EarningsData, BullsEyeError>> {
+        Box::pin(async move {
+            let (
+                earnings_enum_ttm,
+                earnings_enum_annual,
+                currency,
+                earnings_date,
+                price,
+                next_yr_rev,
+            ) = bullseye_api::scrape_all(ticker, exchange).await?;
+            Ok(EarningsData {
+                ttm: NewEarningsReport::create_new_entry(company_id, &currency, earnings_enum_ttm),
+                annual: NewEarningsReport::create_new_entry(
+                    company_id,
+                    &currency,
+                    earnings_enum_annual,
+                ),
+                regular: RegularData {
+                    earnings_date,
+                    price,
+                    revenue_next_year: next_yr_rev,
+                },
+            })
+        })
+    }
+
+    fn fetch_quarter_update<'a>(
+        &'a self,
+        company_id: i32,
+        ticker: &'a str,
+        exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<EarningsData, BullsEyeError>> {
+        Box::pin(async move {
+            let (earnings_enum_ttm, currency, earnings_date, price, next_yr_rev) =
+                bullseye_api::scrape_quarter_update(ticker, exchange).await?;
+            Ok(EarningsData {
+                ttm: NewEarningsReport::create_new_entry(company_id, &currency, earnings_enum_ttm),
+                annual: Vec::new(),
+                regular: RegularData {
+                    earnings_date,
+                    price,
+                    revenue_next_year: next_yr_rev,
+                },
+            })
+        })
+    }
+
+    fn fetch_regular_update<'a>(
+        &'a self,
+        ticker: &'a str,
+        exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<RegularData, BullsEyeError>> {
+        Box::pin(async move {
+            let (earnings_date, price, next_yr_rev) =
+                bullseye_api::scrape_regular_update(ticker, exchange).await?;
+            Ok(RegularData {
+                earnings_date,
+                price,
+                revenue_next_year: next_yr_rev,
+            })
+        })
+    }
+}
+
+/// contents of a single fixture file
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Fixture {
+    profile: Profile,
+    currency: String,
+    #[serde(flatten)]
+    regular: RegularData,
+    #[serde(default)]
+    ttm: Vec<NewEarningsReport>,
+    #[serde(default)]
+    annual: Vec<NewEarningsReport>,
+}
+
+/// data source reading one JSON file per ticker (e.g. `{dir}/aapl.json`). Runs fully offline
+pub struct FixtureSource {
+    dir: PathBuf,
+}
+
+impl FixtureSource {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FixtureSource { dir: dir.into() }
+    }
+
+    async fn load(&self, ticker: &str) -> Result<Fixture, BullsEyeError> {
+        // the ticker becomes a file name, so anything that could leave the directory is refused
+        let is_file_name = ticker
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+        if ticker.is_empty() || !is_file_name {
+            return Err(BullsEyeError::InvalidQueryError(format!(
+                "invalid ticker '{}'",
+                ticker
+            )));
+        }
+        let path = self.dir.join(format!("{}.json", ticker.to_lowercase()));
+        let contents = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(BullsEyeError::CompanyNotFoundError(ticker.to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+/// assigns company and currency to every fixture entry
+fn assign_company(
+    mut entries: Vec<NewEarningsReport>,
+    company_id: i32,
+    currency: &str,
+) -> Vec<NewEarningsReport> {
+    entries
+        .iter_mut()
+        .for_each(|entry| entry.assign_company(company_id, currency));
+    entries
+}
+
+impl FinancialDataSource for FixtureSource {
+    fn fetch_profile<'a>(
+        &'a self,
+        ticker: &'a str,
+        _exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<Profile, BullsEyeError>> {
+        Box::pin(async move { Ok(self.load(ticker).await?.profile) })
+    }
+
+    fn fetch_all_earnings<'a>(
+        &'a self,
+        company_id: i32,
+        ticker: &'a str,
+        _exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<EarningsData, BullsEyeError>> {
+        Box::pin(async move {
+            let fixture = self.load(ticker).await?;
+            Ok(EarningsData {
+                ttm: assign_company(fixture.ttm, company_id, &fixture.currency),
+                annual: assign_company(fixture.annual, company_id, &fixture.currency),
+                regular: fixture.regular,
+            })
+        })
+    }
+
+    fn fetch_quarter_update<'a>(
+        &'a self,
+        company_id: i32,
+        ticker: &'a str,
+        _exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<EarningsData, BullsEyeError>> {
+        Box::pin(async move {
+            let fixture = self.load(ticker).await?;
+            Ok(EarningsData {
+                ttm: assign_company(fixture.ttm, company_id, &fixture.currency),
+                annual: Vec::new(),
+                regular: fixture.regular,
+            })
+        })
+    }
+
+    fn fetch_regular_update<'a>(
+        &'a self,
+        ticker: &'a str,
+        _exchange: &'a Exchange,
+    ) -> BoxFuture<'a, Result<RegularData, BullsEyeError>> {
+        Box::pin(async move { Ok(self.load(ticker).await?.regular) })
+    }
+}
+
+/// selects the data source from the DATA_SOURCE env variable ("scraper" by default).
+/// "fixture" reads files from FIXTURE_DIR
+pub fn data_source_from_env() -> Result<Arc<dyn FinancialDataSource>, BullsEyeError> {
+    match env::var("DATA_SOURCE").as_deref() {
+        Ok("fixture") => Ok(Arc::new(FixtureSource::new(env::var("FIXTURE_DIR")?))),
+        _ => Ok(Arc::new(ScraperSource)),
+    }
+}
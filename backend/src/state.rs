@@ -0,0 +1,40 @@
+use crate::scheduler::Scheduler;
+use crate::source::FinancialDataSource;
+use crate::stream::PriceHub;
+use axum::extract::FromRef;
+use diesel::pg::PgConnection;
+use diesel::r2d2::{ConnectionManager, Pool};
+use std::sync::Arc;
+
+/// shared state handed to every handler
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: Pool<ConnectionManager<PgConnection>>,
+    pub source: Arc<dyn FinancialDataSource>,
+    pub scheduler: Scheduler,
+    pub prices: PriceHub,
+}
+
+impl FromRef<AppState> for Pool<ConnectionManager<PgConnection>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn FinancialDataSource> {
+    fn from_ref(state: &AppState) -> Self {
+        state.source.clone()
+    }
+}
+
+impl FromRef<AppState> for Scheduler {
+    fn from_ref(state: &AppState) -> Self {
+        state.scheduler.clone()
+    }
+}
+
+impl FromRef<AppState> for PriceHub {
+    fn from_ref(state: &AppState) -> Self {
+        state.prices.clone()
+    }
+}
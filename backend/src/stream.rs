@@ -0,0 +1,330 @@
+use crate::calculate;
+use crate::db;
+use crate::errors::BullsEyeError;
+use crate::feed::{PriceFeed, PriceTick};
+use crate::models::companies_model::Company;
+use crate::models::forecast_models::Forecasts;
+use axum::extract::ws::{Message, WebSocket};
+use bullseye_api::model::get_exchange_string;
+use chrono::{NaiveDateTime, NaiveTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::r2d2::{ConnectionManager, Pool};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, Notify};
+use tokio::time::{self, Duration};
+
+/// updates buffered per socket before a slow client starts missing them
+const UPDATE_BUFFER: usize = 256;
+const MAX_SUBSCRIPTIONS: usize = 50;
+const MAX_TICKER_LENGTH: usize = 6;
+const FEED_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// upside of the current price to each price target, in percentage
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetUpside {
+    pub current_revenue_growth: Option<f64>,
+    pub current_gp_growth: Option<f64>,
+    pub next_year_revenue_growth: Option<f64>,
+    pub multi_year_revenue_growth: Option<f64>,
+    pub multi_year_gp_growth: Option<f64>,
+    pub dcf: Option<f64>,
+}
+
+impl TargetUpside {
+    fn new(price: f64, forecast: &Forecasts) -> Self {
+        let upside =
+            |target: Option<f64>| calculate::calculate_yoy_growth_option(target, Some(price));
+        TargetUpside {
+            current_revenue_growth: upside(forecast.price_current_revenue_growth),
+            current_gp_growth: upside(forecast.price_current_gp_growth),
+            next_year_revenue_growth: upside(forecast.price_next_year_revenue_growth),
+            multi_year_revenue_growth: upside(forecast.price_multi_year_revenue_growth),
+            multi_year_gp_growth: upside(forecast.price_multi_year_gp_growth),
+            dcf: upside(forecast.price_dcf),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceUpdate {
+    pub ticker: String,
+    pub price: f64,
+    pub at: NaiveDateTime,
+    pub upside: TargetUpside,
+}
+
+/// messages sent by clients, e.g. `{"action": "subscribe", "tickers": ["AAPL"]}`
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum ClientMessage {
+    Subscribe { tickers: Vec<String> },
+    Unsubscribe { tickers: Vec<String> },
+}
+
+/// messages sent to clients, tagged by `type`
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ServerMessage {
+    Price(PriceUpdate),
+    Subscribed { tickers: Vec<String> },
+    Unsubscribed { tickers: Vec<String> },
+    Error { message: String },
+}
+
+/// fans price updates out to websocket clients.
+/// the feed is only asked for tickers at least one client subscribed to
+#[derive(Clone)]
+pub struct PriceHub {
+    updates: broadcast::Sender<PriceUpdate>,
+    /// number of subscribed sockets per ticker
+    watched: Arc<Mutex<HashMap<String, usize>>>,
+    notify: Arc<Notify>,
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl PriceHub {
+    /// spawns the task reading the feed
+    pub fn start(pool: Pool<ConnectionManager<PgConnection>>, feed: Arc<dyn PriceFeed>) -> Self {
+        let (updates, _) = broadcast::channel(UPDATE_BUFFER);
+        let hub = PriceHub {
+            updates,
+            watched: Arc::new(Mutex::new(HashMap::new())),
+            notify: Arc::new(Notify::new()),
+            pool,
+        };
+        tokio::spawn(run_feed(hub.clone(), feed));
+        hub
+    }
+
+    fn watched_tickers(&self) -> Vec<String> {
+        self.watched.lock().unwrap().keys().cloned().collect()
+    }
+
+    fn watch(&self, ticker: &str) {
+        *self
+            .watched
+            .lock()
+            .unwrap()
+            .entry(ticker.to_string())
+            .or_default() += 1;
+        self.notify.notify_one();
+    }
+
+    fn unwatch(&self, ticker: &str) {
+        let mut watched = self.watched.lock().unwrap();
+        if let Some(count) = watched.get_mut(ticker) {
+            *count -= 1;
+            if *count == 0 {
+                watched.remove(ticker);
+            }
+        }
+    }
+
+    /// stores the tick as the latest price unless told otherwise and returns the update for clients
+    fn store_tick(
+        &self,
+        tick: PriceTick,
+        persist: bool,
+    ) -> Result<Option<PriceUpdate>, BullsEyeError> {
+        let conn = &mut self.pool.get().map_err(|_| BullsEyeError::DbPoolError)?;
+        let Some(company) = load_company(&tick.ticker, conn)? else {
+            return Ok(None);
+        };
+        if persist {
+            db::update_latest_price(company.id, tick.price, tick.at.date(), conn)?;
+            // the implied growth follows the price
+            db::update_dcf(company.id, conn)?;
+        }
+        let forecast = Forecasts::load_by_id(company.id, conn)?;
+        Ok(Some(PriceUpdate {
+            upside: TargetUpside::new(tick.price, &forecast),
+            ticker: tick.ticker,
+            price: tick.price,
+            at: tick.at,
+        }))
+    }
+
+    /// latest stored price of the company, sent right after subscribing
+    fn stored_update(
+        &self,
+        company: &Company,
+        conn: &mut PgConnection,
+    ) -> Result<Option<PriceUpdate>, BullsEyeError> {
+        let forecast = Forecasts::load_by_id(company.id, conn)?;
+        Ok(forecast.latest_price.map(|price| PriceUpdate {
+            ticker: company.ticker.clone(),
+            price,
+            at: forecast
+                .last_updated
+                .map(|date| date.and_time(NaiveTime::MIN))
+                .unwrap_or_else(|| Utc::now().naive_utc()),
+            upside: TargetUpside::new(price, &forecast),
+        }))
+    }
+
+    /// serves one client until it disconnects
+    pub async fn handle_socket(self, mut socket: WebSocket) {
+        let mut updates = self.updates.subscribe();
+        let mut subscriptions: HashSet<String> = HashSet::new();
+        loop {
+            let replies = tokio::select! {
+                message = socket.recv() => match message {
+                    Some(Ok(Message::Text(text))) => {
+                        self.handle_message(text.as_str(), &mut subscriptions)
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                },
+                update = updates.recv() => match update {
+                    Ok(update) if subscriptions.contains(&update.ticker) => {
+                        vec![ServerMessage::Price(update)]
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+            };
+            if send_messages(&mut socket, replies).await.is_err() {
+                break;
+            }
+        }
+        subscriptions.iter().for_each(|ticker| self.unwatch(ticker));
+    }
+
+    fn handle_message(
+        &self,
+        text: &str,
+        subscriptions: &mut HashSet<String>,
+    ) -> Vec<ServerMessage> {
+        let result = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(ClientMessage::Subscribe { tickers }) => self.subscribe(tickers, subscriptions),
+            Ok(ClientMessage::Unsubscribe { tickers }) => {
+                let tickers = tickers
+                    .into_iter()
+                    .map(|ticker| ticker.to_uppercase())
+                    .filter(|ticker| subscriptions.remove(ticker))
+                    .inspect(|ticker| self.unwatch(ticker))
+                    .collect();
+                Ok(vec![ServerMessage::Unsubscribed { tickers }])
+            }
+            Err(e) => Err(BullsEyeError::InvalidQueryError(e.to_string())),
+        };
+        result.unwrap_or_else(|e| {
+            vec![ServerMessage::Error {
+                message: e.to_string(),
+            }]
+        })
+    }
+
+    /// subscribes to known tickers and replies with their latest stored price
+    fn subscribe(
+        &self,
+        tickers: Vec<String>,
+        subscriptions: &mut HashSet<String>,
+    ) -> Result<Vec<ServerMessage>, BullsEyeError> {
+        let conn = &mut self.pool.get().map_err(|_| BullsEyeError::DbPoolError)?;
+        let mut subscribed = Vec::new();
+        let mut replies = Vec::new();
+        for ticker in tickers.iter().map(|ticker| ticker.to_uppercase()) {
+            if !is_valid_ticker(&ticker) {
+                replies.push(ServerMessage::Error {
+                    message: BullsEyeError::InvalidQueryError(format!(
+                        "invalid ticker '{}'",
+                        ticker
+                    ))
+                    .to_string(),
+                });
+                continue;
+            }
+            if subscriptions.contains(&ticker) {
+                continue;
+            }
+            if subscriptions.len() >= MAX_SUBSCRIPTIONS {
+                replies.push(ServerMessage::Error {
+                    message: format!("at most {} tickers per connection", MAX_SUBSCRIPTIONS),
+                });
+                break;
+            }
+            let Some(company) = load_company(&ticker, conn)? else {
+                replies.push(ServerMessage::Error {
+                    message: BullsEyeError::CompanyNotFoundError(ticker).to_string(),
+                });
+                continue;
+            };
+            self.watch(&ticker);
+            subscriptions.insert(ticker.clone());
+            if let Some(update) = self.stored_update(&company, conn)? {
+                replies.push(ServerMessage::Price(update));
+            }
+            subscribed.push(ticker);
+        }
+        replies.insert(
+            0,
+            ServerMessage::Subscribed {
+                tickers: subscribed,
+            },
+        );
+        Ok(replies)
+    }
+}
+
+/// checked before the exchange lookup, which slices the first byte of the ticker
+fn is_valid_ticker(ticker: &str) -> bool {
+    !ticker.is_empty()
+        && ticker.len() <= MAX_TICKER_LENGTH
+        && ticker.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn load_company(ticker: &str, conn: &mut PgConnection) -> Result<Option<Company>, BullsEyeError> {
+    let exchange = db::lookup_exchange(ticker);
+    Ok(Company::load_by_ticker_if_existed(
+        ticker,
+        get_exchange_string(&exchange),
+        conn,
+    )?)
+}
+
+async fn send_messages(
+    socket: &mut WebSocket,
+    messages: Vec<ServerMessage>,
+) -> Result<(), axum::Error> {
+    for message in messages {
+        let text = serde_json::to_string(&message).map_err(axum::Error::new)?;
+        socket.send(Message::Text(text.into())).await?;
+    }
+    Ok(())
+}
+
+/// asks the feed for the watched tickers and broadcasts every tick, storing those of live feeds.
+/// sleeps while nobody is subscribed
+async fn run_feed(hub: PriceHub, feed: Arc<dyn PriceFeed>) {
+    loop {
+        let tickers = hub.watched_tickers();
+        if tickers.is_empty() {
+            hub.notify.notified().await;
+            continue;
+        }
+        let ticks = match feed.next_ticks(&tickers).await {
+            Ok(ticks) => ticks,
+            Err(e) => {
+                eprintln!("price feed failed: {}", e);
+                time::sleep(FEED_RETRY_INTERVAL).await;
+                continue;
+            }
+        };
+        for tick in ticks {
+            match hub.store_tick(tick, feed.persists()) {
+                Ok(Some(update)) => {
+                    // no receivers just means every client left in the meantime
+                    let _ = hub.updates.send(update);
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("storing price tick failed: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use crate::db;
+use crate::errors::BullsEyeError;
+use crate::models::companies_model::Company;
+use crate::models::earnings_model::NewEarningsReport;
+use crate::models::prices_model::NewPriceBar;
+use crate::schema::{companies, earnings_report};
+use crate::services;
+use crate::source::Profile;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// file format used to move earnings between environments
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl TransferFormat {
+    /// picks the format from a file extension. defaults to JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => TransferFormat::Csv,
+            _ => TransferFormat::Json,
+        }
+    }
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TransferFormat::Json => "application/json",
+            TransferFormat::Csv => "text/csv",
+        }
+    }
+}
+
+/// company columns written in front of every earnings row.
+/// used to find or create the company when importing into another database
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyInfo {
+    pub ticker: String,
+    pub exchange: String,
+    pub company_name: String,
+    pub industry: String,
+    pub isin: String,
+}
+
+impl From<Company> for CompanyInfo {
+    fn from(company: Company) -> Self {
+        CompanyInfo {
+            ticker: company.ticker,
+            exchange: company.exchange,
+            company_name: company.company_name,
+            industry: company.industry,
+            isin: company.isin,
+        }
+    }
+}
+
+/// one exported earnings row
+#[derive(Serialize, Deserialize)]
+pub struct EarningsRecord {
+    #[serde(flatten)]
+    pub company: CompanyInfo,
+    #[serde(flatten)]
+    pub report: NewEarningsReport,
+}
+
+#[derive(Serialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+}
+
+/// loads earnings of the given ticker, or of every company when no ticker is given
+pub fn load_records(
+    ticker: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<Vec<EarningsRecord>, BullsEyeError> {
+    let mut query = earnings_report::table
+        .inner_join(companies::table)
+        .select((Company::as_select(), NewEarningsReport::as_select()))
+        .order_by((
+            companies::ticker,
+            earnings_report::duration,
+            earnings_report::year_str,
+            earnings_report::quarter_str,
+        ))
+        .into_boxed();
+    if let Some(curr_ticker) = ticker {
+        query = query.filter(companies::ticker.eq(curr_ticker.to_uppercase()));
+    }
+    let rows: Vec<(Company, NewEarningsReport)> = query.load(conn)?;
+    Ok(rows
+        .into_iter()
+        .map(|(company, report)| EarningsRecord {
+            company: company.into(),
+            report,
+        })
+        .collect())
+}
+
+/// writes records in the given format. CSV has one header row and one row per earnings
+pub fn write_records(
+    records: &[EarningsRecord],
+    format: TransferFormat,
+) -> Result<String, BullsEyeError> {
+    match format {
+        TransferFormat::Json => Ok(serde_json::to_string_pretty(records)?),
+        TransferFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for record in records {
+                // csv can't flatten, but writes headers for a tuple of structs
+                writer.serialize((&record.company, &record.report))?;
+            }
+            let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+            Ok(String::from_utf8_lossy(&bytes).into_owned())
+        }
+    }
+}
+
+/// parses records written by write_records. CSV columns are matched by header name
+pub fn read_records(
+    contents: &str,
+    format: TransferFormat,
+) -> Result<Vec<EarningsRecord>, BullsEyeError> {
+    match format {
+        TransferFormat::Json => serde_json::from_str(contents).map_err(invalid_file),
+        TransferFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(contents.as_bytes());
+            let headers = reader.headers()?.clone();
+            reader
+                .records()
+                .map(|row| {
+                    let row = row?;
+                    Ok(EarningsRecord {
+                        company: row.deserialize(Some(&headers))?,
+                        report: row.deserialize(Some(&headers))?,
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+/// parses daily prices. CSV needs a header row with date and close, other OHLC columns are optional
+pub fn read_price_bars(
+    contents: &str,
+    format: TransferFormat,
+) -> Result<Vec<NewPriceBar>, BullsEyeError> {
+    match format {
+        TransferFormat::Json => serde_json::from_str(contents).map_err(invalid_file),
+        TransferFormat::Csv => Ok(csv::Reader::from_reader(contents.as_bytes())
+            .deserialize()
+            .collect::<Result<_, _>>()?),
+    }
+}
+
+/// a malformed upload is the client's fault, not a server error
+fn invalid_file(e: serde_json::Error) -> BullsEyeError {
+    BullsEyeError::InvalidQueryError(format!("invalid file: {}", e))
+}
+
+/// loads the company by ticker or ISIN, creating it when missing
+fn find_or_create_company(
+    info: &CompanyInfo,
+    conn: &mut PgConnection,
+) -> Result<Company, BullsEyeError> {
+    if let Some(company) = Company::load_by_ticker_if_existed(&info.ticker, &info.exchange, conn)? {
+        return Ok(company);
+    }
+    let profile = Profile {
+        company_name: info.company_name.clone(),
+        industry: info.industry.clone(),
+        isin: info.isin.clone(),
+    };
+    if let Some(company) = Company::load_if_existed(&profile, conn)? {
+        return Ok(company);
+    }
+    let exchange = db::lookup_exchange(&info.ticker);
+    services::create_company(&profile, &exchange, &info.ticker, conn)
+}
+
+/// inserts or overwrites every record, keeping calculated flags as they are in the file.
+/// returns the companies touched and whether each of them received TTM earnings
+pub fn import_records(
+    records: Vec<EarningsRecord>,
+    conn: &mut PgConnection,
+) -> Result<HashMap<i32, bool>, BullsEyeError> {
+    let mut company_ids: HashMap<(String, String), i32> = HashMap::new();
+    let mut touched: HashMap<i32, bool> = HashMap::new();
+    for EarningsRecord {
+        company,
+        mut report,
+    } in records
+    {
+        let key = (company.ticker.clone(), company.exchange.clone());
+        let comp_id = match company_ids.get(&key) {
+            Some(comp_id) => *comp_id,
+            None => {
+                let comp_id = find_or_create_company(&company, conn)?.id;
+                company_ids.insert(key, comp_id);
+                comp_id
+            }
+        };
+        report.assign_company(comp_id, "");
+        *touched.entry(comp_id).or_default() |= report.is_ttm();
+        report.upsert(conn)?;
+    }
+    Ok(touched)
+}
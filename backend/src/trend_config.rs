@@ -0,0 +1,360 @@
+use crate::errors::BullsEyeError;
+use crate::models::trend_settings_model::{TrendOverride, TrendSettingRow};
+use diesel::pg::PgConnection;
+use diesel::result::Error as DieselError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::OnceLock;
+
+const DEFAULT_CONFIG_PATH: &str = "trends.toml";
+/// industry of the overrides applied to every company
+pub const ALL_INDUSTRIES: &str = "All";
+/// past TTM periods each short-term period is compared with, see calculate_short_term_trend
+pub const SHORT_TERM_AVERAGE_PERIODS: usize = 4;
+const DEFAULT_SHORT_TERM_LENGTH: usize = 4;
+const DEFAULT_COUNT_THRESHOLD: usize = 2;
+const DEFAULT_LONG_TERM_LENGTH: usize = 6;
+/// longest trend that can be configured, which also bounds the earnings rows loaded per company
+const MAX_TREND_LENGTH: usize = 40;
+
+/// flat thresholds of the short-term trends, by earnings_report column
+const DEFAULT_SHORT_TERM: [(&str, f64); 9] = [
+    ("net_interest_margin", 0.5),
+    ("cost_of_risk", 0.5),
+    ("gross_margin", 0.5),
+    ("sga_gp_ratio", 0.01),
+    ("rnd_gp_ratio", 0.01),
+    ("operating_margin", 0.5),
+    ("return_on_equity", 1.),
+    ("return_on_assets", 0.5),
+    ("return_on_invested_capital", 1.),
+];
+/// flat thresholds of the long-term trends, by earnings_report column
+const DEFAULT_LONG_TERM: [(&str, f64); 14] = [
+    ("net_interest_margin", 1.),
+    ("cost_of_risk", 1.),
+    ("gross_margin", 1.),
+    ("sga_gp_ratio", 0.02),
+    ("rnd_gp_ratio", 0.02),
+    ("operating_margin", 1.),
+    ("shares_change_yoy", 2.),
+    ("retained_earnings", 100.),
+    ("net_cash", 100.),
+    ("operating_cash_flow_margin", 1.),
+    ("ffo_margin", 1.),
+    ("return_on_equity", 2.),
+    ("return_on_assets", 1.),
+    ("return_on_invested_capital", 2.),
+];
+
+static FILE_CONFIG: OnceLock<TrendFile> = OnceLock::new();
+
+/// short-term trends compare each of the latest `length` TTM periods with the average of the
+/// four before it and hold when `count_threshold` of them agree.
+/// long-term trends compare the halves of the latest `length` annual reports
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrendSetting {
+    pub length: usize,
+    pub flat_threshold: f64,
+    pub count_threshold: usize,
+}
+
+impl TrendSetting {
+    fn apply(&mut self, changes: &TrendOverride) {
+        if let Some(length) = changes.length {
+            self.length = length;
+        }
+        if let Some(flat_threshold) = changes.flat_threshold {
+            self.flat_threshold = flat_threshold;
+        }
+        if let Some(count_threshold) = changes.count_threshold {
+            self.count_threshold = count_threshold;
+        }
+    }
+
+    /// the short-term count can't be reached when it exceeds the periods compared
+    fn validate(&self) -> Result<(), String> {
+        if self.count_threshold > self.length {
+            return Err(format!(
+                "count threshold {} exceeds the length {}",
+                self.count_threshold, self.length
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// short or long term trend of a metric. stored as lowercase strings
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TrendTerm {
+    Short,
+    Long,
+}
+
+impl TrendTerm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrendTerm::Short => "short",
+            TrendTerm::Long => "long",
+        }
+    }
+}
+
+impl std::str::FromStr for TrendTerm {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "short" => Ok(TrendTerm::Short),
+            "long" => Ok(TrendTerm::Long),
+            _ => Err(format!("unknown trend term '{}'", s)),
+        }
+    }
+}
+
+/// overrides of one set of metrics by earnings_report column
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct TrendSection {
+    short_term: HashMap<String, TrendOverride>,
+    long_term: HashMap<String, TrendOverride>,
+}
+
+impl TrendSection {
+    fn validate(&self) -> Result<(), String> {
+        for (metric, changes) in &self.short_term {
+            validate_override(TrendTerm::Short, metric, changes)?;
+        }
+        for (metric, changes) in &self.long_term {
+            validate_override(TrendTerm::Long, metric, changes)?;
+        }
+        Ok(())
+    }
+}
+
+/// contents of the TOML file. industry sections take precedence over the top-level ones, e.g.
+///     [short_term.gross_margin]
+///     flat_threshold = 1.0
+///
+///     [industries."Banks - Regional".long_term.net_interest_margin]
+///     length = 8
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct TrendFile {
+    short_term: HashMap<String, TrendOverride>,
+    long_term: HashMap<String, TrendOverride>,
+    industries: HashMap<String, TrendSection>,
+}
+
+impl TrendFile {
+    fn validate(&self) -> Result<(), String> {
+        for (metric, changes) in &self.short_term {
+            validate_override(TrendTerm::Short, metric, changes)?;
+        }
+        for (metric, changes) in &self.long_term {
+            validate_override(TrendTerm::Long, metric, changes)?;
+        }
+        for (industry, section) in &self.industries {
+            section
+                .validate()
+                .map_err(|e| format!("industry '{}': {}", industry, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// checks that the metric has a trend of the term and the override is usable
+pub fn validate_override(
+    term: TrendTerm,
+    metric: &str,
+    changes: &TrendOverride,
+) -> Result<(), String> {
+    let known = match term {
+        TrendTerm::Short => DEFAULT_SHORT_TERM.iter().any(|(name, _)| *name == metric),
+        TrendTerm::Long => DEFAULT_LONG_TERM.iter().any(|(name, _)| *name == metric),
+    };
+    if !known {
+        return Err(format!(
+            "unknown {} term trend metric '{}'",
+            term.as_str(),
+            metric
+        ));
+    }
+    if changes
+        .length
+        .is_some_and(|length| !(2..=MAX_TREND_LENGTH).contains(&length))
+    {
+        return Err(format!("length must be between 2 and {}", MAX_TREND_LENGTH));
+    }
+    if changes.flat_threshold.is_some_and(|val| val < 0.) {
+        return Err("flat threshold can't be negative".to_string());
+    }
+    if changes.count_threshold.is_some() && term == TrendTerm::Long {
+        return Err("long term trends have no count threshold".to_string());
+    }
+    if changes.count_threshold.is_some_and(|val| val == 0) {
+        return Err("count threshold must be positive".to_string());
+    }
+    Ok(())
+}
+
+/// reads the TOML file at TREND_CONFIG (trends.toml by default). a missing file keeps the
+/// built-in settings. runs once at startup so a malformed file stops the process right away
+pub fn init() -> Result<(), BullsEyeError> {
+    let path = env::var("TREND_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+    let file = match fs::read_to_string(&path) {
+        Ok(contents) => toml::from_str::<TrendFile>(&contents)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => TrendFile::default(),
+        Err(e) => return Err(e.into()),
+    };
+    file.validate()
+        .and_then(|_| {
+            // each industry section is checked on top of the top-level overrides
+            let industries = file.industries.keys().map(String::as_str);
+            for industry in [ALL_INDUSTRIES].into_iter().chain(industries) {
+                TrendSettings::from_file(&file, industry)
+                    .validate()
+                    .map_err(|e| format!("industry '{}': {}", industry, e))?;
+            }
+            Ok(())
+        })
+        .map_err(|e| BullsEyeError::InvalidConfigError(format!("{}: {}", path, e)))?;
+    // a second call keeps the file read first
+    let _ = FILE_CONFIG.set(file);
+    Ok(())
+}
+
+fn file_config() -> &'static TrendFile {
+    FILE_CONFIG.get_or_init(TrendFile::default)
+}
+
+/// trend settings of every metric for one industry.
+/// built-in defaults are overridden by the file, then by the trend_settings table,
+/// and settings for all industries by the ones for the industry
+pub struct TrendSettings {
+    short_term: HashMap<&'static str, TrendSetting>,
+    long_term: HashMap<&'static str, TrendSetting>,
+}
+
+impl TrendSettings {
+    pub fn load(industry: &str, conn: &mut PgConnection) -> Result<Self, DieselError> {
+        let rows = TrendSettingRow::load_for_industry(industry, conn)?;
+        Ok(Self::with_rows(industry, &rows))
+    }
+
+    /// applies the stored overrides on top of the file. rows for all industries have to come
+    /// first, so the industry's own rows win. rows that fail validation, e.g. after being
+    /// edited into the table by hand, are skipped
+    pub fn with_rows(industry: &str, rows: &[TrendSettingRow]) -> Self {
+        let mut settings = Self::from_file(file_config(), industry);
+        for row in rows {
+            let Ok(term) = row.term.parse::<TrendTerm>() else {
+                continue;
+            };
+            let changes = row.as_override();
+            match settings.check(term, &row.metric, &changes) {
+                Ok(()) => settings.apply(term, &row.metric, &changes),
+                Err(e) => eprintln!("skipping trend setting {}: {}", row.id, e),
+            }
+        }
+        settings
+    }
+
+    /// built-in defaults overridden by the file
+    fn from_file(file: &TrendFile, industry: &str) -> Self {
+        let mut settings = TrendSettings {
+            short_term: DEFAULT_SHORT_TERM
+                .iter()
+                .map(|(metric, flat_threshold)| {
+                    let setting = TrendSetting {
+                        length: DEFAULT_SHORT_TERM_LENGTH,
+                        flat_threshold: *flat_threshold,
+                        count_threshold: DEFAULT_COUNT_THRESHOLD,
+                    };
+                    (*metric, setting)
+                })
+                .collect(),
+            long_term: DEFAULT_LONG_TERM
+                .iter()
+                .map(|(metric, flat_threshold)| {
+                    let setting = TrendSetting {
+                        length: DEFAULT_LONG_TERM_LENGTH,
+                        flat_threshold: *flat_threshold,
+                        count_threshold: DEFAULT_COUNT_THRESHOLD,
+                    };
+                    (*metric, setting)
+                })
+                .collect(),
+        };
+        settings.apply_all(TrendTerm::Short, &file.short_term);
+        settings.apply_all(TrendTerm::Long, &file.long_term);
+        if let Some(section) = file.industries.get(industry) {
+            settings.apply_all(TrendTerm::Short, &section.short_term);
+            settings.apply_all(TrendTerm::Long, &section.long_term);
+        }
+        settings
+    }
+
+    /// checks the override on its own and the setting it results in
+    pub fn check(
+        &self,
+        term: TrendTerm,
+        metric: &str,
+        changes: &TrendOverride,
+    ) -> Result<(), String> {
+        validate_override(term, metric, changes)?;
+        let mut setting = *match term {
+            TrendTerm::Short => self.short_term(metric),
+            TrendTerm::Long => self.long_term(metric),
+        };
+        setting.apply(changes);
+        setting.validate()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (metric, setting) in &self.short_term {
+            setting
+                .validate()
+                .map_err(|e| format!("short term {}: {}", metric, e))?;
+        }
+        Ok(())
+    }
+
+    fn apply_all(&mut self, term: TrendTerm, overrides: &HashMap<String, TrendOverride>) {
+        for (metric, changes) in overrides {
+            self.apply(term, metric, changes);
+        }
+    }
+
+    fn apply(&mut self, term: TrendTerm, metric: &str, changes: &TrendOverride) {
+        let settings = match term {
+            TrendTerm::Short => &mut self.short_term,
+            TrendTerm::Long => &mut self.long_term,
+        };
+        if let Some(setting) = settings.get_mut(metric) {
+            setting.apply(changes);
+        }
+    }
+
+    pub fn short_term(&self, metric: &str) -> &TrendSetting {
+        &self.short_term[metric]
+    }
+
+    pub fn long_term(&self, metric: &str) -> &TrendSetting {
+        &self.long_term[metric]
+    }
+
+    /// TTM periods to load so the longest short-term trend has its trailing averages
+    pub fn short_term_rows(&self) -> i64 {
+        let longest = self.short_term.values().map(|s| s.length).max();
+        (longest.unwrap_or(DEFAULT_SHORT_TERM_LENGTH) + SHORT_TERM_AVERAGE_PERIODS) as i64
+    }
+
+    /// annual reports to load for the longest long-term trend
+    pub fn long_term_rows(&self) -> i64 {
+        let longest = self.long_term.values().map(|s| s.length).max();
+        longest.unwrap_or(DEFAULT_LONG_TERM_LENGTH) as i64
+    }
+}
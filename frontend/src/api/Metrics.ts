@@ -49,14 +49,99 @@ export interface Metrics {
   freeCashFlowMarginTtm: number | undefined;
   ffoMarginTtm: number | undefined;
   ffoMarginTrend: string | undefined;
+  piotroskiFScore: number | undefined;
+  altmanZScore: number | undefined;
+  beneishMScore: number | undefined;
+  returnOnEquityTtm: number | undefined;
+  returnOnEquityShortTermTrend: string | undefined;
+  returnOnEquityLongTermTrend: string | undefined;
+  returnOnAssetsTtm: number | undefined;
+  returnOnAssetsShortTermTrend: string | undefined;
+  returnOnAssetsLongTermTrend: string | undefined;
+  returnOnInvestedCapitalTtm: number | undefined;
+  returnOnInvestedCapitalShortTermTrend: string | undefined;
+  returnOnInvestedCapitalLongTermTrend: string | undefined;
+  assetTurnoverTtm: number | undefined;
+  equityMultiplierTtm: number | undefined;
   nextEarningsDate: Date | undefined;
   latestPrice: number | undefined;
   lastUpdated: Date | undefined;
   revenueNextYear: number;
   revenueGrowthNextYear: number;
+  impliedGrowth: number | undefined;
   priceCurrentRevenueGrowth: number | undefined;
   priceCurrentGpGrowth: number | undefined;
   priceNextYearRevenueGrowth: number | undefined;
   priceMultiYearRevenueGrowth: number | undefined;
   priceMultiYearGpGrowth: number | undefined;
+  priceDcf: number | undefined;
+  netInterestMarginShortTermSlope: number | undefined;
+  netInterestMarginShortTermRSquared: number | undefined;
+  netInterestMarginShortTermAcceleration: string | undefined;
+  costOfRiskShortTermSlope: number | undefined;
+  costOfRiskShortTermRSquared: number | undefined;
+  costOfRiskShortTermAcceleration: string | undefined;
+  grossMarginShortTermSlope: number | undefined;
+  grossMarginShortTermRSquared: number | undefined;
+  grossMarginShortTermAcceleration: string | undefined;
+  sgaShortTermSlope: number | undefined;
+  sgaShortTermRSquared: number | undefined;
+  sgaShortTermAcceleration: string | undefined;
+  rndShortTermSlope: number | undefined;
+  rndShortTermRSquared: number | undefined;
+  rndShortTermAcceleration: string | undefined;
+  operatingMarginShortTermSlope: number | undefined;
+  operatingMarginShortTermRSquared: number | undefined;
+  operatingMarginShortTermAcceleration: string | undefined;
+  returnOnEquityShortTermSlope: number | undefined;
+  returnOnEquityShortTermRSquared: number | undefined;
+  returnOnEquityShortTermAcceleration: string | undefined;
+  returnOnAssetsShortTermSlope: number | undefined;
+  returnOnAssetsShortTermRSquared: number | undefined;
+  returnOnAssetsShortTermAcceleration: string | undefined;
+  returnOnInvestedCapitalShortTermSlope: number | undefined;
+  returnOnInvestedCapitalShortTermRSquared: number | undefined;
+  returnOnInvestedCapitalShortTermAcceleration: string | undefined;
+  netInterestMarginLongTermSlope: number | undefined;
+  netInterestMarginLongTermRSquared: number | undefined;
+  netInterestMarginLongTermAcceleration: string | undefined;
+  costOfRiskLongTermSlope: number | undefined;
+  costOfRiskLongTermRSquared: number | undefined;
+  costOfRiskLongTermAcceleration: string | undefined;
+  grossMarginLongTermSlope: number | undefined;
+  grossMarginLongTermRSquared: number | undefined;
+  grossMarginLongTermAcceleration: string | undefined;
+  sgaLongTermSlope: number | undefined;
+  sgaLongTermRSquared: number | undefined;
+  sgaLongTermAcceleration: string | undefined;
+  rndLongTermSlope: number | undefined;
+  rndLongTermRSquared: number | undefined;
+  rndLongTermAcceleration: string | undefined;
+  operatingMarginLongTermSlope: number | undefined;
+  operatingMarginLongTermRSquared: number | undefined;
+  operatingMarginLongTermAcceleration: string | undefined;
+  sharesChangeSlope: number | undefined;
+  sharesChangeRSquared: number | undefined;
+  sharesChangeAcceleration: string | undefined;
+  retainedEarningsSlope: number | undefined;
+  retainedEarningsRSquared: number | undefined;
+  retainedEarningsAcceleration: string | undefined;
+  netCashSlope: number | undefined;
+  netCashRSquared: number | undefined;
+  netCashAcceleration: string | undefined;
+  operatingCashFlowMarginSlope: number | undefined;
+  operatingCashFlowMarginRSquared: number | undefined;
+  operatingCashFlowMarginAcceleration: string | undefined;
+  ffoMarginSlope: number | undefined;
+  ffoMarginRSquared: number | undefined;
+  ffoMarginAcceleration: string | undefined;
+  returnOnEquityLongTermSlope: number | undefined;
+  returnOnEquityLongTermRSquared: number | undefined;
+  returnOnEquityLongTermAcceleration: string | undefined;
+  returnOnAssetsLongTermSlope: number | undefined;
+  returnOnAssetsLongTermRSquared: number | undefined;
+  returnOnAssetsLongTermAcceleration: string | undefined;
+  returnOnInvestedCapitalLongTermSlope: number | undefined;
+  returnOnInvestedCapitalLongTermRSquared: number | undefined;
+  returnOnInvestedCapitalLongTermAcceleration: string | undefined;
 }
@@ -1,6 +1,8 @@
 import { Metrics } from "../api/Metrics";
 
 const baseUrl = "http://localhost:3000";
+// the screener returns at most 1000 companies per request
+const screenerPageSize = 500;
 
 export async function getMetrics(ticker: string): Promise<Metrics> {
   const url = `${baseUrl}/companies/${ticker.toLowerCase()}`;
@@ -23,9 +25,18 @@ export async function getMetrics(ticker: string): Promise<Metrics> {
 }
 
 export async function getAllMetrics(): Promise<Metrics[]> {
-  const url = `${baseUrl}/screener`;
   try {
-    const metrics = await fetchData<Metrics[]>(url);
+    const metrics: Metrics[] = [];
+    let total = Infinity;
+    while (metrics.length < total) {
+      const url = `${baseUrl}/screener?limit=${screenerPageSize}&offset=${metrics.length}`;
+      const page = await fetchPage<Metrics>(url);
+      if (page.items.length === 0) {
+        break;
+      }
+      metrics.push(...page.items);
+      total = page.total;
+    }
     // For Debug
     // console.log(JSON.stringify(metrics, null, 2));
     return metrics;
@@ -52,6 +63,19 @@ async function fetchData<T>(url: string, options?: RequestInit): Promise<T> {
   }
 }
 
+async function fetchPage<T>(
+  url: string
+): Promise<{ items: T[]; total: number }> {
+  const response = await fetch(url);
+  if (!response.ok) {
+    const errorText = await response.text();
+    throw new Error(`Status: ${response.status}, Message: ${errorText}`);
+  }
+  const items = await parseJSON<T[]>(response);
+  const total = Number(response.headers.get("x-total-count") ?? items.length);
+  return { items, total };
+}
+
 function parseJSON<T>(response: Response): Promise<T> {
   return response.json();
 }
@@ -167,6 +167,28 @@ function TickerMetricsPage(props: MetricsPageProps) {
                     </span>
                   </p>
                 )}
+                {metrics?.priceDcf && (
+                  <p>
+                    Discounted cash flow:{" "}
+                    <span className="text-2xl">
+                      {metrics?.exchange === "US" ? "$" : "¥"}
+                      {metrics?.priceDcf?.toFixed(2) ?? "-"}{" "}
+                    </span>
+                  </p>
+                )}
+                {metrics?.impliedGrowth !== undefined &&
+                  metrics?.impliedGrowth !== null && (
+                    <p>
+                      Growth implied by the current price:{" "}
+                      <span className="text-2xl">
+                        {metrics.impliedGrowth.toFixed(2)}%
+                      </span>{" "}
+                      (multi-year{" "}
+                      {metrics?.revenueGrowthMultiYear?.toFixed(2) ?? "-"}%,
+                      next year{" "}
+                      {metrics?.revenueGrowthNextYear?.toFixed(2) ?? "-"}%)
+                    </p>
+                  )}
                 {/* <p>
                   Simulation (
                   {metrics?.revenueGrowthNextYear?.toFixed(2) ?? "-"}%):{" "}
@@ -206,6 +228,13 @@ function TickerMetricsPage(props: MetricsPageProps) {
                   metrics?.sharesChangeTtm <= 0 && (
                     <li>The company is actively buying back its shares.</li>
                   )}
+                {typeof metrics?.piotroskiFScore === "number" &&
+                  metrics?.piotroskiFScore >= 8 && (
+                    <li>
+                      Fundamentals are improving on most fronts (Piotroski
+                      F-score {metrics.piotroskiFScore}).
+                    </li>
+                  )}
               </ul>
             </div>
             <div className="col-span-2 lg:col-span-1 px-2">
@@ -235,6 +264,27 @@ function TickerMetricsPage(props: MetricsPageProps) {
                   metrics?.sharesChangeTtm >= 3 && (
                     <li>Share is excessively diluted.</li>
                   )}
+                {typeof metrics?.piotroskiFScore === "number" &&
+                  metrics?.piotroskiFScore <= 2 && (
+                    <li>
+                      Fundamentals are deteriorating on most fronts (Piotroski
+                      F-score {metrics.piotroskiFScore}).
+                    </li>
+                  )}
+                {typeof metrics?.altmanZScore === "number" &&
+                  metrics?.altmanZScore < 1.81 && (
+                    <li>
+                      Bankruptcy risk is elevated (Altman Z-score{" "}
+                      {metrics.altmanZScore.toFixed(2)}).
+                    </li>
+                  )}
+                {typeof metrics?.beneishMScore === "number" &&
+                  metrics?.beneishMScore > -1.78 && (
+                    <li>
+                      Earnings may be manipulated (Beneish M-score{" "}
+                      {metrics.beneishMScore.toFixed(2)}).
+                    </li>
+                  )}
               </ul>
             </div>
           </div>
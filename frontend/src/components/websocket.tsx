@@ -1,6 +1,7 @@
 import { useEffect, useRef, useState } from "react";
 
 const RENDERING_INTERVAL = 5000; // 5 seconds
+const TICKER = "AAPL";
 function WebSocketComponent() {
   const priceRef = useRef<string | null>(null);
   const [price, setPrice] = useState<string | null>(null);
@@ -8,9 +9,10 @@ function WebSocketComponent() {
   const intervalRef = useRef<number | null>(null);
 
   useEffect(() => {
-    const socket = new WebSocket("ws://localhost:3000/wstest");
+    const socket = new WebSocket("ws://localhost:3000/ws/prices");
     socket.onopen = () => {
       console.log("Connected");
+      socket.send(JSON.stringify({ action: "subscribe", tickers: [TICKER] }));
       intervalRef.current = setInterval(() => {
         if (priceRef.current) {
           setPrice(priceRef.current);
@@ -25,8 +27,16 @@ function WebSocketComponent() {
     };
 
     socket.onmessage = (event) => {
-      priceRef.current = event.data;
-      setPrice((prevPrice) => (!prevPrice ? event.data : prevPrice));
+      const message = JSON.parse(event.data);
+      if (message.type === "error") {
+        console.error(message.message);
+        return;
+      }
+      if (message.type !== "price") {
+        return;
+      }
+      priceRef.current = `${message.ticker}: ${message.price}`;
+      setPrice((prevPrice) => (!prevPrice ? priceRef.current : prevPrice));
     };
 
     socket.onerror = (event) => {